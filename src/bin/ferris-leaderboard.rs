@@ -0,0 +1,14 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+extern crate ferris;
+
+/// Serves the online leaderboard, keeping runs in SQLite. See the
+/// README for the options.
+pub fn main() {
+    ferris::game::leaderboard_server::main();
+}
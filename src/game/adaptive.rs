@@ -0,0 +1,179 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+use game::LOG_ADAPTIVE_DIFFICULTY;
+use std;
+
+/// How far back player performance is looked at (in ms).
+const WINDOW: u64 = 20_000;
+/// Time between adjustments (in ms).
+const ADJUST_PERIOD: u64 = 5_000;
+/// How much the level changes in one adjustment.
+const STEP: f32 = 0.05;
+const MIN_LEVEL: f32 = 0.7;
+const MAX_LEVEL: f32 = 1.4;
+/// Getting hit this many times within the window eases off.
+const HITS_TO_EASE: usize = 2;
+/// Kills within the window, without getting hit, that push harder.
+const KILLS_TO_PUSH: usize = 15;
+/// Grazes within the window, without getting hit, that push harder.
+const GRAZES_TO_PUSH: usize = 10;
+
+/// Something the player did that says how well they are doing.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+pub enum Sample {
+    Hit,
+    Kill,
+    Graze,
+}
+
+/// Optional mode that watches how the player is doing and nudges the
+/// spawn and fire rates up or down to match.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AdaptiveDifficulty {
+    /// The mode is picked on the title screen and stays off by default.
+    pub enabled: bool,
+    /// How much faster than the preset enemies spawn and shoot.
+    level: f32,
+    /// Time played this run (in ms).
+    clock_ms: u64,
+    next_adjust_ms: u64,
+    /// Recent samples and when they happened.
+    samples: std::collections::VecDeque<(u64, Sample)>,
+}
+
+impl AdaptiveDifficulty {
+    /// Disabled, at the preset's own pace.
+    pub fn new() -> AdaptiveDifficulty {
+        AdaptiveDifficulty {
+            enabled: false,
+            level: 1.0,
+            clock_ms: 0,
+            next_adjust_ms: ADJUST_PERIOD,
+            samples: std::collections::VecDeque::new(),
+        }
+    }
+
+    /// Starts a new run, keeping whether the mode is enabled.
+    pub fn reset(&mut self) {
+        *self = AdaptiveDifficulty {
+            enabled: self.enabled,
+            ..AdaptiveDifficulty::new()
+        };
+    }
+
+    /// Spawn and fire rates are multiplied by this. Always 1 when disabled.
+    pub fn level(&self) -> f32 {
+        if self.enabled {
+            self.level
+        } else {
+            1.0
+        }
+    }
+
+    /// Notes something the player did.
+    pub fn record(&mut self, sample: Sample) {
+        if self.enabled {
+            self.samples.push_back((self.clock_ms, sample));
+        }
+    }
+
+    /// Times a kind of sample happened within the window.
+    pub fn count(&self, sample: Sample) -> usize {
+        self.samples.iter().filter(|&&(_, s)| s == sample).count()
+    }
+
+    /// Moves the clock on, adjusting the level every few seconds.
+    pub fn update(&mut self, delta_ms: u64) {
+        if !self.enabled {
+            return;
+        }
+        self.clock_ms += delta_ms;
+        while let Some(&(time, _)) = self.samples.front() {
+            if time + WINDOW > self.clock_ms {
+                break;
+            }
+            self.samples.pop_front();
+        }
+        if self.clock_ms < self.next_adjust_ms {
+            return;
+        }
+        self.next_adjust_ms = self.clock_ms + ADJUST_PERIOD;
+
+        let (hits, kills, grazes) = (self.count(Sample::Hit), self.count(Sample::Kill), self.count(Sample::Graze));
+        let old = self.level;
+        if hits >= HITS_TO_EASE {
+            self.level = (self.level - STEP).max(MIN_LEVEL);
+        } else if hits == 0 && (kills >= KILLS_TO_PUSH || grazes >= GRAZES_TO_PUSH) {
+            self.level = (self.level + STEP).min(MAX_LEVEL);
+        }
+
+        if LOG_ADAPTIVE_DIFFICULTY && self.level != old {
+            println!(
+                "[{:>4}s] adaptive difficulty {:.2} -> {:.2} (hits {}, kills {}, grazes {} in the last {}s)",
+                self.clock_ms / 1000, old, self.level, hits, kills, grazes, WINDOW / 1000
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled() -> AdaptiveDifficulty {
+        let mut adaptive = AdaptiveDifficulty::new();
+        adaptive.enabled = true;
+        adaptive
+    }
+
+    #[test]
+    fn disabled_mode_never_adjusts() {
+        let mut adaptive = AdaptiveDifficulty::new();
+        for _ in 0..KILLS_TO_PUSH {
+            adaptive.record(Sample::Kill);
+        }
+        adaptive.update(ADJUST_PERIOD);
+        assert_eq!(adaptive.level(), 1.0);
+    }
+
+    #[test]
+    fn doing_well_pushes_and_getting_hit_eases() {
+        let mut adaptive = enabled();
+        for _ in 0..KILLS_TO_PUSH {
+            adaptive.record(Sample::Kill);
+        }
+        adaptive.update(ADJUST_PERIOD);
+        assert_eq!(adaptive.level(), 1.0 + STEP);
+
+        adaptive.record(Sample::Hit);
+        adaptive.record(Sample::Hit);
+        adaptive.update(ADJUST_PERIOD);
+        assert!((adaptive.level() - 1.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn old_samples_fall_out_of_the_window() {
+        let mut adaptive = enabled();
+        adaptive.record(Sample::Hit);
+        adaptive.update(WINDOW - 1);
+        assert_eq!(adaptive.count(Sample::Hit), 1);
+        adaptive.update(1);
+        assert_eq!(adaptive.count(Sample::Hit), 0);
+    }
+
+    #[test]
+    fn level_stays_in_bounds() {
+        let mut adaptive = enabled();
+        for _ in 0..100 {
+            adaptive.record(Sample::Hit);
+            adaptive.record(Sample::Hit);
+            adaptive.update(ADJUST_PERIOD);
+        }
+        assert_eq!(adaptive.level(), MIN_LEVEL);
+    }
+}
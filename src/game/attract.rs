@@ -0,0 +1,138 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+/// How long the logo and settings stay up while nobody touches anything (in ms).
+const TITLE_MS: u64 = 10_000;
+/// How long the high-score table is shown (in ms).
+const SCORES_MS: u64 = 8_000;
+/// How long the online leaderboard is shown (in ms).
+const ONLINE_MS: u64 = 8_000;
+/// How long the bot plays the demo for, unless it dies first (in ms).
+const DEMO_MS: u64 = 40_000;
+/// How long the stats page stays up without a key press (in ms).
+const STATS_MS: u64 = 30_000;
+/// How long the high-score browser stays up without a key press (in ms).
+const RECORDS_MS: u64 = 60_000;
+/// How far into a run the demo starts, so there's plenty going on.
+pub const DEMO_START_MS: u64 = 90_000;
+
+/// The screens the title cycles through while it sits idle.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Screen {
+    /// The logo and the mode settings
+    Title,
+    /// The high-score table of the selected mode
+    Scores,
+    /// The online leaderboard of the selected mode. Skipped when the game
+    /// wasn't given a server.
+    Online,
+    /// The bot playing a game
+    Demo,
+    /// The user's lifetime stats and achievements, shown when asked for
+    Stats,
+    /// Every high score, paged and filtered, shown when asked for
+    Records,
+}
+
+/// Attract mode: keeps the title screen moving while nobody is playing,
+/// and goes back to the logo as soon as anyone presses something.
+pub struct Attract {
+    pub screen: Screen,
+    /// Time spent on the current screen (in ms).
+    timer_ms: u64,
+}
+
+impl Attract {
+    pub fn new() -> Attract {
+        Attract {
+            screen: Screen::Title,
+            timer_ms: 0,
+        }
+    }
+
+    /// Counts down the current screen. Returns true when it moves on to the
+    /// next one.
+    pub fn update(&mut self, delta_ms: u64) -> bool {
+        self.timer_ms += delta_ms;
+        let duration = match self.screen {
+            Screen::Title => TITLE_MS,
+            Screen::Scores => SCORES_MS,
+            Screen::Online => ONLINE_MS,
+            Screen::Demo => DEMO_MS,
+            Screen::Stats => STATS_MS,
+            Screen::Records => RECORDS_MS,
+        };
+        if self.timer_ms < duration {
+            return false;
+        }
+        self.next();
+        true
+    }
+
+    /// Moves on to the next screen straight away, e.g. once every player
+    /// in the demo is out.
+    pub fn next(&mut self) {
+        self.screen = match self.screen {
+            Screen::Title => Screen::Scores,
+            Screen::Scores => Screen::Online,
+            Screen::Online => Screen::Demo,
+            Screen::Demo | Screen::Stats | Screen::Records => Screen::Title,
+        };
+        self.timer_ms = 0;
+    }
+
+    /// Switches to a screen straight away, e.g. the stats page when asked for.
+    pub fn show(&mut self, screen: Screen) {
+        self.screen = screen;
+        self.timer_ms = 0;
+    }
+
+    /// Goes back to the title after a key press. Returns true if it wasn't
+    /// already showing, in which case the key press is used up.
+    pub fn wake(&mut self) -> bool {
+        let woken = self.screen != Screen::Title;
+        *self = Attract::new();
+        woken
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycles_while_idle_and_wakes_on_a_key() {
+        let mut attract = Attract::new();
+        assert!(!attract.update(TITLE_MS - 1));
+        assert!(attract.update(1));
+        assert_eq!(attract.screen, Screen::Scores);
+        assert!(attract.update(SCORES_MS));
+        assert_eq!(attract.screen, Screen::Online);
+        assert!(attract.update(ONLINE_MS));
+        assert_eq!(attract.screen, Screen::Demo);
+
+        // A key press during the demo goes back to the title
+        assert!(attract.wake());
+        assert_eq!(attract.screen, Screen::Title);
+
+        // On the title it just restarts the wait
+        attract.update(TITLE_MS - 1);
+        assert!(!attract.wake());
+        assert!(!attract.update(TITLE_MS - 1));
+
+        // The stats page stays until a key press, or goes back on its own
+        attract.show(Screen::Stats);
+        assert!(attract.wake());
+        attract.show(Screen::Stats);
+        assert!(attract.update(STATS_MS));
+        assert_eq!(attract.screen, Screen::Title);
+        attract.show(Screen::Records);
+        assert!(!attract.update(STATS_MS));
+        assert!(attract.update(RECORDS_MS));
+        assert_eq!(attract.screen, Screen::Title);
+    }
+}
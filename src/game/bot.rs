@@ -0,0 +1,250 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+//! A bot that plays the game by filling in a player's `Input` from the
+//! state of the world, for demos and for soak testing the simulation.
+
+use game::Input;
+use game::components::Movement;
+use game::entity::EntityType;
+use game::status::StatusKind;
+use game::systems::{hitbox_bounds, overlapping, SPEED_BOOST_FACTOR};
+use game::world::World;
+use ggez::graphics::Rect;
+use std;
+
+/// How far ahead the bot looks for trouble (in ms).
+const LOOK_AHEAD_MS: f32 = 600.0;
+/// Time between the points the bot checks along the way (in ms).
+const LOOK_AHEAD_STEP_MS: f32 = 50.0;
+/// Room the bot tries to keep between its hitbox and any threat.
+const DODGE_MARGIN: f32 = 12.0;
+/// Distance from the bottom of the screen the bot likes to hang back at.
+const HOME_HEIGHT: f32 = 140.0;
+/// A hit this many checks away or sooner can't be dodged, so the bot bombs.
+const PANIC_STEPS: usize = 2;
+
+/// The eight directions and standing still, as (x, y) steps.
+const MOVES: [(i32, i32); 9] = [
+    (0, 0), (-1, 0), (1, 0), (0, -1), (0, 1),
+    (-1, -1), (1, -1), (-1, 1), (1, 1),
+];
+
+/// Something that hurts on contact, and how it moves.
+struct Threat {
+    bounds: Rect,
+    /// Velocity in pixels per ms. Only straight-line movement can be
+    /// predicted, so anything else is treated as standing still.
+    velocity: (f32, f32),
+}
+
+/// Works out what the given player should press this update. Every move
+/// is played out over the next `LOOK_AHEAD_MS`, checking it against where
+/// the enemies and their bullets will be, and the safest is picked. Safe
+/// moves are broken by heading for pickups, or lining up under enemies to
+/// shoot them.
+pub fn think(world: &World, slot: usize) -> Input {
+    let player = &world.players[slot];
+    let id = match player.entity {
+        Some(id) => id,
+        None => return Input::default(),
+    };
+    let bounds = match hitbox_bounds(world, id) {
+        Some(bounds) => bounds,
+        None => return Input::default(),
+    };
+    let mut speed = match world.velocities.get(id).map(|v| v.movement) {
+        Some(Movement::Controlled(speed)) => speed / 1000.0,
+        _ => 0.0,
+    };
+    if player.status.has(StatusKind::SpeedBoost) {
+        speed *= SPEED_BOOST_FACTOR;
+    }
+
+    let threats = threats(world);
+    let goal = goal(world, &bounds);
+
+    // Pick the move that gets hit the least and the latest, then the one
+    // that ends up closest to the goal
+    let mut best = (0, 0);
+    let (mut best_danger, mut best_hit, mut best_distance) = (usize::MAX, 0, f32::MAX);
+    for &(dx, dy) in MOVES.iter() {
+        let (danger, first_hit) = danger(world, &bounds, &threats, (dx, dy), speed);
+        let step = speed * LOOK_AHEAD_STEP_MS * if dx != 0 && dy != 0 { 0.707 } else { 1.0 };
+        let x = bounds.x + bounds.w / 2.0 + dx as f32 * step;
+        let y = bounds.y + bounds.h / 2.0 + dy as f32 * step;
+        let distance = (goal.0 - x).powi(2) + (goal.1 - y).powi(2);
+        let better = danger < best_danger
+            || (danger == best_danger && first_hit > best_hit)
+            || (danger == best_danger && first_hit == best_hit && distance < best_distance);
+        if better {
+            best = (dx, dy);
+            best_danger = danger;
+            best_hit = first_hit;
+            best_distance = distance;
+        }
+    }
+
+    let enemies = world.kinds.iter().any(|(_, &kind)| is_enemy(kind));
+    Input {
+        left: best.0 < 0,
+        right: best.0 > 0,
+        up: best.1 < 0,
+        down: best.1 > 0,
+        shoot: enemies,
+        bomb: best_hit <= PANIC_STEPS && player.bombs > 0 && player.invulnerable_ms <= 0,
+    }
+}
+
+fn is_enemy(kind: EntityType) -> bool {
+    matches!(kind, EntityType::Enemy | EntityType::EnemyBlueScreen | EntityType::Boss | EntityType::Special)
+}
+
+/// Everything that would hurt a player on contact.
+fn threats(world: &World) -> Vec<Threat> {
+    let mut threats = vec![];
+    for (id, &kind) in world.kinds.iter() {
+        if !is_enemy(kind) && kind != EntityType::EnemyBullet {
+            continue;
+        }
+        let bounds = match hitbox_bounds(world, id) {
+            Some(bounds) => bounds,
+            None => continue,
+        };
+        let velocity = match world.velocities.get(id).map(|v| v.movement) {
+            Some(Movement::Linear(x, y)) => (x / 1000.0, y / 1000.0),
+            _ => (0.0, 0.0),
+        };
+        threats.push(Threat { bounds, velocity });
+    }
+    threats
+}
+
+/// Where the bot would like to be: on the nearest pickup, or else at the
+/// bottom of the screen under the nearest enemy.
+fn goal(world: &World, bounds: &Rect) -> (f32, f32) {
+    let (x, y) = (bounds.x + bounds.w / 2.0, bounds.y + bounds.h / 2.0);
+    let center = |id| hitbox_bounds(world, id).map(|b| (b.x + b.w / 2.0, b.y + b.h / 2.0));
+
+    let pickups = world.pickups.iter().filter_map(|(id, _)| center(id)).collect();
+    if let Some(pickup) = closest(pickups, |p| (p.0 - x).powi(2) + (p.1 - y).powi(2)) {
+        return pickup;
+    }
+    let home = world.screen_height - HOME_HEIGHT;
+    let enemies = world.kinds.iter()
+        .filter(|&(_, &kind)| is_enemy(kind))
+        .filter_map(|(id, _)| center(id))
+        .filter(|e| e.1 < home)
+        .collect();
+    match closest(enemies, |e| (e.0 - x).abs()) {
+        Some(enemy) => (enemy.0, home),
+        None => (world.screen_width / 2.0, home),
+    }
+}
+
+/// The point with the smallest distance.
+fn closest<F: Fn(&(f32, f32)) -> f32>(points: Vec<(f32, f32)>, distance: F) -> Option<(f32, f32)> {
+    points.into_iter().min_by(|a, b| {
+        distance(a).partial_cmp(&distance(b)).unwrap_or(std::cmp::Ordering::Equal)
+    })
+}
+
+/// Plays a move out over the look-ahead, returning how much trouble it
+/// runs into (sooner hits count for more) and the check of the first hit.
+fn danger(world: &World, bounds: &Rect, threats: &[Threat], (dx, dy): (i32, i32), speed: f32) -> (usize, usize) {
+    let steps = (LOOK_AHEAD_MS / LOOK_AHEAD_STEP_MS) as usize;
+    let speed = if dx != 0 && dy != 0 { speed * 0.707 } else { speed };
+    let mut danger = 0;
+    let mut first_hit = usize::MAX;
+    for step in 1..steps + 1 {
+        let t = step as f32 * LOOK_AHEAD_STEP_MS;
+        let player = Rect {
+            x: (bounds.x + dx as f32 * speed * t).max(0.0).min(world.screen_width - bounds.w) - DODGE_MARGIN,
+            y: (bounds.y + dy as f32 * speed * t).max(0.0).min(world.screen_height - bounds.h) - DODGE_MARGIN,
+            w: bounds.w + DODGE_MARGIN * 2.0,
+            h: bounds.h + DODGE_MARGIN * 2.0,
+        };
+        for threat in threats {
+            let at = Rect {
+                x: threat.bounds.x + threat.velocity.0 * t,
+                y: threat.bounds.y + threat.velocity.1 * t,
+                ..threat.bounds
+            };
+            if overlapping(&player, &at) {
+                danger += steps + 1 - step;
+                first_hit = first_hit.min(step);
+            }
+        }
+    }
+    (danger, first_hit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::play_mode::PlayMode;
+
+    fn new_world() -> World {
+        let mut world = World::new(1024, 768);
+        world.reset();
+        world.spawner.cooldowns.clear();
+        world
+    }
+
+    #[test]
+    fn steps_out_of_the_way_of_a_bullet() {
+        let mut world = new_world();
+        let crab = hitbox_bounds(&world, world.players[0].entity.unwrap()).unwrap();
+
+        // A bullet falling straight onto the crab
+        let bullet = world.spawner.spawn_enemy_bullet(crab.x - 8.0, crab.y - 200.0, -std::f32::consts::PI / 2.0);
+        world.insert(bullet);
+        let input = think(&world, 0);
+        assert!(input.left || input.right);
+
+        // With nothing around it heads for the enemy to shoot it
+        let mut world = new_world();
+        let enemy = world.spawner.spawn_enemy(0.0, "SEGFAULT", EntityType::Enemy);
+        world.insert(enemy);
+        let input = think(&world, 0);
+        assert!(input.shoot);
+        assert!(!input.bomb);
+    }
+
+    /// Plays seeded games of every mode to the end, or until the time runs
+    /// out, so that anything that panics along the way shows up.
+    fn soak(seeds: std::ops::Range<u32>, max_ms: u64) {
+        for seed in seeds {
+            for &mode in PlayMode::all().iter() {
+                let mut world = World::new(1024, 768);
+                world.play_mode = mode;
+                world.player_count = 1 + seed as usize % 2;
+                world.seed(seed);
+                world.reset();
+                let mut elapsed_ms = 0;
+                while elapsed_ms < max_ms && world.player_alive() && !world.won(elapsed_ms) {
+                    let inputs: Vec<Input> = (0..world.players.len()).map(|slot| think(&world, slot)).collect();
+                    elapsed_ms += 16;
+                    world.update(&inputs, elapsed_ms, 16);
+                    world.events.clear();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn plays_seeded_games_without_panicking() {
+        soak(0..2, 60_000);
+    }
+
+    /// Thousands of full games; run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn soak_test() {
+        soak(0..2_000, 20 * 60_000);
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+/// Time allowed between kills to keep a combo going (in ms).
+pub const COMBO_WINDOW: i64 = 2_000;
+/// Kills needed to raise the combo multiplier by one.
+const KILLS_PER_LEVEL: u32 = 5;
+const MAX_COMBO_MULTIPLIER: i32 = 8;
+
+/// Tracks chains of kills made in quick succession.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Combo {
+    /// Kills in the current chain.
+    pub count: u32,
+    /// Time left to make the next kill before the chain breaks (in ms).
+    pub timer_ms: i64,
+    /// Longest chain of the run.
+    pub max: u32,
+}
+
+impl Combo {
+    /// No chain yet.
+    pub fn new() -> Combo {
+        Combo {
+            count: 0,
+            timer_ms: 0,
+            max: 0,
+        }
+    }
+
+    /// Adds a kill to the chain and restarts the timer.
+    pub fn add_kill(&mut self) {
+        self.count += 1;
+        self.timer_ms = COMBO_WINDOW;
+        if self.count > self.max {
+            self.max = self.count;
+        }
+    }
+
+    /// Breaks the current chain.
+    pub fn reset(&mut self) {
+        self.count = 0;
+        self.timer_ms = 0;
+    }
+
+    /// Counts down the timer, breaking the chain when it runs out.
+    pub fn update(&mut self, delta_ms: u64) {
+        if self.count == 0 {
+            return;
+        }
+        self.timer_ms -= delta_ms as i64;
+        if self.timer_ms <= 0 {
+            self.reset();
+        }
+    }
+
+    /// What kill scores are multiplied by. It goes up by one every five
+    /// kills in the chain.
+    pub fn multiplier(&self) -> i32 {
+        let multiplier = 1 + (self.count / KILLS_PER_LEVEL) as i32;
+        if multiplier > MAX_COMBO_MULTIPLIER {
+            MAX_COMBO_MULTIPLIER
+        } else {
+            multiplier
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quick_kills_raise_the_multiplier() {
+        let mut combo = Combo::new();
+        for _ in 0..KILLS_PER_LEVEL {
+            assert_eq!(combo.multiplier(), 1);
+            combo.add_kill();
+            combo.update(COMBO_WINDOW as u64 - 1);
+        }
+        assert_eq!(combo.multiplier(), 2);
+        assert_eq!(combo.max, KILLS_PER_LEVEL);
+    }
+
+    #[test]
+    fn chains_break_when_the_window_closes() {
+        let mut combo = Combo::new();
+        combo.add_kill();
+        combo.add_kill();
+        combo.update(COMBO_WINDOW as u64);
+        assert_eq!(combo.count, 0);
+        assert_eq!(combo.max, 2);
+    }
+}
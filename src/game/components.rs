@@ -0,0 +1,235 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+extern crate ggez;
+extern crate rand;
+
+use self::rand::Rng;
+use ggez::graphics;
+use game::entity::EntityType;
+use game::entity_store::EntityId;
+use game::rng::GameRng;
+use game::status::StatusKind;
+use game::weapons::WeaponKind;
+
+/// Position and orientation of an entity on screen.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transform {
+    pub x: f32,
+    pub y: f32,
+    pub angle: f32,
+}
+
+impl Transform {
+    /// Creates an unrotated transform at the given position.
+    pub fn at(x: f32, y: f32) -> Transform {
+        Transform { x, y, angle: 0.0 }
+    }
+
+    /// This function moves an entity around by the pixels specified.
+    /// # Example
+    /// ```ignore
+    /// let mut t = Transform::at(10.0, 10.0);
+    /// t.translate(1.0, -1.0);
+    /// assert!(t.x == 11.0 && t.y == 9.0);
+    /// ```
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        self.x += dx;
+        self.y += dy;
+    }
+}
+
+/// An entity with a velocity has one of three movement types.
+/// Static entities (text/effects) simply have no `Velocity`.
+/// - Linear: The entity has a constant x and y velocity.
+/// - Generated: The entity follows a `Pattern`, which works out an x and y
+///   velocity every time it updates from the ms elapsed since the entity
+///   spawned, a random number generator and a unique seed value between
+///   -1.0 and 1.0.
+/// - Controlled: The entity is steered by the player input at the given
+///   speed in pixels per second, and is kept inside the window.
+/// - Homing: The entity has an x and y velocity that turns towards the
+///   nearest enemy every update.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Movement {
+    Linear(f32, f32),
+    Generated(Pattern),
+    Controlled(f32),
+    Homing(f32, f32),
+}
+
+/// The ways enemies weave down the screen.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Pattern {
+    /// Swings side to side with a random jitter, bobbing downwards.
+    Bug,
+    /// Like a bug, but falls more slowly.
+    Special,
+    /// Shakes quickly from side to side, drifting down at random speeds.
+    BlueScreen,
+    /// Sways slowly side to side, sinking at a steady pace.
+    Boss,
+}
+
+impl Pattern {
+    /// Velocity in pixels per second, `time` ms after the entity spawned.
+    pub fn velocity(&self, time: u64, rng: &mut GameRng, seed: f64) -> (f32, f32) {
+        let t = time as f64;
+        match *self {
+            Pattern::Bug | Pattern::Special => {
+                let fall = if *self == Pattern::Bug { 80_f32 } else { 60_f32 };
+                (
+                    ((t / 1000.0 + seed * 1000.0).sin() + rng.gen_range(-3.0, 3.0)) as f32 * 60_f32,
+                    (1.0 + (t / 900.0 + seed * 100.0).sin()) as f32 * fall,
+                )
+            }
+            Pattern::BlueScreen => (
+                ((t / 1000.0 + seed * 300.0).sin() + t.sin() * 2_f64) as f32 * 60_f32,
+                (1.0 + (t / 900.0 + seed * 100.0).sin() + rng.gen_range(0.1, 3.0)) as f32 * 20_f32,
+            ),
+            Pattern::Boss => ((t / 1000.0 + seed * 1000.0).sin() as f32 * 60.0, 35.0),
+        }
+    }
+}
+
+/// How an entity moves and spins over time.
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct Velocity {
+    pub movement: Movement,
+    /// Unique seed handed to `Movement::Generated` patterns.
+    pub seed: f64,
+    /// Time since the entity spawned (in ms).
+    pub timer: u64,
+    /// Rotation speed in radians per ms.
+    pub spin: f32,
+}
+
+impl Velocity {
+    /// A velocity that only uses the given movement.
+    pub fn new(movement: Movement) -> Velocity {
+        Velocity {
+            movement,
+            seed: 0.0,
+            timer: 0,
+            spin: 0.0,
+        }
+    }
+}
+
+/// Hit points of anything that can be destroyed by damage.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Health {
+    pub hp: i32,
+    /// Points awarded to the player when this entity is killed.
+    pub bounty: i32,
+}
+
+/// The different ways a weapon can spray bullets.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FirePattern {
+    /// The player's gun, firing whatever is selected in the `Arsenal`.
+    Player,
+    /// A single bullet straight down.
+    Down,
+    /// Three bullets spread downward.
+    TripleSpread,
+    /// A ring of bullets around the entity that rotates with it.
+    Ring(i64),
+}
+
+/// Anything that shoots bullets.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Weapon {
+    pub pattern: FirePattern,
+    /// Time remaining until the weapon can fire again (in ms).
+    pub cooldown: i64,
+    /// Cooldown applied after every shot (in ms). The player's weapon
+    /// uses the cooldown of its current gun instead.
+    pub period: i64,
+    /// Where bullets leave the entity, relative to its position.
+    pub muzzle: (f32, f32),
+    /// Distance from the muzzle at which bullets appear, along their angle.
+    pub radius: f32,
+}
+
+/// Collision bounds of an entity, relative to its position, and the
+/// damage it deals on contact.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hitbox {
+    #[serde(with = "RectDef")]
+    pub bounds: graphics::Rect,
+    pub damage: i32,
+}
+
+/// Lets serde save the ggez `Rect` in a hitbox.
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "graphics::Rect")]
+struct RectDef {
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+}
+
+/// Used to specify the lifetime of an entity.
+/// Those with `Forever` will never expire, while
+/// those with a `Milliseconds()` value will be
+/// culled after that many milliseconds have elapsed.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Lifetime {
+    Forever,
+    Milliseconds(i64),
+}
+
+/// What to draw for an entity. Textures are looked up by `texture`,
+/// and entities with a `label` get a name tag drawn next to them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sprite {
+    pub texture: EntityType,
+    pub label: Option<String>,
+}
+
+/// Lets a player projectile pass through enemies instead of dying on
+/// the first hit. Each enemy is only damaged once.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Piercing {
+    /// How many more enemies it can hit, or `None` for no limit.
+    pub hits_left: Option<u32>,
+    /// Enemies that have already been hit.
+    pub hit: Vec<EntityId>,
+}
+
+/// An expanding shockwave from a player bomb. Everything it reaches is
+/// hit once: enemies and bullets are destroyed, bosses take damage.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Blast {
+    pub radius: f32,
+    pub max_radius: f32,
+    /// Growth of the radius in pixels per second.
+    pub speed: f32,
+    /// Entities the blast has already reached.
+    pub hit: Vec<EntityId>,
+}
+
+/// The different items the player can collect.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PickupKind {
+    Powerbomb,
+    GunUpgrade,
+    Weapon(WeaponKind),
+    Status(StatusKind),
+}
+
+/// Marks an entity as an item the player can collect.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pickup {
+    pub kind: PickupKind,
+}
+
+/// Marks a tougher variant of an enemy, spawned as endless runs escalate.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Elite;
@@ -0,0 +1,244 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+use game::{ENEMY_BULLET_SPEED, MAX_DIFFICULTY, SECONDS_UNTIL_MAX_DIFFICULTY};
+
+/// How hard the game is.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    Insane,
+}
+
+/// How the spawn rate ramps up over the course of a run.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Curve {
+    /// Ramps up at a steady pace.
+    Linear,
+    /// Starts gently and ramps up faster towards the end.
+    EaseIn,
+    /// Ramps up quickly, then levels off.
+    EaseOut,
+}
+
+/// The gameplay parameters for a difficulty.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    /// How often enemies spawn, relative to normal.
+    pub spawn_rate: f32,
+    /// Enemy hit points, relative to normal.
+    pub enemy_hp: f32,
+    /// Speed of enemy bullets (in pixels per second).
+    pub bullet_speed: f32,
+    /// Time between enemy shots, relative to normal.
+    pub fire_cooldown: f32,
+    /// Hit points the player starts with.
+    pub lives: i32,
+    /// Time it takes to reach the highest spawn rate, which is also how
+    /// long the player has to survive to win (in seconds).
+    pub ramp_seconds: u64,
+    /// Spawn cooldowns are never scaled below this fraction.
+    pub min_cooldown: f32,
+    /// Shape of the ramp.
+    pub curve: Curve,
+}
+
+impl Preset {
+    /// How much to scale spawn cooldowns by at this point in the run.
+    pub fn cooldown_factor(&self, elapsed_ms: u64) -> f32 {
+        let progress = (elapsed_ms as f32 / 1000.0 / self.ramp_seconds as f32).min(1.0);
+        let ramp = match self.curve {
+            Curve::Linear => progress,
+            Curve::EaseIn => progress * progress,
+            Curve::EaseOut => 1.0 - (1.0 - progress) * (1.0 - progress),
+        };
+        (1.0 - ramp).max(self.min_cooldown) / self.spawn_rate
+    }
+
+    /// Scales a base hit point value, never going below one.
+    pub fn scale_hp(&self, hp: i32) -> i32 {
+        ((hp as f32 * self.enemy_hp).round() as i32).max(1)
+    }
+
+    /// Scales the time between shots of an enemy weapon.
+    pub fn scale_fire_cooldown(&self, cooldown: i64) -> i64 {
+        (cooldown as f32 * self.fire_cooldown) as i64
+    }
+}
+
+/// How the game reacts when the player gets hit.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HitFeedback {
+    /// Time after a hit during which the player can't be hurt (in ms).
+    pub invulnerability_ms: i64,
+    /// Speed the player is pushed away from what hit them (in pixels per second).
+    pub knockback: f32,
+    /// How long the screen shakes for (in ms).
+    pub shake_ms: i64,
+    /// How far the screen shakes (in pixels).
+    pub shake_intensity: f32,
+    /// How long the game freezes on a hit (in ms).
+    pub hitstop_ms: i64,
+}
+
+impl Difficulty {
+    /// Every difficulty, from easiest to hardest.
+    pub fn all() -> [Difficulty; 4] {
+        [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard, Difficulty::Insane]
+    }
+
+    /// Name shown on the title screen.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Difficulty::Easy => "EASY",
+            Difficulty::Normal => "NORMAL",
+            Difficulty::Hard => "HARD",
+            Difficulty::Insane => "INSANE",
+        }
+    }
+
+    /// The next harder difficulty, wrapping around to the easiest.
+    pub fn next(&self) -> Difficulty {
+        match *self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Insane,
+            Difficulty::Insane => Difficulty::Easy,
+        }
+    }
+
+    /// The next easier difficulty, wrapping around to the hardest.
+    pub fn previous(&self) -> Difficulty {
+        match *self {
+            Difficulty::Easy => Difficulty::Insane,
+            Difficulty::Normal => Difficulty::Easy,
+            Difficulty::Hard => Difficulty::Normal,
+            Difficulty::Insane => Difficulty::Hard,
+        }
+    }
+
+    /// Gameplay parameters for this difficulty.
+    pub fn preset(&self) -> Preset {
+        match *self {
+            Difficulty::Easy => Preset {
+                spawn_rate: 0.75,
+                enemy_hp: 0.75,
+                bullet_speed: 300.0,
+                fire_cooldown: 1.4,
+                lives: 7,
+                ramp_seconds: SECONDS_UNTIL_MAX_DIFFICULTY,
+                min_cooldown: 0.25,
+                curve: Curve::EaseIn,
+            },
+            Difficulty::Normal => Preset {
+                spawn_rate: 1.0,
+                enemy_hp: 1.0,
+                bullet_speed: ENEMY_BULLET_SPEED,
+                fire_cooldown: 1.0,
+                lives: 5,
+                ramp_seconds: SECONDS_UNTIL_MAX_DIFFICULTY,
+                min_cooldown: MAX_DIFFICULTY,
+                curve: Curve::Linear,
+            },
+            Difficulty::Hard => Preset {
+                spawn_rate: 1.25,
+                enemy_hp: 1.5,
+                bullet_speed: 500.0,
+                fire_cooldown: 0.8,
+                lives: 4,
+                ramp_seconds: 6 * 60,
+                min_cooldown: 0.12,
+                curve: Curve::Linear,
+            },
+            Difficulty::Insane => Preset {
+                spawn_rate: 1.5,
+                enemy_hp: 2.0,
+                bullet_speed: 600.0,
+                fire_cooldown: 0.6,
+                lives: 3,
+                ramp_seconds: 5 * 60,
+                min_cooldown: 0.1,
+                curve: Curve::EaseOut,
+            },
+        }
+    }
+
+    /// Hit feedback tuned for this difficulty. Harder settings give
+    /// shorter invulnerability windows.
+    pub fn hit_feedback(&self) -> HitFeedback {
+        match *self {
+            Difficulty::Easy => HitFeedback {
+                invulnerability_ms: 2_000,
+                knockback: 500.0,
+                shake_ms: 300,
+                shake_intensity: 8.0,
+                hitstop_ms: 80,
+            },
+            Difficulty::Normal => HitFeedback {
+                invulnerability_ms: 1_500,
+                knockback: 600.0,
+                shake_ms: 300,
+                shake_intensity: 10.0,
+                hitstop_ms: 80,
+            },
+            Difficulty::Hard => HitFeedback {
+                invulnerability_ms: 1_000,
+                knockback: 700.0,
+                shake_ms: 350,
+                shake_intensity: 12.0,
+                hitstop_ms: 60,
+            },
+            Difficulty::Insane => HitFeedback {
+                invulnerability_ms: 600,
+                knockback: 800.0,
+                shake_ms: 400,
+                shake_intensity: 14.0,
+                hitstop_ms: 40,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normal_ramps_linearly_to_its_floor() {
+        let preset = Difficulty::Normal.preset();
+        let ramp_ms = preset.ramp_seconds * 1000;
+        assert_eq!(preset.cooldown_factor(0), 1.0);
+        assert_eq!(preset.cooldown_factor(ramp_ms / 2), 0.5);
+        assert_eq!(preset.cooldown_factor(ramp_ms), MAX_DIFFICULTY);
+        assert_eq!(preset.cooldown_factor(ramp_ms * 2), MAX_DIFFICULTY);
+    }
+
+    #[test]
+    fn curves_change_the_pace_but_not_the_ends() {
+        let mut preset = Difficulty::Normal.preset();
+        let half = preset.ramp_seconds * 500;
+        preset.curve = Curve::EaseIn;
+        assert!(preset.cooldown_factor(half) > 0.5);
+        preset.curve = Curve::EaseOut;
+        assert!(preset.cooldown_factor(half) < 0.5);
+        assert_eq!(preset.cooldown_factor(0), 1.0);
+        assert_eq!(preset.cooldown_factor(preset.ramp_seconds * 1000), MAX_DIFFICULTY);
+    }
+
+    #[test]
+    fn harder_presets_are_harder() {
+        let presets: Vec<Preset> = Difficulty::all().iter().map(|d| d.preset()).collect();
+        for pair in presets.windows(2) {
+            assert!(pair[0].spawn_rate < pair[1].spawn_rate);
+            assert!(pair[0].bullet_speed < pair[1].bullet_speed);
+            assert!(pair[0].lives > pair[1].lives);
+        }
+        assert_eq!(presets[0].scale_hp(1), 1);
+    }
+}
@@ -7,33 +7,74 @@
 
 extern crate ggez;
 extern crate rand;
-use ggez::{graphics, Context};
+use ggez::graphics;
 use self::rand::Rng;
-use game::entity::{Lifetime, Movement, Entity, EntityType};
-use game::{ENEMY_NAMES, ENEMY_BULLET_SPEED, PLAYER_BULLET_SPEED, SPLAT_LIFETIME, SHUTOFF_LIFETIME, ENEMY_LIFETIME, SECONDS_UNTIL_MAX_DIFFICULTY, MAX_DIFFICULTY};
+use game::components::{Blast, Elite, FirePattern, Health, Hitbox, Lifetime, Movement, Pattern, Pickup, PickupKind, Piercing, Sprite, Transform, Velocity, Weapon};
+use game::difficulty::{Difficulty, Preset};
+use game::entity::{Entity, EntityType};
+use game::play_mode::EnemySet;
+use game::rng::GameRng;
+use game::status::StatusKind;
+use game::weapons::WeaponKind;
+use game::{ENEMY_NAMES, PLAYER_BULLET_SPEED, SPLAT_LIFETIME, SHUTOFF_LIFETIME, ENEMY_LIFETIME};
+use game::{BOMB_BLAST_RADIUS, BOMB_BLAST_SPEED, POPUP_LIFETIME, BOSS_BULLET_COOLDOWN, BOSS_BULLET_NUMBER, ENEMY_BULLET_COOLDOWN, PLAYER_BULLET_COOLDOWN};
 use std;
 
 const ENEMY_COOLDOWN: i64 = 1_000;
 const ENEMY_COOLDOWN_BLUESCREEN: i64 = 6_000;
 const ENEMY_COOLDOWN_BOSS: i64 = 65_000;
 const SPECIAL_COOLDOWN: i64 = 20_000;
+/// When only bosses are sent, the next one arrives this long after the last is gone.
+const BOSS_RUSH_DELAY: i64 = 3_000;
+/// Size of the crab sprite, used to place the player and its gun.
+const PLAYER_SPRITE_SIZE: (f32, f32) = (128.0, 86.0);
+/// Size of the rust logo sprite fired by the player.
+const PLAYER_BULLET_SPRITE_SIZE: (f32, f32) = (50.0, 50.0);
+const HOMING_MISSILE_SPEED: f32 = 450.0;
+/// Once fully ramped up, endless runs escalate one level this often (in seconds).
+const ESCALATION_PERIOD: u64 = 60;
+/// Spawn cooldowns shrink by this factor with every escalation level.
+const ESCALATION_COOLDOWN_DECAY: f32 = 0.85;
+/// Spawn cooldowns are never scaled below this fraction, however far a run escalates.
+const MIN_COOLDOWN_FACTOR: f32 = 0.03;
+/// Chance of an enemy spawning as an elite per escalation level, and the cap.
+const ELITE_CHANCE_PER_LEVEL: f32 = 0.1;
+const MAX_ELITE_CHANCE: f32 = 0.6;
+/// Elite enemies have this many times the hit points of a normal one.
+const ELITE_HP_FACTOR: i32 = 3;
+const LASER_LIFETIME: i64 = 120;
 
 /// This keeps track of cooldowns for various entity types and spawns when necessary
+#[derive(Clone, Serialize, Deserialize)]
 pub struct EntitySpawner {
-    pub _screen_height: u32,
+    pub screen_height: u32,
     pub screen_width: u32,
-    pub rng: rand::ThreadRng,
-    pub cooldowns: std::collections::HashMap<EntityType, i64>,
+    pub rng: GameRng,
+    /// Time left until each type spawns next. Kept sorted so every machine
+    /// picks the same type when several are due at once.
+    pub cooldowns: std::collections::BTreeMap<EntityType, i64>,
+    /// Gameplay parameters of the difficulty being played
+    pub preset: Preset,
+    /// Extra speed-up of spawn and fire rates from adaptive difficulty
+    pub adjustment: f32,
+    /// Keep escalating after the difficulty has fully ramped up
+    pub endless: bool,
+    /// Which enemies get spawned
+    pub enemies: EnemySet,
 }
 
 impl EntitySpawner {
 	/// Create a new entity spawner.
-    pub fn new(ctx: &Context) -> EntitySpawner {        
+    pub fn new(screen_width: u32, screen_height: u32) -> EntitySpawner {
         let mut p = EntitySpawner {
-            _screen_height: ctx.conf.window_mode.height,
-            screen_width: ctx.conf.window_mode.width,
-            rng: rand::thread_rng(),
-            cooldowns: std::collections::HashMap::new(),
+            screen_height,
+            screen_width,
+            rng: GameRng::new(),
+            cooldowns: std::collections::BTreeMap::new(),
+            preset: Difficulty::Normal.preset(),
+            adjustment: 1.0,
+            endless: false,
+            enemies: EnemySet::All,
         };
 		// Set up the basic cooldowns
 
@@ -48,191 +89,348 @@ impl EntitySpawner {
         p
     }
 
+	/// Generates the crab of player `slot` out of `players`, at the bottom
+	/// of the screen.
+    pub fn spawn_player(&self, slot: usize, players: usize) -> Entity {
+        // Players start spread evenly along the bottom of the screen
+        let x = self.screen_width as f32 * (slot + 1) as f32 / (players + 1) as f32;
+        Entity {
+            entity_type: EntityType::Player,
+            transform: Transform::at(
+                x - PLAYER_SPRITE_SIZE.0 / 2.0,
+                self.screen_height as f32 - PLAYER_SPRITE_SIZE.1,
+            ),
+            velocity: Some(Velocity::new(Movement::Controlled(400.0))),
+            health: Some(Health { hp: self.preset.lives, bounty: 0 }),
+            weapon: Some(Weapon {
+                pattern: FirePattern::Player,
+                cooldown: PLAYER_BULLET_COOLDOWN,
+                period: PLAYER_BULLET_COOLDOWN,
+                // Bullets leave from a circle around the middle of the crab.
+                muzzle: (
+                    PLAYER_SPRITE_SIZE.0 / 2.0 - PLAYER_BULLET_SPRITE_SIZE.0 / 2.0,
+                    PLAYER_SPRITE_SIZE.1 / 2.0 - PLAYER_BULLET_SPRITE_SIZE.1 / 2.0,
+                ),
+                radius: PLAYER_SPRITE_SIZE.0 / 2.0,
+            }),
+            hitbox: Some(Hitbox {
+                bounds: graphics::Rect {
+                    x: 60.0,
+                    y: 40.0,
+                    w: 10.0,
+                    h: 18.0,
+                },
+                damage: 0,
+            }),
+            lifetime: Lifetime::Forever,
+            sprite: Some(Sprite {
+                texture: EntityType::Player,
+                label: None,
+            }),
+            pickup: None,
+            piercing: None,
+            blast: None,
+            elite: None,
+        }
+    }
+
 	/// Generates a binary splat entity
     pub fn spawn_splat(&self, x: f32, y: f32) -> Entity {
-        let splat = Entity {
-            name: "splat".to_string(),
+        Entity {
             entity_type: EntityType::Splat,
-            x: x,
-            y: y,
-            hp: 1,
-            damage: 0,
-            vel: 0.0,
-            bounds: graphics::Rect {
-                x: 0.0,
-                y: 0.0,
-                w: 80.0,
-                h: 80.0,
-            },
-            movement: Movement::None,
+            transform: Transform::at(x, y),
             lifetime: Lifetime::Milliseconds(SPLAT_LIFETIME),
-            seed: 0.0,
-            timer: 0,
-            bullet_cooldown: 0,
-            angle: 0.0,
-        };
-        splat
+            sprite: Some(Sprite {
+                texture: EntityType::Splat,
+                label: None,
+            }),
+            ..Default::default()
+        }
     }
 
 	/// Generates a screen shutoff entity
     pub fn spawn_shutoff(&self, x: f32, y: f32) -> Entity {
-        let shutoff = Entity {
-            name: "shutoff".to_string(),
+        Entity {
             entity_type: EntityType::Shutoff,
-            x: x,
-            y: y,
-            hp: 1,
-            damage: 0,
-            vel: 0.0,
-            bounds: graphics::Rect {
-                x: 0.0,
-                y: 0.0,
-                w: 80.0,
-                h: 80.0,
-            },
-            movement: Movement::None,
+            transform: Transform::at(x, y),
             lifetime: Lifetime::Milliseconds(SHUTOFF_LIFETIME),
-            seed: 0.0,
-            timer: 0,
-            bullet_cooldown: 0,
-            angle: 0.0,
-        };
-        shutoff
+            sprite: Some(Sprite {
+                texture: EntityType::Shutoff,
+                label: None,
+            }),
+            ..Default::default()
+        }
     }
 	
     /// Spawns bullets for the player
     pub fn player_bullet_spawner(&self, x: f32, y: f32) -> Entity {
-        let mut bullet = Entity::default();
-        bullet.x = x;
-        bullet.y = y;
-        bullet.bounds = graphics::Rect {
-            x: 0.0,
-            y: 0.0,
-            w: 50.0,
-            h: 50.0,
-        };
-		bullet.movement = Movement::Linear(0.0, -PLAYER_BULLET_SPEED);
-        bullet.lifetime = Lifetime::Milliseconds(2_000);
-        bullet.entity_type = EntityType::PlayerBullet;
-        bullet.name = "player_bullet".to_string();
+        let mut velocity = Velocity::new(Movement::Linear(0.0, -PLAYER_BULLET_SPEED));
+        velocity.spin = 1.0 / 100.0;
+        Entity {
+            entity_type: EntityType::PlayerBullet,
+            transform: Transform::at(x, y),
+            velocity: Some(velocity),
+            hitbox: Some(Hitbox {
+                bounds: graphics::Rect {
+                    x: 0.0,
+                    y: 0.0,
+                    w: 50.0,
+                    h: 50.0,
+                },
+                damage: 1,
+            }),
+            lifetime: Lifetime::Milliseconds(2_000),
+            sprite: Some(Sprite {
+                texture: EntityType::PlayerBullet,
+                label: None,
+            }),
+            ..Default::default()
+        }
+    }
 
+    /// Spawns a bullet that flies straight up through `hits` enemies.
+    pub fn spawn_piercing_bullet(&self, x: f32, y: f32, hits: u32) -> Entity {
+        let mut bullet = self.player_bullet_spawner(x, y);
+        bullet.velocity = Some(Velocity::new(Movement::Linear(0.0, -PLAYER_BULLET_SPEED * 1.5)));
+        bullet.piercing = Some(Piercing {
+            hits_left: Some(hits),
+            hit: vec![],
+        });
         bullet
     }
 
+    /// Spawns a missile that steers towards the nearest enemy.
+    pub fn spawn_homing_missile(&self, x: f32, y: f32, angle: f32) -> Entity {
+        let mut missile = self.player_bullet_spawner(x, y);
+        let mut velocity = Velocity::new(Movement::Homing(
+            angle.cos() * HOMING_MISSILE_SPEED,
+            -angle.sin() * HOMING_MISSILE_SPEED,
+        ));
+        velocity.spin = 1.0 / 50.0;
+        missile.velocity = Some(velocity);
+        missile.hitbox = Some(Hitbox {
+            bounds: graphics::Rect {
+                x: 0.0,
+                y: 0.0,
+                w: 50.0,
+                h: 50.0,
+            },
+            damage: 2,
+        });
+        missile.lifetime = Lifetime::Milliseconds(4_000);
+        missile
+    }
+
+    /// Spawns a laser beam of the given width, running from where a player
+    /// bullet at `x`, `y` would be up to the top of the screen.
+    pub fn spawn_laser(&self, x: f32, y: f32, width: f32) -> Entity {
+        let center = x + PLAYER_BULLET_SPRITE_SIZE.0 / 2.0;
+        Entity {
+            entity_type: EntityType::Laser,
+            transform: Transform::at(center - width / 2.0, 0.0),
+            hitbox: Some(Hitbox {
+                bounds: graphics::Rect {
+                    x: 0.0,
+                    y: 0.0,
+                    w: width,
+                    h: y + PLAYER_BULLET_SPRITE_SIZE.1 / 2.0,
+                },
+                damage: 1,
+            }),
+            lifetime: Lifetime::Milliseconds(LASER_LIFETIME),
+            sprite: Some(Sprite {
+                texture: EntityType::Laser,
+                label: None,
+            }),
+            piercing: Some(Piercing {
+                hits_left: None,
+                hit: vec![],
+            }),
+            ..Default::default()
+        }
+    }
+
     /// Spawns bullets for the enemy
     pub fn spawn_enemy_bullet(&self, x: f32, y: f32, angle: f32) -> Entity {
-        let mut bullet = Entity::default();
-        bullet.x = x;
-        bullet.y = y;
-        bullet.bounds = graphics::Rect {
-            x: 0.0,
-            y: 0.0,
-            w: 25.0,
-            h: 25.0,
-        };
-		bullet.movement = Movement::Linear(angle.cos() * ENEMY_BULLET_SPEED, -angle.sin() * ENEMY_BULLET_SPEED);
-        bullet.lifetime = Lifetime::Milliseconds(8_000);
-        bullet.entity_type = EntityType::EnemyBullet;
-        bullet.name = "player_bullet".to_string();
-
-        bullet
+        Entity {
+            entity_type: EntityType::EnemyBullet,
+            transform: Transform::at(x, y),
+            velocity: Some(Velocity::new(Movement::Linear(
+                angle.cos() * self.preset.bullet_speed,
+                -angle.sin() * self.preset.bullet_speed,
+            ))),
+            hitbox: Some(Hitbox {
+                bounds: graphics::Rect {
+                    x: 0.0,
+                    y: 0.0,
+                    w: 25.0,
+                    h: 25.0,
+                },
+                damage: 1,
+            }),
+            lifetime: Lifetime::Milliseconds(8_000),
+            sprite: Some(Sprite {
+                texture: EntityType::EnemyBullet,
+                label: None,
+            }),
+            ..Default::default()
+        }
     }
 
 	/// Spawns a special enemy holding an item.
     pub fn spawn_special(&self, seed: f64) -> Entity {
 	    Entity {
-            name: "special".to_string(),
             entity_type: EntityType::Special,
-            x: 0.0,
-            y: 0.0,
-            hp: 1,
-            damage: 1,
-            vel: 0.0,
-            bounds: graphics::Rect {
-                x: 18.0,
-                y: 5.0,
-                w: 44.0,
-                h: 60.0,
-            },
-            movement: Movement::Generated(|t, r, s| {
-                (
-                    (((t as f64) / 1000.0 + s * 1000.0).sin() + r.gen_range(-3.0, 3.0)) as f32
-                        * 60_f32,
-                    (1.0 + ((t as f64) / 900.0 + s * 100.0).sin()) as f32 * 60_f32,
-                )
+            transform: Transform::at(0.0, 0.0),
+            velocity: Some(Velocity {
+                movement: Movement::Generated(Pattern::Special),
+                seed,
+                timer: 0,
+                spin: 0.0,
+            }),
+            health: Some(Health { hp: 1, bounty: 50 }),
+            weapon: None,
+            hitbox: Some(Hitbox {
+                bounds: graphics::Rect {
+                    x: 18.0,
+                    y: 5.0,
+                    w: 44.0,
+                    h: 60.0,
+                },
+                damage: 1,
             }),
             lifetime: Lifetime::Milliseconds(ENEMY_LIFETIME),
-            seed,
-            timer: 0,
-            bullet_cooldown: 0,
-            angle: 0.0,
+            sprite: Some(Sprite {
+                texture: EntityType::Special,
+                label: None,
+            }),
+            pickup: None,
+            piercing: None,
+            blast: None,
+            elite: None,
         }
 	}
 	
+	/// How far an endless run has escalated past the fully ramped up
+	/// difficulty. Always 0 outside of endless runs.
+    pub fn escalation(&self, elapsed_ms: u64) -> u32 {
+        let seconds = elapsed_ms / 1000;
+        if !self.endless || seconds < self.preset.ramp_seconds {
+            return 0;
+        }
+        1 + ((seconds - self.preset.ramp_seconds) / ESCALATION_PERIOD) as u32
+    }
+
+	/// Turns an enemy into a tougher elite that is worth more and shoots
+	/// twice as often.
+    fn make_elite(&self, e: &mut Entity) {
+        if let Some(ref mut health) = e.health {
+            health.hp *= ELITE_HP_FACTOR;
+            health.bounty *= 2;
+        }
+        if let Some(ref mut weapon) = e.weapon {
+            weapon.period /= 2;
+        }
+        e.elite = Some(Elite);
+    }
+
+	/// Time between shots for an enemy weapon, scaled by the difficulty.
+    fn fire_cooldown(&self, cooldown: i64) -> i64 {
+        (self.preset.scale_fire_cooldown(cooldown) as f32 / self.adjustment) as i64
+    }
+
 	/// Spawns an enemy entity of a specific type.
     pub fn spawn_enemy(&self, seed: f64, name: &str, enemy_type: EntityType) -> Entity {
         // Default entity
         let mut e = Entity {
-            name: name.to_string(),
             entity_type: EntityType::Enemy,
-            x: 0.0,
-            y: 0.0,
-            hp: 1,
-            damage: 1,
-            vel: 0.0,
-            bounds: graphics::Rect {
-                x: 18.0,
-                y: 5.0,
-                w: 44.0,
-                h: 60.0,
-            },
-            movement: Movement::Generated(|t, r, s| {
-                (
-                    (((t as f64) / 1000.0 + s * 1000.0).sin() + r.gen_range(-3.0, 3.0)) as f32
-                        * 60_f32,
-                    (1.0 + ((t as f64) / 900.0 + s * 100.0).sin()) as f32 * 80_f32,
-                )
+            transform: Transform::at(0.0, 0.0),
+            velocity: Some(Velocity {
+                movement: Movement::Generated(Pattern::Bug),
+                seed,
+                timer: 0,
+                spin: 0.0,
+            }),
+            health: Some(Health { hp: self.preset.scale_hp(1), bounty: 10 }),
+            // Fires straight down from just under the bug's mouth.
+            weapon: Some(Weapon {
+                pattern: FirePattern::Down,
+                cooldown: 0,
+                period: self.fire_cooldown(ENEMY_BULLET_COOLDOWN),
+                muzzle: (22.0, 82.0),
+                radius: 0.0,
+            }),
+            hitbox: Some(Hitbox {
+                bounds: graphics::Rect {
+                    x: 18.0,
+                    y: 5.0,
+                    w: 44.0,
+                    h: 60.0,
+                },
+                damage: 1,
             }),
             lifetime: Lifetime::Milliseconds(ENEMY_LIFETIME),
-            seed,
-            timer: 0,
-            bullet_cooldown: 0,
-            angle: 0.0,
+            sprite: Some(Sprite {
+                texture: EntityType::Enemy,
+                label: Some(name.to_string()),
+            }),
+            pickup: None,
+            piercing: None,
+            blast: None,
+            elite: None,
         };
 
         // Certain enemies recieve different traits
         match enemy_type {
             EntityType::EnemyBlueScreen => {
-				e.name = "BSOD".to_string();
 				e.entity_type = EntityType::EnemyBlueScreen;
-                e.hp = 4;
-                e.movement = Movement::Generated(
-                    |t,r,s|{
-                        (
-                            ( ( (t as f64) / 1000.0 + s * 300.0 ).sin() + (t as f64).sin() * 2_f64 ) as f32 * 60_f32,
-                            (1.0 + ( (t as f64) / 900.0 + s * 100.0).sin() + r.gen_range(0.1, 3.0)  ) as f32 * 20_f32
-                        )
-                    }
-                );
+                e.health = Some(Health { hp: self.preset.scale_hp(4), bounty: 30 });
+                e.velocity = Some(Velocity {
+                    movement: Movement::Generated(Pattern::BlueScreen),
+                    seed,
+                    timer: 0,
+                    spin: 0.0,
+                });
+                e.weapon = Some(Weapon {
+                    pattern: FirePattern::TripleSpread,
+                    cooldown: 0,
+                    period: self.fire_cooldown(ENEMY_BULLET_COOLDOWN),
+                    muzzle: (23.0, 80.0),
+                    radius: 0.0,
+                });
+                e.sprite = Some(Sprite {
+                    texture: EntityType::EnemyBlueScreen,
+                    label: Some("BSOD".to_string()),
+                });
             },
 			EntityType::Boss => {
-				e.name = "ANSI C".to_string();
-				e.entity_type = EntityType::Boss;
-				e.hp = 40;
-				e.movement = Movement::Generated(
-				    |t,_r,s|{
-                        (
-                            ( (t as f64) / 1000.0 + s * 1000.0 ).sin() as f32 * 60.0,
-							35.0
-                        )
-                    }
-                );
-				e.bounds = graphics::Rect {
+				let bounds = graphics::Rect {
 					x: 30.0,
 					y: 20.0,
 					w: 140.0,
 					h: 130.0,
 				};
+				e.entity_type = EntityType::Boss;
+				e.health = Some(Health { hp: self.preset.scale_hp(40), bounty: 200 });
+				e.velocity = Some(Velocity {
+					movement: Movement::Generated(Pattern::Boss),
+					seed,
+					timer: 0,
+					spin: 1.0 / 600.0,
+				});
+				// A rotating ring of bullets around the middle of the boss.
+				e.weapon = Some(Weapon {
+					pattern: FirePattern::Ring(BOSS_BULLET_NUMBER),
+					cooldown: 0,
+					period: self.fire_cooldown(BOSS_BULLET_COOLDOWN),
+					muzzle: (bounds.x + bounds.w / 2.0, bounds.y + bounds.h / 2.0),
+					radius: (bounds.x * 2.0 + bounds.w) / 2.0,
+				});
+				e.hitbox = Some(Hitbox { bounds, damage: 1 });
+				e.sprite = Some(Sprite {
+					texture: EntityType::Boss,
+					label: None,
+				});
 			},
 
             _ => ()
@@ -242,98 +440,124 @@ impl EntitySpawner {
         e
     }
 
-	/// Spawns one of three powerups randomly
+	/// Spawns the shockwave of a player bomb centered on `x`, `y`.
+    pub fn spawn_blast(&self, x: f32, y: f32) -> Entity {
+        Entity {
+            entity_type: EntityType::Blast,
+            transform: Transform::at(x, y),
+            lifetime: Lifetime::Milliseconds((BOMB_BLAST_RADIUS / BOMB_BLAST_SPEED * 1000.0) as i64),
+            sprite: Some(Sprite {
+                texture: EntityType::Blast,
+                label: None,
+            }),
+            blast: Some(Blast {
+                radius: 0.0,
+                max_radius: BOMB_BLAST_RADIUS,
+                speed: BOMB_BLAST_SPEED,
+                hit: vec![],
+            }),
+            ..Default::default()
+        }
+    }
+
+	/// Spawns a floating bit of text, like the points scored for a kill.
+    pub fn spawn_popup(&self, x: f32, y: f32, text: String) -> Entity {
+        Entity {
+            entity_type: EntityType::Popup,
+            transform: Transform::at(x, y),
+            velocity: Some(Velocity::new(Movement::Linear(0.0, -60.0))),
+            lifetime: Lifetime::Milliseconds(POPUP_LIFETIME),
+            sprite: Some(Sprite {
+                texture: EntityType::Popup,
+                label: Some(text),
+            }),
+            ..Default::default()
+        }
+    }
+
+	/// Spawns one of the powerups randomly
 	pub fn spawn_item(&mut self) -> Entity {
-		let random = self.rng.gen_range(0.0, 1.0);
-		if random < 0.4 {
-			return self.spawn_powerbomb();
-		}
-		else if random < 0.7 {
-			return self.spawn_gun_upgrade();
+		let percent = (self.rng.gen_range(0.0, 1.0) * 100.0) as u32;
+		match percent {
+			0..30 => self.spawn_powerbomb(),
+			30..50 => self.spawn_gun_upgrade(),
+			50..65 => self.spawn_shield(),
+			65..80 => {
+				let kinds = WeaponKind::all();
+				let kind = kinds[self.rng.gen::<usize>() % kinds.len()];
+				self.spawn_weapon_pickup(kind)
+			}
+			_ => {
+				// Any timed effect, the shield already has its own pickup
+				let kinds: Vec<StatusKind> = StatusKind::all().iter()
+					.cloned()
+					.filter(|&kind| kind != StatusKind::Shield)
+					.collect();
+				let kind = kinds[self.rng.gen::<usize>() % kinds.len()];
+				self.spawn_status_pickup(kind)
+			}
 		}
-		else {
-			return self.spawn_shield();
+	}
+
+	/// Builds a falling item the player can collect.
+	fn spawn_pickup(&self, entity_type: EntityType, kind: PickupKind) -> Entity {
+		Entity {
+			entity_type,
+			transform: Transform::at(0.0, 0.0),
+			velocity: Some(Velocity::new(Movement::Linear(0.0, 100.0))),
+			hitbox: Some(Hitbox {
+				bounds: graphics::Rect {
+					x: 0.0,
+					y: 0.0,
+					w: 64.0,
+					h: 64.0,
+				},
+				damage: 0,
+			}),
+			lifetime: Lifetime::Milliseconds(100_000),
+			sprite: Some(Sprite {
+				texture: entity_type,
+				label: None,
+			}),
+			pickup: Some(Pickup { kind }),
+			..Default::default()
 		}
 	}
-                
 	
 	/// Spawns a power bomb
     pub fn spawn_powerbomb(&self) -> Entity {
-        let e = Entity {
-            name: "power bomb".to_string(),
-            entity_type: EntityType::Powerbomb,
-            x: 0.0,
-            y: 0.0,
-            hp: 1,
-            damage: 1,
-            vel: 10.0,
-            bounds: graphics::Rect {
-                x: 0.0,
-                y: 0.0,
-                w: 64.0,
-                h: 64.0,
-            },
-            movement: Movement::Linear(0.0, 100.0),
-            lifetime: Lifetime::Milliseconds(100_000),
-            seed: 0.0,
-            timer: 0,
-            bullet_cooldown: 0,
-            angle: 0.0,
-        };
-        // Return powerbomb entity option type.
-        e
+        self.spawn_pickup(EntityType::Powerbomb, PickupKind::Powerbomb)
     }
 
-
+	/// Spawns a gun upgrade
     pub fn spawn_gun_upgrade(&self) -> Entity {
-        let e = Entity {
-            name: "gun upgrade".to_string(),
-            entity_type: EntityType::GunUpgrade,
-            x: 0.0,
-            y: 0.0,
-            hp: 1,
-            damage: 1,
-            vel: 10.0,
-            bounds: graphics::Rect {
-                x: 0.0,
-                y: 0.0,
-                w: 64.0,
-                h: 64.0,
-            },
-            movement: Movement::Linear(0.0, 100.0),
-            lifetime: Lifetime::Milliseconds(100_000),
-            seed: 0.0,
-            timer: 0,
-            bullet_cooldown: 0,
-            angle: 0.0,
-        };
-        // Return upgrade entity option type.
-        e
+        self.spawn_pickup(EntityType::GunUpgrade, PickupKind::GunUpgrade)
     }
 
+	/// Spawns a shield
     pub fn spawn_shield(&self) -> Entity {
-        let e = Entity {
-            name: "firewall".to_string(),
-            entity_type: EntityType::Shield,
-            x: 0.0,
-            y: 0.0,
-            hp: 1,
-            damage: 1,
-            vel: 10.0,
-            bounds: graphics::Rect {
-                x: 0.0,
-                y: 0.0,
-                w: 64.0,
-                h: 64.0,
-            },
-            movement: Movement::Linear(0.0, 100.0),
-            lifetime: Lifetime::Milliseconds(100_000),
-            seed: 0.0,
-            timer: 0,
-            bullet_cooldown: 0,
-            angle: 0.0,
-        };
-        // Return shield entity option type.
+        self.spawn_pickup(EntityType::Shield, PickupKind::Status(StatusKind::Shield))
+    }
+
+	/// Spawns a timed power-up. It reuses the shield sprite, tinted by
+	/// effect when drawn.
+    pub fn spawn_status_pickup(&self, kind: StatusKind) -> Entity {
+        let mut e = self.spawn_pickup(EntityType::StatusPickup, PickupKind::Status(kind));
+        e.sprite = Some(Sprite {
+            texture: EntityType::Shield,
+            label: None,
+        });
+        e
+    }
+
+	/// Spawns a weapon that switches to, or levels up, the given gun.
+	/// It reuses the gun upgrade sprite, tinted by weapon when drawn.
+    pub fn spawn_weapon_pickup(&self, kind: WeaponKind) -> Entity {
+        let mut e = self.spawn_pickup(EntityType::WeaponPickup, PickupKind::Weapon(kind));
+        e.sprite = Some(Sprite {
+            texture: EntityType::GunUpgrade,
+            label: None,
+        });
         e
     }
 
@@ -346,14 +570,15 @@ impl EntitySpawner {
         for (k, v) in self.cooldowns.iter_mut() {
             *v -= delta_ms as i64;
             if *v <= 0 {
-                entity_type = k.clone();
+                entity_type = *k;
             }
         }
 
-		let mut difficulty_factor = (SECONDS_UNTIL_MAX_DIFFICULTY - elapsed_ms / 1000) as f32 / SECONDS_UNTIL_MAX_DIFFICULTY as f32;
-		if difficulty_factor < MAX_DIFFICULTY {
-			difficulty_factor = MAX_DIFFICULTY;
-		}
+		// Endless runs keep speeding up after the preset has fully ramped up
+		let escalation = self.escalation(elapsed_ms);
+		let difficulty_factor = (self.preset.cooldown_factor(elapsed_ms)
+			* ESCALATION_COOLDOWN_DECAY.powi(escalation as i32)).max(MIN_COOLDOWN_FACTOR) / self.adjustment;
+		let elite_chance = (escalation as f32 * ELITE_CHANCE_PER_LEVEL).min(MAX_ELITE_CHANCE);
 		
         match entity_type {
             EntityType::Enemy => {
@@ -361,13 +586,16 @@ impl EntitySpawner {
                 self.cooldowns.insert(entity_type, (ENEMY_COOLDOWN as f32 * difficulty_factor) as i64);
 
                 // Create enemy name and seed.
-                let name = ENEMY_NAMES[self.rng.gen::<usize>() % ENEMY_NAMES.len()].clone();
+                let name = ENEMY_NAMES[self.rng.gen::<usize>() % ENEMY_NAMES.len()];
                 let seed: f64 = self.rng.gen_range(-1.0, 1.0);
 
                 // Create enemy.
                 let mut entity = self.spawn_enemy(seed, name, EntityType::Enemy);
-                entity.x = self.rng.gen_range(0.0, self.screen_width as f32);
-                entity.y = -70.0;
+                entity.transform.x = self.rng.gen_range(0.0, self.screen_width as f32);
+                entity.transform.y = -70.0;
+                if self.rng.gen_range(0.0, 1.0) < elite_chance {
+                    self.make_elite(&mut entity);
+                }
                 return Some(entity);
             }
             EntityType::EnemyBlueScreen => {
@@ -376,26 +604,34 @@ impl EntitySpawner {
                     .insert(entity_type, (ENEMY_COOLDOWN_BLUESCREEN as f32 * difficulty_factor) as i64);
 
                 // Create enemy name and seed.
-                let name = ENEMY_NAMES[self.rng.gen::<usize>() % ENEMY_NAMES.len()].clone();
+                let name = ENEMY_NAMES[self.rng.gen::<usize>() % ENEMY_NAMES.len()];
                 let seed: f64 = self.rng.gen_range(-1.0, 1.0);
 
                 // Create enemy.
                 let mut entity = self.spawn_enemy(seed, name, EntityType::EnemyBlueScreen);
-                entity.x = self.rng.gen_range(0.0, self.screen_width as f32);
-                entity.y = -70.0;
+                entity.transform.x = self.rng.gen_range(0.0, self.screen_width as f32);
+                entity.transform.y = -70.0;
+                if self.rng.gen_range(0.0, 1.0) < elite_chance {
+                    self.make_elite(&mut entity);
+                }
                 return Some(entity);
             }
             EntityType::Boss => {
-                // Reset cooldown.
-                self.cooldowns.insert(entity_type, (ENEMY_COOLDOWN_BOSS as f32 * difficulty_factor) as i64);
+                // Reset cooldown. Bosses come more often with each escalation, so several end up on screen at once.
+                // When only bosses are sent, wait for this one to go before sending the next.
+                let cooldown = match self.enemies {
+                    EnemySet::Bosses => i64::MAX,
+                    _ => (ENEMY_COOLDOWN_BOSS as f32 * difficulty_factor / (1 + escalation) as f32) as i64,
+                };
+                self.cooldowns.insert(entity_type, cooldown);
 
                 // Create seed.
                 let seed: f64 = self.rng.gen_range(-1.0, 1.0);
 
                 // Create enemy.
                 let mut entity = self.spawn_enemy(seed, "ANSI C", EntityType::Boss);
-                entity.x = self.rng.gen_range(200.0, self.screen_width as f32 - 200.0);
-                entity.y = -200.0;
+                entity.transform.x = self.rng.gen_range(200.0, self.screen_width as f32 - 200.0);
+                entity.transform.y = -200.0;
                 return Some(entity);
             }
             EntityType::Special => {
@@ -406,8 +642,8 @@ impl EntitySpawner {
 
                 // Create enemy.
                 let mut entity = self.spawn_special(seed);
-                entity.x = self.rng.gen_range(0.0, self.screen_width as f32);
-                entity.y = -70.0;
+                entity.transform.x = self.rng.gen_range(0.0, self.screen_width as f32);
+                entity.transform.y = -70.0;
                 return Some(entity);
             }
             _ => (),
@@ -418,9 +654,104 @@ impl EntitySpawner {
 	
 	/// Resets everything for a new game
 	pub fn reset(&mut self) {
-		self.cooldowns.insert(EntityType::Enemy, ENEMY_COOLDOWN);
-		self.cooldowns.insert(EntityType::EnemyBlueScreen, ENEMY_COOLDOWN_BLUESCREEN);
-		self.cooldowns.insert(EntityType::Boss, ENEMY_COOLDOWN_BOSS);
-		self.cooldowns.insert(EntityType::Special, SPECIAL_COOLDOWN);
+		let boss_cooldown = match self.enemies {
+			EnemySet::Bosses => BOSS_RUSH_DELAY,
+			_ => ENEMY_COOLDOWN_BOSS,
+		};
+		self.cooldowns.clear();
+		for &(entity_type, cooldown) in [
+			(EntityType::Enemy, ENEMY_COOLDOWN),
+			(EntityType::EnemyBlueScreen, ENEMY_COOLDOWN_BLUESCREEN),
+			(EntityType::Boss, boss_cooldown),
+			(EntityType::Special, SPECIAL_COOLDOWN),
+		].iter() {
+			if self.enemies.includes(entity_type) {
+				self.cooldowns.insert(entity_type, cooldown);
+			}
+		}
 	}
-}
\ No newline at end of file
+
+	/// Called when a boss is killed or leaves the screen. When only bosses
+	/// are sent, this starts the countdown to the next one.
+	pub fn boss_gone(&mut self) {
+		if self.enemies == EnemySet::Bosses {
+			self.cooldowns.insert(EntityType::Boss, BOSS_RUSH_DELAY);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::difficulty::Difficulty;
+    use proptest::prelude::*;
+
+    /// The cooldown an enemy gets after spawning at the given time.
+    fn enemy_cooldown(difficulty: Difficulty, endless: bool, elapsed_ms: u64) -> i64 {
+        let mut spawner = EntitySpawner::new(1024, 768);
+        spawner.preset = difficulty.preset();
+        spawner.endless = endless;
+        spawner.cooldowns.clear();
+        spawner.cooldowns.insert(EntityType::Enemy, 0);
+        spawner.update(elapsed_ms, 0).expect("The enemy should have spawned");
+        spawner.cooldowns[&EntityType::Enemy]
+    }
+
+    proptest! {
+        #[test]
+        fn cooldowns_shrink_as_the_run_goes_on(
+            difficulty in 0usize..4,
+            endless: bool,
+            earlier in 0u64..3_600_000,
+            later in 0u64..3_600_000,
+        ) {
+            let difficulty = Difficulty::all()[difficulty];
+            let (earlier, later) = (earlier.min(later), earlier.max(later));
+            let (before, after) = (enemy_cooldown(difficulty, endless, earlier), enemy_cooldown(difficulty, endless, later));
+            prop_assert!(after <= before);
+            prop_assert!(after > 0);
+        }
+
+        #[test]
+        fn spawning_resets_the_cooldown(seed: u32, deltas in prop::collection::vec(0u64..5_000, 1..200)) {
+            let mut spawner = EntitySpawner::new(1024, 768);
+            spawner.rng = GameRng::from_seed([seed, 1, 2, 3]);
+            let kinds: Vec<EntityType> = spawner.cooldowns.keys().cloned().collect();
+            let mut elapsed_ms = 0;
+            for delta in deltas {
+                elapsed_ms += delta;
+                if let Some(entity) = spawner.update(elapsed_ms, delta) {
+                    prop_assert!(spawner.cooldowns[&entity.entity_type] > 0);
+                }
+                // Spawning never adds or drops a kind of enemy
+                prop_assert_eq!(spawner.cooldowns.keys().cloned().collect::<Vec<EntityType>>(), kinds.clone());
+            }
+        }
+    }
+
+    #[test]
+    fn only_endless_runs_escalate() {
+        let mut spawner = EntitySpawner::new(1024, 768);
+        let ramp_ms = spawner.preset.ramp_seconds * 1000;
+        assert_eq!(spawner.escalation(ramp_ms * 2), 0);
+
+        spawner.endless = true;
+        assert_eq!(spawner.escalation(ramp_ms - 1), 0);
+        assert_eq!(spawner.escalation(ramp_ms), 1);
+        assert_eq!(spawner.escalation(ramp_ms + ESCALATION_PERIOD * 1000), 2);
+    }
+
+    #[test]
+    fn elites_are_tougher_and_worth_more() {
+        let spawner = EntitySpawner::new(1024, 768);
+        let normal = spawner.spawn_enemy(0.0, "SEGFAULT", EntityType::EnemyBlueScreen);
+        let mut elite = spawner.spawn_enemy(0.0, "SEGFAULT", EntityType::EnemyBlueScreen);
+        spawner.make_elite(&mut elite);
+
+        let (normal_health, elite_health) = (normal.health.unwrap(), elite.health.unwrap());
+        assert_eq!(elite_health.hp, normal_health.hp * ELITE_HP_FACTOR);
+        assert_eq!(elite_health.bounty, normal_health.bounty * 2);
+        assert!(elite.weapon.unwrap().period < normal.weapon.unwrap().period);
+        assert_eq!(elite.elite, Some(Elite));
+    }
+}
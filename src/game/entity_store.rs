@@ -0,0 +1,239 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+/// A stable handle to an entity living in an `EntityStore`.
+/// The generation is bumped every time a slot is reused, so a handle
+/// to an entity that has since died will never point at its replacement.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, Serialize, Deserialize)]
+pub struct EntityId {
+    index: u32,
+    generation: u32,
+}
+
+/// Generational arena handing out entity ids. The entities themselves
+/// are just the sum of their components, which live in `ComponentStorage`s.
+/// Despawns requested while the game loop is iterating are buffered and
+/// only applied when the world flushes them.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EntityStore {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
+    pending_despawns: Vec<EntityId>,
+}
+
+impl EntityStore {
+    /// Create an empty entity store.
+    pub fn new() -> EntityStore {
+        EntityStore {
+            generations: Vec::new(),
+            alive: Vec::new(),
+            free: Vec::new(),
+            pending_despawns: Vec::new(),
+        }
+    }
+
+    /// Immediately allocates a new entity id.
+    pub fn create(&mut self) -> EntityId {
+        match self.free.pop() {
+            Some(index) => {
+                let i = index as usize;
+                self.generations[i] += 1;
+                self.alive[i] = true;
+                EntityId {
+                    index,
+                    generation: self.generations[i],
+                }
+            }
+            None => {
+                self.generations.push(0);
+                self.alive.push(true);
+                EntityId {
+                    index: self.generations.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    /// Immediately frees an entity id. Returns false if it was already dead.
+    pub fn destroy(&mut self, id: EntityId) -> bool {
+        if !self.contains(id) {
+            return false;
+        }
+        self.alive[id.index as usize] = false;
+        self.free.push(id.index);
+        true
+    }
+
+    /// Queues an entity to be removed on the next flush.
+    pub fn despawn(&mut self, id: EntityId) {
+        self.pending_despawns.push(id);
+    }
+
+    /// Hands back every buffered despawn, leaving the buffer empty.
+    pub fn drain_despawns(&mut self) -> Vec<EntityId> {
+        self.pending_despawns.drain(..).collect()
+    }
+
+    /// Frees every id and forgets any buffered despawns.
+    pub fn clear(&mut self) {
+        self.generations.clear();
+        self.alive.clear();
+        self.free.clear();
+        self.pending_despawns.clear();
+    }
+
+    /// Returns true if the handle still refers to a live entity.
+    pub fn contains(&self, id: EntityId) -> bool {
+        let i = id.index as usize;
+        i < self.alive.len() && self.alive[i] && self.generations[i] == id.generation
+    }
+
+    /// Handles of every live entity, in slot order.
+    pub fn ids(&self) -> Vec<EntityId> {
+        (0..self.alive.len())
+            .filter(|&i| self.alive[i])
+            .map(|i| EntityId {
+                index: i as u32,
+                generation: self.generations[i],
+            })
+            .collect()
+    }
+}
+
+/// Sparse storage of one kind of component, indexed by entity id.
+/// Each value remembers the generation of the entity it belongs to,
+/// so stale ids never see a newer entity's component.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ComponentStorage<T> {
+    slots: Vec<Option<(u32, T)>>,
+}
+
+impl<T> ComponentStorage<T> {
+    /// Create an empty storage.
+    pub fn new() -> ComponentStorage<T> {
+        ComponentStorage { slots: Vec::new() }
+    }
+
+    /// Attaches a component to an entity, replacing any previous one.
+    pub fn insert(&mut self, id: EntityId, value: T) {
+        let i = id.index as usize;
+        while self.slots.len() <= i {
+            self.slots.push(None);
+        }
+        self.slots[i] = Some((id.generation, value));
+    }
+
+    /// Detaches and returns the component of an entity.
+    pub fn remove(&mut self, id: EntityId) -> Option<T> {
+        if !self.contains(id) {
+            return None;
+        }
+        self.slots[id.index as usize].take().map(|(_, value)| value)
+    }
+
+    /// Returns true if the entity has this component.
+    pub fn contains(&self, id: EntityId) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Returns the component of an entity.
+    pub fn get(&self, id: EntityId) -> Option<&T> {
+        match self.slots.get(id.index as usize) {
+            Some(&Some((generation, ref value))) if generation == id.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the component of an entity mutably.
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut T> {
+        match self.slots.get_mut(id.index as usize) {
+            Some(&mut Some((generation, ref mut value))) if generation == id.generation => {
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+
+    /// Removes every component.
+    pub fn clear(&mut self) {
+        self.slots.clear();
+    }
+
+    /// Iterates over every component along with the id of its entity.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (EntityId, &'a T)> + 'a {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.as_ref().map(|&(generation, ref value)| {
+                (
+                    EntityId {
+                        index: index as u32,
+                        generation,
+                    },
+                    value,
+                )
+            })
+        })
+    }
+
+    /// Iterates mutably over every component along with the id of its entity.
+    pub fn iter_mut<'a>(&'a mut self) -> impl Iterator<Item = (EntityId, &'a mut T)> + 'a {
+        self.slots.iter_mut().enumerate().filter_map(|(index, slot)| {
+            slot.as_mut().map(|&mut (generation, ref mut value)| {
+                (
+                    EntityId {
+                        index: index as u32,
+                        generation,
+                    },
+                    value,
+                )
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reused_slots_get_new_generations() {
+        let mut store = EntityStore::new();
+        let a = store.create();
+        assert!(store.destroy(a));
+        let b = store.create();
+        assert_eq!(a.index, b.index);
+        assert!(!store.contains(a));
+        assert!(store.contains(b));
+        assert!(!store.destroy(a));
+    }
+
+    #[test]
+    fn stale_ids_do_not_see_new_components() {
+        let mut store = EntityStore::new();
+        let mut healths = ComponentStorage::new();
+        let a = store.create();
+        healths.insert(a, 5);
+        store.destroy(a);
+        healths.remove(a);
+        let b = store.create();
+        healths.insert(b, 7);
+        assert_eq!(healths.get(a), None);
+        assert_eq!(healths.get(b), Some(&7));
+        assert_eq!(healths.iter().map(|(id, _)| id).collect::<Vec<_>>(), vec![b]);
+    }
+
+    #[test]
+    fn despawns_are_buffered() {
+        let mut store = EntityStore::new();
+        let a = store.create();
+        store.despawn(a);
+        assert!(store.contains(a));
+        assert_eq!(store.drain_despawns(), vec![a]);
+        assert!(store.drain_despawns().is_empty());
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+//! Gameplay events. The systems report what happened during an update
+//! here instead of reacting to it on the spot, and everything that cares
+//! (scoring, the run stats, sound effects and whatever else comes along)
+//! reads them from the world's queue.
+
+use game::components::PickupKind;
+use game::entity::EntityType;
+
+/// Something that happened in the simulation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    /// A player fired their gun.
+    PlayerFired { slot: usize },
+    /// An enemy or boss fired.
+    EnemyFired { kind: EntityType },
+    /// A player's shot hit an enemy or boss.
+    EnemyHit { kind: EntityType },
+    /// An enemy or boss ran out of hit points. `name` is its name tag, and
+    /// `bounty` is what it is worth before any multipliers.
+    EnemyKilled { kind: EntityType, name: Option<String>, x: f32, y: f32, bounty: i32 },
+    /// A player was hit. `damage` is what the hit was worth, even if a
+    /// shield absorbed it or practice mode kept the hit points.
+    PlayerHit { slot: usize, source: EntityType, damage: i32, absorbed: bool },
+    /// A player ran out of hit points.
+    PlayerDown { slot: usize },
+    /// A downed player came back while their partner held on.
+    PlayerRevived { slot: usize },
+    /// A player picked up an item.
+    PickupCollected { slot: usize, kind: PickupKind },
+    /// A player set off a bomb.
+    BombUsed { slot: usize },
+    /// A full graze meter gave a player a bomb.
+    BombEarned { slot: usize },
+    /// Enemy bullets passed close to a player without hitting them.
+    Grazed { slot: usize, bullets: u32 },
+    /// A boss came on screen.
+    BossArrived,
+    /// A boss was killed, as opposed to leaving the screen.
+    BossDefeated,
+}
+
+impl GameEvent {
+    /// The sound effect to play for the event, if any.
+    pub fn sound(&self) -> Option<&'static str> {
+        match *self {
+            GameEvent::PlayerFired { .. } => Some("player_shot"),
+            GameEvent::EnemyFired { .. } => Some("enemy_shot"),
+            GameEvent::EnemyHit { .. } | GameEvent::PlayerHit { .. } => Some("hit"),
+            GameEvent::EnemyKilled { .. } | GameEvent::PlayerDown { .. } => Some("explode"),
+            GameEvent::PickupCollected { kind: PickupKind::Status(_), .. } => Some("shield"),
+            GameEvent::PickupCollected { .. } | GameEvent::BombEarned { .. } | GameEvent::PlayerRevived { .. } => {
+                Some("upgrade")
+            }
+            GameEvent::BombUsed { .. } => Some("powerbomb"),
+            GameEvent::Grazed { .. } => Some("graze"),
+            GameEvent::BossArrived | GameEvent::BossDefeated => None,
+        }
+    }
+}
+
+/// The names of the sounds for a batch of events, each once, in the order
+/// they first come up.
+pub fn sounds(events: &[GameEvent]) -> Vec<&'static str> {
+    let mut names = vec![];
+    for name in events.iter().filter_map(|event| event.sound()) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::Input;
+    use game::components::Transform;
+    use game::world::World;
+
+    #[test]
+    fn updates_report_kills_once_with_one_sound_each() {
+        let mut world = World::new(1024, 768);
+        world.reset();
+        world.spawner.cooldowns.clear();
+        for x in [100.0, 300.0].iter() {
+            let mut enemy = world.spawner.spawn_enemy(0.0, "SEGFAULT", EntityType::Enemy);
+            enemy.transform = Transform::at(*x, 100.0);
+            enemy.weapon = None;
+            enemy.health.as_mut().unwrap().hp = 0;
+            world.insert(enemy);
+        }
+
+        world.update(&[Input::default()], 16, 16);
+        let kills: Vec<&GameEvent> = world.events.iter()
+            .filter(|e| matches!(e, GameEvent::EnemyKilled { .. }))
+            .collect();
+        assert_eq!(kills.len(), 2);
+        assert_eq!(sounds(&world.events), vec!["explode"]);
+
+        // Nothing is reported twice
+        world.events.clear();
+        world.update(&[Input::default()], 32, 16);
+        assert!(world.events.is_empty());
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+//! Golden-image tests for the renderer. Each scene puts the game into a
+//! fixed state, draws it offscreen and compares the frame with a PNG in
+//! `tests/golden`, allowing for the small differences between GL drivers.
+//!
+//! They need a window and OpenGL 3.2, so they are ignored by default. On
+//! machines without a GPU, run them under a software GL such as Mesa's
+//! llvmpipe:
+//!
+//! `LIBGL_ALWAYS_SOFTWARE=1 xvfb-run cargo test golden -- --ignored --test-threads=1`
+//!
+//! Set `UPDATE_GOLDEN=1` to save the current frames as the new goldens.
+
+extern crate image;
+
+use game::{GameMode, MainState};
+use game::attract::Attract;
+use game::components::Transform;
+use game::entity::EntityType;
+use ggez::event::EventHandler;
+use ggez::{conf, graphics, Context};
+use std;
+use std::path::PathBuf;
+
+const WIDTH: u32 = 1280;
+const HEIGHT: u32 = 720;
+/// How far a color channel can be off before the pixel counts as different.
+const CHANNEL_TOLERANCE: i16 = 24;
+/// Share of the pixels that can differ before a frame fails.
+const MAX_DIFFERENT_PIXELS: f64 = 0.005;
+
+/// A scene's name, which its golden image is saved under, and how to set it up.
+type Scene = (&'static str, fn(&mut MainState));
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+/// Frames that don't match are saved here, to compare with the goldens.
+fn actual_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target").join("golden")
+}
+
+/// A context with a fixed window size and the game's assets mounted, set
+/// up the same way as in `main`.
+fn context() -> Context {
+    let mut c = conf::Conf::new();
+    c.window_mode.width = WIDTH;
+    c.window_mode.height = HEIGHT;
+    c.window_mode.fullscreen_type = conf::FullscreenType::Off;
+    c.window_mode.vsync = false;
+    let mut ctx = Context::load_from_conf("ferris-golden", "ggez", c).expect("Could not create a GL context");
+    let assets = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets");
+    ctx.filesystem.mount(&assets, true);
+    ctx
+}
+
+/// Draws one frame into an offscreen canvas and reads it back as RGBA.
+fn render(ctx: &mut Context, state: &mut MainState) -> Vec<u8> {
+    let canvas = graphics::Canvas::with_window_size(ctx).unwrap();
+    graphics::set_canvas(ctx, Some(&canvas));
+    state.draw(ctx).unwrap();
+    graphics::set_canvas(ctx, None);
+    canvas.get_image().to_rgba8(ctx).unwrap()
+}
+
+/// Counts the pixels with any channel further apart than the tolerance.
+fn different_pixels(a: &[u8], b: &[u8]) -> usize {
+    a.chunks(4)
+        .zip(b.chunks(4))
+        .filter(|&(p, q)| p.iter().zip(q.iter()).any(|(&x, &y)| (x as i16 - y as i16).abs() > CHANNEL_TOLERANCE))
+        .count()
+}
+
+/// Compares a frame with its golden image, or replaces the golden when
+/// updating. Returns what went wrong if they don't match.
+fn check(name: &str, pixels: &[u8]) -> Result<(), String> {
+    let golden = golden_dir().join(format!("{}.png", name));
+    if std::env::var("UPDATE_GOLDEN").is_ok() {
+        std::fs::create_dir_all(golden_dir()).unwrap();
+        image::save_buffer(&golden, pixels, WIDTH, HEIGHT, image::ColorType::RGBA(8)).unwrap();
+        return Ok(());
+    }
+
+    let expected = match image::open(&golden) {
+        Ok(image) => image.to_rgba(),
+        Err(e) => return Err(format!("{}: can't open {} ({}), run with UPDATE_GOLDEN=1 to make it", name, golden.display(), e)),
+    };
+    let different = if expected.dimensions() == (WIDTH, HEIGHT) {
+        different_pixels(&expected.into_raw(), pixels)
+    } else {
+        pixels.len() / 4
+    };
+    if different as f64 > (WIDTH * HEIGHT) as f64 * MAX_DIFFERENT_PIXELS {
+        std::fs::create_dir_all(actual_dir()).unwrap();
+        let actual = actual_dir().join(format!("{}.png", name));
+        image::save_buffer(&actual, pixels, WIDTH, HEIGHT, image::ColorType::RGBA(8)).unwrap();
+        return Err(format!("{}: {} pixels differ from the golden image, see {}", name, different, actual.display()));
+    }
+    Ok(())
+}
+
+/// The title screen with its default settings, while the prompt is showing.
+fn title(state: &mut MainState) {
+    state.game_mode = GameMode::Menu;
+    state.attract = Attract::new();
+    state.resumable = false;
+    state.elapsed_ms = 200;
+}
+
+/// A run a minute in, with a couple of enemies, their bullets and a
+/// pickup on screen.
+fn in_game(state: &mut MainState) {
+    state.game_mode = GameMode::Game;
+    state.elapsed_ms = 61_000;
+    let world = &mut state.world;
+    world.seed(1);
+    world.reset();
+    world.score = 12_345;
+
+    let mut enemy = world.spawner.spawn_enemy(0.0, "SEGFAULT", EntityType::Enemy);
+    enemy.transform = Transform::at(300.0, 150.0);
+    world.insert(enemy);
+    let mut blue_screen = world.spawner.spawn_enemy(0.5, "BLUE SCREEN", EntityType::EnemyBlueScreen);
+    blue_screen.transform = Transform::at(800.0, 220.0);
+    world.insert(blue_screen);
+    for i in 0..5 {
+        let bullet = world.spawner.spawn_enemy_bullet(340.0 + i as f32 * 40.0, 300.0 + i as f32 * 30.0, -1.2);
+        world.insert(bullet);
+    }
+    let mut bomb = world.spawner.spawn_powerbomb();
+    bomb.transform = Transform::at(600.0, 450.0);
+    world.insert(bomb);
+}
+
+/// The win screen partway through its dance.
+fn win(state: &mut MainState) {
+    state.game_mode = GameMode::Win;
+    state.world.score = 12_345;
+    state.elapsed_ms = 1_500;
+}
+
+#[test]
+fn small_differences_are_tolerated() {
+    let golden = [10, 20, 30, 255, 200, 200, 200, 255];
+    assert_eq!(different_pixels(&golden, &[20, 20, 30, 255, 190, 210, 200, 255]), 0);
+    assert_eq!(different_pixels(&golden, &[10, 20, 30, 255, 0, 200, 200, 255]), 1);
+}
+
+#[test]
+#[ignore]
+fn scenes_match_their_golden_images() {
+    let mut ctx = context();
+    let mut state = MainState::new(&mut ctx).unwrap();
+    let scenes: [Scene; 3] = [("title", title), ("in_game", in_game), ("win", win)];
+
+    let failures: Vec<String> = scenes
+        .iter()
+        .filter_map(|&(name, setup)| {
+            setup(&mut state);
+            let pixels = render(&mut ctx, &mut state);
+            check(name, &pixels).err()
+        })
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
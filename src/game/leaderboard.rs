@@ -0,0 +1,141 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+//! Client for the online leaderboard. Finished runs are sent to a
+//! `ferris-leaderboard` server along with their seed and replay hash,
+//! and the best runs of each mode and difficulty can be fetched back.
+//!
+//! The protocol is JSON over HTTP:
+//!
+//! - `POST /runs` with an `Entry` adds a run, and answers with its `Rank`.
+//! - `GET /runs?mode=CLASSIC&difficulty=NORMAL&limit=10` answers with the
+//!   best entries of a table, best first.
+//!
+//! All of it is optional. Without a server, or when it can't be reached,
+//! the game just keeps its local high scores.
+
+extern crate serde_json;
+extern crate ureq;
+
+use std::time::Duration;
+
+/// How long to wait on the server before giving up, so a dead server
+/// doesn't hold anything up for long.
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A finished run on the leaderboard.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub user: String,
+    /// Name of the mode, as on the title screen.
+    pub mode: String,
+    /// Name of the difficulty, as on the title screen.
+    pub difficulty: String,
+    pub score: i32,
+    /// How long the run lasted (in ms)
+    pub time_ms: u64,
+    pub max_combo: u32,
+    /// Seed the run was started with.
+    pub seed: u32,
+    /// `Replay::hash` of the run's replay, 16 hex digits, so the entry can
+    /// be matched up with the inputs it came from.
+    pub replay_hash: String,
+}
+
+/// Where a submitted run placed in its table, counting from 1.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rank {
+    pub rank: u32,
+}
+
+/// Talks to a leaderboard server.
+#[derive(Clone)]
+pub struct Client {
+    /// Address of the server, e.g. `http://127.0.0.1:8080`
+    url: String,
+    agent: ureq::Agent,
+}
+
+impl Client {
+    pub fn new(url: &str) -> Client {
+        Client {
+            url: url.trim_end_matches('/').to_string(),
+            agent: ureq::AgentBuilder::new().timeout(TIMEOUT).build(),
+        }
+    }
+
+    /// A client for the server given with `--leaderboard <url>` on the
+    /// command line, if any.
+    pub fn from_args<I: Iterator<Item = String>>(args: I) -> Option<Client> {
+        let args: Vec<String> = args.collect();
+        args.iter()
+            .position(|a| a == "--leaderboard")
+            .and_then(|i| args.get(i + 1))
+            .map(|url| Client::new(url))
+    }
+
+    /// Sends a finished run, and returns where it placed.
+    pub fn submit(&self, entry: &Entry) -> Result<Rank, String> {
+        let json = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+        let response = self.agent
+            .post(&format!("{}/runs", self.url))
+            .set("Content-Type", "application/json")
+            .send_string(&json)
+            .map_err(describe)?;
+        let body = response.into_string().map_err(|e| e.to_string())?;
+        serde_json::from_str(&body).map_err(|e| e.to_string())
+    }
+
+    /// The best runs of a mode and difficulty, best first.
+    pub fn top(&self, mode: &str, difficulty: &str, limit: usize) -> Result<Vec<Entry>, String> {
+        let response = self.agent
+            .get(&format!("{}/runs", self.url))
+            .query("mode", mode)
+            .query("difficulty", difficulty)
+            .query("limit", &limit.to_string())
+            .call()
+            .map_err(describe)?;
+        let body = response.into_string().map_err(|e| e.to_string())?;
+        serde_json::from_str(&body).map_err(|e| e.to_string())
+    }
+}
+
+/// Turns a failed request into a message, including what the server said
+/// when it turned the request down.
+fn describe(error: ureq::Error) -> String {
+    match error {
+        ureq::Error::Status(code, response) => {
+            format!("server answered {}: {}", code, response.into_string().unwrap_or_default())
+        }
+        ureq::Error::Transport(e) => e.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std;
+
+    fn args(line: &str) -> std::vec::IntoIter<String> {
+        let args: Vec<String> = line.split_whitespace().map(String::from).collect();
+        args.into_iter()
+    }
+
+    #[test]
+    fn reads_the_server_from_the_command_line() {
+        assert!(Client::from_args(args("ferris")).is_none());
+        let client = Client::from_args(args("ferris --leaderboard http://localhost:8080/")).unwrap();
+        assert_eq!(client.url, "http://localhost:8080");
+    }
+
+    #[test]
+    fn unreachable_servers_give_an_error() {
+        // Nothing listens on port 1, so this fails straight away
+        let client = Client::new("http://127.0.0.1:1");
+        assert!(client.top("CLASSIC", "NORMAL", 10).is_err());
+    }
+}
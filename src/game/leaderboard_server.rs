@@ -0,0 +1,328 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+//! The `ferris-leaderboard` server. It keeps the runs sent by games in an
+//! SQLite database and serves the best of each table back, speaking the
+//! protocol described in `leaderboard`.
+
+extern crate rusqlite;
+extern crate serde_json;
+extern crate tiny_http;
+
+use game::difficulty::Difficulty;
+use game::leaderboard::{Entry, Rank};
+use game::play_mode::PlayMode;
+use game::scores::Ranking;
+use std;
+
+const USAGE: &str = "usage: ferris-leaderboard [--address HOST:PORT] [--database FILE]";
+/// Most entries sent back for one table.
+const MAX_LIMIT: usize = 100;
+/// Longest user name accepted.
+const MAX_USER_LENGTH: usize = 32;
+
+/// Columns of the `runs` table, in the order `Entry` is read back.
+const COLUMNS: &str = "user, mode, difficulty, score, time_ms, max_combo, seed, replay_hash";
+
+/// Stores the runs and answers requests for them.
+pub struct Leaderboard {
+    db: rusqlite::Connection,
+}
+
+impl Leaderboard {
+    /// Opens the database, creating it if needed. `:memory:` keeps the runs
+    /// in memory only.
+    pub fn open(file: &str) -> Result<Leaderboard, String> {
+        let db = rusqlite::Connection::open(file).map_err(|e| e.to_string())?;
+        db.execute_batch(
+            "CREATE TABLE IF NOT EXISTS runs (
+                id INTEGER PRIMARY KEY,
+                user TEXT NOT NULL,
+                mode TEXT NOT NULL,
+                difficulty TEXT NOT NULL,
+                score INTEGER NOT NULL,
+                time_ms INTEGER NOT NULL,
+                max_combo INTEGER NOT NULL,
+                seed INTEGER NOT NULL,
+                replay_hash TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS runs_by_table ON runs (mode, difficulty);",
+        ).map_err(|e| e.to_string())?;
+        Ok(Leaderboard { db })
+    }
+
+    /// Adds a run, and returns where it placed in its table.
+    pub fn add(&self, entry: &Entry) -> Result<Rank, String> {
+        let ranking = validate(entry)?;
+        self.db.execute(
+            &format!("INSERT INTO runs ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)", COLUMNS),
+            rusqlite::params![
+                entry.user,
+                entry.mode,
+                entry.difficulty,
+                entry.score,
+                entry.time_ms as i64,
+                entry.max_combo,
+                entry.seed,
+                entry.replay_hash,
+            ],
+        ).map_err(|e| e.to_string())?;
+
+        // Ties go to whoever got there first
+        let count = "SELECT COUNT(*) FROM runs WHERE mode = ?1 AND difficulty = ?2 AND";
+        let ahead: u32 = match ranking {
+            Ranking::Score => self.db.query_row(
+                &format!("{} score > ?3", count),
+                rusqlite::params![entry.mode, entry.difficulty, entry.score],
+                |row| row.get(0),
+            ),
+            Ranking::Survival => self.db.query_row(
+                &format!("{} (time_ms > ?3 OR (time_ms = ?3 AND score > ?4))", count),
+                rusqlite::params![entry.mode, entry.difficulty, entry.time_ms as i64, entry.score],
+                |row| row.get(0),
+            ),
+        }.map_err(|e| e.to_string())?;
+        Ok(Rank { rank: ahead + 1 })
+    }
+
+    /// The best runs of a table, best first.
+    pub fn top(&self, mode: &str, difficulty: &str, limit: usize) -> Result<Vec<Entry>, String> {
+        let order = match table_ranking(mode)? {
+            Ranking::Score => "score DESC, id",
+            Ranking::Survival => "time_ms DESC, score DESC, id",
+        };
+        let mut query = self.db.prepare(&format!(
+            "SELECT {} FROM runs WHERE mode = ?1 AND difficulty = ?2 ORDER BY {} LIMIT ?3",
+            COLUMNS, order
+        )).map_err(|e| e.to_string())?;
+        let rows = query.query_map(rusqlite::params![mode, difficulty, limit.min(MAX_LIMIT) as i64], |row| {
+            Ok(Entry {
+                user: row.get(0)?,
+                mode: row.get(1)?,
+                difficulty: row.get(2)?,
+                score: row.get(3)?,
+                time_ms: row.get::<_, i64>(4)? as u64,
+                max_combo: row.get(5)?,
+                seed: row.get(6)?,
+                replay_hash: row.get(7)?,
+            })
+        }).map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<Entry>, _>>().map_err(|e| e.to_string())
+    }
+
+    /// Answers one HTTP request with a status code and a JSON body.
+    pub fn respond(&self, method: &str, url: &str, body: &str) -> (u16, String) {
+        let (path, query) = match url.find('?') {
+            Some(i) => (&url[..i], &url[i + 1..]),
+            None => (url, ""),
+        };
+        let result = match (method, path) {
+            ("POST", "/runs") => serde_json::from_str::<Entry>(body)
+                .map_err(|e| e.to_string())
+                .and_then(|entry| self.add(&entry))
+                .map(|rank| serde_json::to_string(&rank).unwrap()),
+            ("GET", "/runs") => {
+                let params = parse_query(query);
+                let param = |name: &str| params.iter().find(|pair| pair.0 == name).map(|pair| pair.1.clone());
+                let limit = param("limit").and_then(|limit| limit.parse().ok()).unwrap_or(10);
+                match (param("mode"), param("difficulty")) {
+                    (Some(mode), Some(difficulty)) => self.top(&mode, &difficulty, limit)
+                        .map(|entries| serde_json::to_string(&entries).unwrap()),
+                    _ => Err("mode and difficulty are needed".to_string()),
+                }
+            }
+            _ => return (404, error_json("not found")),
+        };
+        match result {
+            Ok(json) => (200, json),
+            Err(e) => (400, error_json(&e)),
+        }
+    }
+}
+
+/// Turns down malformed entries, and returns how the run's table is
+/// ordered. Only the form of an entry is looked at: runs aren't played
+/// back here, so the score itself is taken on trust. The replay hash is
+/// kept so a run can be verified against its replay later on.
+fn validate(entry: &Entry) -> Result<Ranking, String> {
+    let ranking = table_ranking(&entry.mode)?;
+    if !Difficulty::all().iter().any(|d| d.name() == entry.difficulty) {
+        return Err(format!("unknown difficulty {}", entry.difficulty));
+    }
+    if entry.user.is_empty() || entry.user.chars().count() > MAX_USER_LENGTH {
+        return Err(format!("user names must be 1 to {} characters", MAX_USER_LENGTH));
+    }
+    if entry.score < 0 {
+        return Err("scores can't be negative".to_string());
+    }
+    if entry.time_ms == 0 {
+        return Err("runs can't end before they start".to_string());
+    }
+    if entry.replay_hash.len() != 16 || !entry.replay_hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("replay hashes must be 16 hex digits".to_string());
+    }
+    Ok(ranking)
+}
+
+/// How the table of a mode is ordered. Practice runs have no table.
+fn table_ranking(mode: &str) -> Result<Ranking, String> {
+    match PlayMode::all().iter().find(|m| m.name() == mode) {
+        Some(mode) if mode.keeps_scores() => Ok(mode.ranking()),
+        _ => Err(format!("no leaderboard for mode {}", mode)),
+    }
+}
+
+fn error_json(message: &str) -> String {
+    format!("{{\"error\":{}}}", serde_json::to_string(message).unwrap())
+}
+
+/// Splits a query string into its decoded names and values.
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.find('=') {
+            Some(i) => (decode(&pair[..i]), decode(&pair[i + 1..])),
+            None => (decode(pair), String::new()),
+        })
+        .collect()
+}
+
+/// Undoes URL encoding, e.g. `BOSS%20RUSH` or `BOSS+RUSH` to `BOSS RUSH`.
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = text.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+            (b'+', _) => decoded.push(b' '),
+            (byte, _) => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Serves requests until the process is stopped.
+pub fn serve(server: &tiny_http::Server, leaderboard: &Leaderboard) {
+    for mut request in server.incoming_requests() {
+        let mut body = String::new();
+        let (status, json) = match request.as_reader().read_to_string(&mut body) {
+            Ok(_) => leaderboard.respond(&request.method().to_string(), request.url(), &body),
+            Err(e) => (400, error_json(&e.to_string())),
+        };
+        let header = tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+        let response = tiny_http::Response::from_string(json).with_status_code(status).with_header(header);
+        if let Err(e) = request.respond(response) {
+            eprintln!("Could not answer a request: {}", e);
+        }
+    }
+}
+
+/// Entry point of the `ferris-leaderboard` binary.
+pub fn main() {
+    let mut address = "127.0.0.1:8080".to_string();
+    let mut database = "leaderboard.db".to_string();
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        match (flag.as_str(), args.next()) {
+            ("--address", Some(value)) => address = value,
+            ("--database", Some(value)) => database = value,
+            _ => {
+                eprintln!("{}", USAGE);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let leaderboard = Leaderboard::open(&database).unwrap_or_else(|e| {
+        eprintln!("Could not open {}: {}", database, e);
+        std::process::exit(1);
+    });
+    let server = tiny_http::Server::http(&address).unwrap_or_else(|e| {
+        eprintln!("Could not listen on {}: {}", address, e);
+        std::process::exit(1);
+    });
+    eprintln!("Leaderboard listening on http://{}", address);
+    serve(&server, &leaderboard);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::leaderboard::Client;
+    use std::thread;
+
+    fn entry(user: &str, mode: &str, score: i32, time_ms: u64) -> Entry {
+        Entry {
+            user: user.to_string(),
+            mode: mode.to_string(),
+            difficulty: "NORMAL".to_string(),
+            score,
+            time_ms,
+            max_combo: 3,
+            seed: 42,
+            replay_hash: "00000000deadbeef".to_string(),
+        }
+    }
+
+    /// Starts a server with an empty in-memory database on a free port,
+    /// and returns a client for it.
+    fn local_server() -> Client {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+        thread::spawn(move || serve(&server, &Leaderboard::open(":memory:").unwrap()));
+        Client::new(&format!("http://127.0.0.1:{}", port))
+    }
+
+    #[test]
+    fn decodes_query_strings() {
+        let params = parse_query("mode=BOSS%20RUSH&difficulty=HARD+MODE&limit=5&x");
+        assert_eq!(params[0], ("mode".to_string(), "BOSS RUSH".to_string()));
+        assert_eq!(params[1].1, "HARD MODE");
+        assert_eq!(params[3], ("x".to_string(), String::new()));
+        assert_eq!(decode("100%"), "100%");
+    }
+
+    #[test]
+    fn ranks_runs_over_http() {
+        let client = local_server();
+        assert_eq!(client.submit(&entry("ferris", "CLASSIC", 300, 60_000)).unwrap().rank, 1);
+        assert_eq!(client.submit(&entry("corro", "CLASSIC", 500, 30_000)).unwrap().rank, 1);
+        assert_eq!(client.submit(&entry("gopher", "CLASSIC", 100, 90_000)).unwrap().rank, 3);
+        let users: Vec<String> = client.top("CLASSIC", "NORMAL", 10).unwrap().into_iter().map(|e| e.user).collect();
+        assert_eq!(users, vec!["corro", "ferris", "gopher"]);
+        assert_eq!(client.top("CLASSIC", "NORMAL", 1).unwrap()[0], entry("corro", "CLASSIC", 500, 30_000));
+        assert!(client.top("CLASSIC", "HARD", 10).unwrap().is_empty());
+
+        // Endless runs rank by how long they lasted
+        client.submit(&entry("ferris", "ENDLESS", 900, 60_000)).unwrap();
+        assert_eq!(client.submit(&entry("corro", "ENDLESS", 100, 120_000)).unwrap().rank, 1);
+        assert_eq!(client.top("ENDLESS", "NORMAL", 10).unwrap()[0].user, "corro");
+    }
+
+    #[test]
+    fn turns_down_runs_without_a_table() {
+        let client = local_server();
+        assert!(client.submit(&entry("ferris", "PRACTICE", 300, 60_000)).is_err());
+        assert!(client.submit(&entry("", "CLASSIC", 300, 60_000)).is_err());
+        assert!(client.submit(&entry("ferris", "CLASSIC", -5, 60_000)).is_err());
+        assert!(client.submit(&entry("ferris", "CLASSIC", 300, 0)).is_err());
+        let mut unhashed = entry("ferris", "CLASSIC", 300, 60_000);
+        for hash in &["", "deadbeef", "00000000deadbeeg", "00000000deadbeef0"] {
+            unhashed.replay_hash = hash.to_string();
+            assert!(client.submit(&unhashed).is_err());
+        }
+        assert!(client.top("CHESS", "NORMAL", 10).is_err());
+        assert!(client.top("CLASSIC", "NORMAL", 10).unwrap().is_empty());
+    }
+}
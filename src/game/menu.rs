@@ -0,0 +1,129 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+use game::play_mode::PlayMode;
+use game::player::MAX_PLAYERS;
+use game::world::World;
+
+/// A setting that can be changed on the title screen.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum MenuItem {
+    Mode,
+    Difficulty,
+    Adaptive,
+    Players,
+    PracticeStart,
+    PracticeEnemies,
+}
+
+/// The settings on the title screen. `up` and `down` pick a setting,
+/// `left` and `right` change it.
+pub struct TitleMenu {
+    /// Index of the highlighted setting.
+    pub selected: usize,
+}
+
+impl TitleMenu {
+    /// Starts with the mode highlighted.
+    pub fn new() -> TitleMenu {
+        TitleMenu { selected: 0 }
+    }
+
+    /// The settings shown for the chosen mode, one row per inner `Vec`.
+    pub fn rows(world: &World) -> Vec<Vec<MenuItem>> {
+        let mut rows = vec![vec![MenuItem::Mode, MenuItem::Difficulty, MenuItem::Adaptive, MenuItem::Players]];
+        if world.play_mode == PlayMode::Practice {
+            rows.push(vec![MenuItem::PracticeStart, MenuItem::PracticeEnemies]);
+        }
+        rows
+    }
+
+    /// The highlighted setting.
+    pub fn current(&self, world: &World) -> MenuItem {
+        let items: Vec<MenuItem> = TitleMenu::rows(world).into_iter().flatten().collect();
+        items[self.selected % items.len()]
+    }
+
+    /// Highlights the next setting, or the previous one if `forward` is false.
+    pub fn select(&mut self, world: &World, forward: bool) {
+        let count = TitleMenu::rows(world).iter().map(|row| row.len()).sum::<usize>();
+        self.selected = if forward {
+            (self.selected + 1) % count
+        } else {
+            (self.selected + count - 1) % count
+        };
+    }
+
+    /// Changes the highlighted setting to its next value, or its previous
+    /// one if `forward` is false.
+    pub fn change(&mut self, world: &mut World, forward: bool) {
+        match self.current(world) {
+            MenuItem::Mode => {
+                world.play_mode = if forward { world.play_mode.next() } else { world.play_mode.previous() };
+            }
+            MenuItem::Difficulty => {
+                world.difficulty = if forward { world.difficulty.next() } else { world.difficulty.previous() };
+            }
+            MenuItem::Adaptive => world.adaptive.enabled = !world.adaptive.enabled,
+            MenuItem::Players => {
+                world.player_count = if forward {
+                    world.player_count % MAX_PLAYERS + 1
+                } else {
+                    (world.player_count + MAX_PLAYERS - 2) % MAX_PLAYERS + 1
+                };
+            }
+            MenuItem::PracticeStart => {
+                if forward {
+                    world.practice.later_start();
+                } else {
+                    world.practice.earlier_start();
+                }
+            }
+            MenuItem::PracticeEnemies => {
+                let enemies = world.practice.enemies;
+                world.practice.enemies = if forward { enemies.next() } else { enemies.previous() };
+            }
+        }
+    }
+
+    /// Text shown for a setting.
+    pub fn label(world: &World, item: MenuItem) -> String {
+        match item {
+            MenuItem::Mode => format!("< {} >", world.play_mode.name()),
+            MenuItem::Difficulty => format!("< {} >", world.difficulty.name()),
+            MenuItem::Adaptive => format!("ADAPTIVE {}", if world.adaptive.enabled { "ON" } else { "OFF" }),
+            MenuItem::Players => match world.player_count {
+                1 => "1 PLAYER".to_string(),
+                count => format!("{} PLAYERS", count),
+            },
+            MenuItem::PracticeStart => format!("START < {}:00 >", world.practice.start_minutes),
+            MenuItem::PracticeEnemies => format!("ENEMIES < {} >", world.practice.enemies.name()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn practice_settings_only_show_for_practice() {
+        let mut world = World::new(1024, 768);
+        let mut menu = TitleMenu::new();
+        assert_eq!(TitleMenu::rows(&world).len(), 1);
+
+        menu.change(&mut world, false);
+        assert_eq!(world.play_mode, PlayMode::Practice);
+        assert_eq!(TitleMenu::rows(&world).len(), 2);
+
+        // Walk over to the enemy set and change it
+        menu.select(&world, false);
+        assert_eq!(menu.current(&world), MenuItem::PracticeEnemies);
+        menu.change(&mut world, true);
+        assert_eq!(world.practice.enemies.name(), "BUGS");
+    }
+}
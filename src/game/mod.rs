@@ -11,18 +11,72 @@ extern crate rand;
 
 // Modules and namespaces
 use ggez::{Context, GameResult};
-use ggez::event::{self, Keycode, Mod};
+use ggez::event::{self, Axis, Button, Keycode, Mod};
 use ggez::{audio, graphics};
 use std;
+mod adaptive;
+mod attract;
+mod bot;
+mod combo;
+mod components;
+mod difficulty;
 mod entity;
 mod entity_spawner;
+mod entity_store;
+mod events;
+mod fnv;
+#[cfg(test)]
+mod golden;
+mod leaderboard;
+#[cfg(feature = "leaderboard-server")]
+pub mod leaderboard_server;
+mod menu;
+mod netplay;
+mod play_mode;
+mod player;
+mod profile;
+mod render;
+mod replay;
+mod rng;
+mod score_viewer;
 mod scores;
-use self::entity_spawner::EntitySpawner;
-use self::entity::{EntityType, Lifetime, Movement};
-use self::scores::Scores;
+pub mod sim;
+mod snapshot;
+mod stats;
+mod status;
+mod systems;
+pub mod verify;
+mod weapons;
+mod world;
+use self::attract::Attract;
+use self::difficulty::Difficulty;
+use self::entity::EntityType;
+use self::events::GameEvent;
+use self::menu::TitleMenu;
+use self::play_mode::PlayMode;
+use self::profile::{Achievement, Profile};
+use self::replay::Replay;
+use self::score_viewer::ScoreViewer;
+use self::scores::{Origin, ScoreRecord, Scores};
+use self::snapshot::Snapshot;
+use self::world::World;
 
 // Constants
 const ANIMATION_FRAMERATE: f64 = 2.283 * 2.0;
+/// Let the bot play as player 1, for demos and soak testing by hand
+const AUTOPLAY: bool = false;
+/// Print every change adaptive difficulty makes, for tuning
+const LOG_ADAPTIVE_DIFFICULTY: bool = false;
+/// Print a checksum of the world every few seconds of an online run, to
+/// check that both peers agree
+const LOG_NETPLAY_CHECKSUMS: bool = false;
+/// How long the player can't be hurt after setting off a bomb (in ms)
+const BOMB_INVULNERABILITY: i64 = 2_000;
+/// Damage a bomb blast deals to a boss caught in it
+const BOMB_BOSS_DAMAGE: i32 = 10;
+const BOMB_BLAST_RADIUS: f32 = 900.0;
+/// How quickly a bomb blast expands (in pixels per second)
+const BOMB_BLAST_SPEED: f32 = 1_500.0;
 const BOSS_BULLET_COOLDOWN: i64 = 170;
 const BOSS_BULLET_NUMBER: i64 = 4;
 const DEFAULT_FONT: &str = "/font/PressStart2P.ttf";
@@ -42,27 +96,58 @@ const ENEMY_NAMES: [&str;7] = [
 	"DEADLOCK",
 	"RACE CONDITION",
 ];
-/// The closer this is to zero, the faster enemies will spawn at maximum difficulty
+/// Graze meter gained for each bullet grazed
+const GRAZE_CHARGE: u32 = 4;
+/// How long the player glows after grazing a bullet (in ms)
+const GRAZE_FLASH: i64 = 150;
+/// How far past the player's hitbox a bullet counts as a graze (in pixels)
+const GRAZE_MARGIN: f32 = 36.0;
+/// A full graze meter gives the player a bomb
+const GRAZE_METER_MAX: u32 = 100;
+const GRAZE_POINTS: i32 = 5;
+/// The closer this is to zero, the faster enemies will spawn at maximum difficulty.
+/// Used by the normal difficulty, the others have their own presets.
 const MAX_DIFFICULTY: f32 = 0.15;
+const MAX_BOMBS: u32 = 5;
+/// Top runs shown from the online leaderboard
+const MAX_ONLINE_SCORES: usize = 10;
+/// High scores shown on the title screen while it cycles
+const MAX_TITLE_SCORES: usize = 10;
 const MAX_UPGRADE_LEVEL: u32 = 12;
 const PIXEL_SKIP: i32 = 2;
+const POPUP_LIFETIME: i64 = 800;
 const PLAYER_BULLET_COOLDOWN: i64 = 200;
 const PLAYER_BULLET_SPEED: f32 = 600.0;
+/// Room taken by player 2's weapon, bomb and graze display on the right
+const PLAYER_HUD_WIDTH: f32 = 300.0;
+/// Where F5 saves a practice run and F9 loads it back
+const QUICKSAVE_FILE: &str = "quicksave.json";
+/// How far a gamepad stick has to be pushed to move the crab
+const STICK_DEADZONE: i16 = 8_000;
+/// Where a run is kept when quitting mid-game, to resume from the title
+const SUSPEND_FILE: &str = "suspend.json";
 const SHOW_INPUT_DEBUG: bool = false;
 const SHUTOFF_LIFETIME: i64 = 500;
+const STARTING_BOMBS: u32 = 2;
 const SPLAT_LIFETIME: i64 = 500;
 /// The game will slowly ramp up to maximum difficulty over this amount of time
+/// on normal and easy
 const SECONDS_UNTIL_MAX_DIFFICULTY: u64 = 8 * 60; 
+/// How long an achievement stays up on screen once unlocked (in ms)
+const TOAST_MS: i64 = 4_000;
 
 static mut GOD_MODE: bool = false;
 
-/// Represents the state of player controls
+/// Represents the state of one player's controls
+#[derive(Default, Clone, Copy)]
 struct Input {
     left: bool,
     right: bool,
     up: bool,
     down: bool,
     shoot: bool,
+    /// Set for the one update after the bomb key is pressed
+    bomb: bool,
 }
 
 /// Game modes for switching between menu display and the main game loop.
@@ -81,28 +166,57 @@ pub struct MainState {
 	delta_ms: u64,
 	/// Time elapsed since beginning of game (in ms).
 	elapsed_ms: u64,
-	/// Vector of all drawable game entities.
-    entities: Vec<entity::Entity>,
 	/// Current game mode determining whether to display menu or game
 	game_mode: GameMode,
-	/// List of recent high scores.
+	/// High scores of every mode and difficulty
 	high_scores: Scores,
-	/// Player input state
-	input: Input,
+	/// Which high scores the browser on the title screen is showing
+	score_viewer: ScoreViewer,
+	/// Gamepads in use, in the order they were first touched. The first
+	/// one controls player 1 and the second player 2.
+	gamepads: Vec<i32>,
+	/// Input state of each player
+	inputs: [Input; player::MAX_PLAYERS],
 	/// Hash map of text label graphics for enemy names
 	labels: std::collections::HashMap<String, graphics::Text>,
+	/// Online leaderboard to send finished runs to, if one was given on
+	/// the command line
+	leaderboard: Option<leaderboard::Client>,
+	/// Top runs fetched from the leaderboard for the title screen, or why
+	/// they couldn't be. `None` while they are on their way.
+	online_scores: Option<Result<Vec<leaderboard::Entry>, String>>,
+	/// Where the top runs arrive from the thread fetching them
+	online_fetch: Option<std::sync::mpsc::Receiver<Result<Vec<leaderboard::Entry>, String>>>,
+	/// Settings for playing online, if asked for on the command line
+	netplay: Option<netplay::Options>,
+	/// Connection to the other player while an online run is going
+	session: Option<netplay::Session<netplay::LossyTransport<netplay::UdpTransport>>>,
+	/// Settings on the title screen
+	menu: TitleMenu,
+	/// Cycles the title screen through the logo, high scores and a demo
+	/// while nobody is playing
+	attract: Attract,
 	/// Means of exiting the game
 	quit: bool,
-	/// Random number generator passed to certain functions
-	rng: rand::ThreadRng,
-	/// Current player score
-    score: i32,
+	/// Whether a run was suspended on quit and can be resumed
+	resumable: bool,
+	/// Lifetime stats and achievements of the user playing
+	profile: Profile,
+	/// Inputs of the run so far, to save with its score. Online, practice
+	/// and resumed runs aren't recorded.
+	replay: Option<Replay>,
+	/// Whether the debug keys were used during the run, which keeps it off
+	/// the high scores and the online leaderboard
+	cheated: bool,
+	/// Achievements unlocked during the run still waiting to be shown, with
+	/// how long the first has left on screen (in ms)
+	toasts: Vec<(Achievement, i64)>,
+	/// Font to use for enemy name tags and score popups
+	label_font: graphics::Font,
 	/// Font to use for player score
     score_font: graphics::Font,
 	/// Hash map of all game sounds and music, indexed by string name
 	sfx: std::collections::HashMap<&'static str, audio::Source>,
-	/// Generator for game objects like enemies and bullets
-	spawner: EntitySpawner,
 	/// Reference time for when the game began
 	start_time: std::time::SystemTime,
 	/// Hash map of game entity textures, indexed by the enum `EntityType`.
@@ -111,8 +225,8 @@ pub struct MainState {
 	textures: std::collections::HashMap<entity::EntityType, Vec<graphics::Image>>,
 	/// Game logo
 	title: graphics::Image,
-    gun_level: u32,
-    shield_active: bool
+	/// All game entities and the state of the current run
+	world: World,
 }
 
 /// This is the object ggez will update with the screen.
@@ -129,28 +243,38 @@ impl MainState {
             background: graphics::Image::new(ctx, "/texture/background_tiled.png").unwrap(),
 			delta_ms: 0,
 			elapsed_ms: 0,
-            entities: Vec::new(),
 			game_mode: GameMode::Menu,
-			high_scores: Scores::new("scores.txt"),
-			input: Input {
-				left: false, 
-				right: false, 
-				up: false,
-				down: false,
-				shoot: false,
-			},
+			high_scores: load_high_scores(),
+			score_viewer: ScoreViewer::new(PlayMode::Classic, Difficulty::Normal),
+			gamepads: Vec::new(),
+			inputs: [Input::default(); player::MAX_PLAYERS],
 			labels: std::collections::HashMap::new(),
+			leaderboard: leaderboard::Client::from_args(std::env::args()),
+			online_scores: None,
+			online_fetch: None,
+			netplay: match netplay::Options::from_args(std::env::args()) {
+				Ok(options) => options,
+				Err(e) => {
+					println!("{}", e);
+					std::process::exit(1);
+				}
+			},
+			session: None,
+			menu: TitleMenu::new(),
+			attract: Attract::new(),
 			quit: false,
-			rng: rand::thread_rng(),
-            score: 0,
-            gun_level: 0,
-            shield_active: false,
+			resumable: std::path::Path::new(SUSPEND_FILE).exists(),
+			profile: load_profile(),
+			replay: None,
+			cheated: false,
+			toasts: Vec::new(),
+            label_font: graphics::Font::new(ctx, DEFAULT_FONT, ENEMY_FONT_SIZE)?,
             score_font,
 			sfx: std::collections::HashMap::new(),
-			spawner: EntitySpawner::new(ctx),
 			start_time:  std::time::SystemTime::now(),
 			textures: std::collections::HashMap::new(),
             title: graphics::Image::new(ctx, "/texture/title.png").unwrap(),
+			world: World::new(ctx.conf.window_mode.width, ctx.conf.window_mode.height),
 		};
 		
 		// Set up textures
@@ -205,15 +329,15 @@ impl MainState {
 		s.sfx.insert("upgrade", audio::Source::new(ctx, "/sounds/upgrade.wav")?);
 		s.sfx.insert("shield", audio::Source::new(ctx, "/sounds/shield.wav")?);
 		s.sfx.insert("powerbomb", audio::Source::new(ctx, "/sounds/powerbomb.wav")?);
+		s.sfx.insert("graze", audio::Source::new(ctx, "/sounds/shield.wav")?);
 		s.sfx.insert("win", audio::Source::new(ctx, "/sounds/Tejaswi-Solstice.ogg")?);
 		
 		// Generate labels
-		let entity_font = graphics::Font::new(ctx, DEFAULT_FONT, ENEMY_FONT_SIZE)?;
 		for name in ENEMY_NAMES.iter() {
-			let text = graphics::Text::new(ctx, name, &entity_font).unwrap();
+			let text = graphics::Text::new(ctx, name, &s.label_font).unwrap();
 			s.labels.insert(name.to_string(), text);
 		}
-		let bsod_text = graphics::Text::new(ctx, "BSOD", &entity_font).unwrap();
+		let bsod_text = graphics::Text::new(ctx, "BSOD", &s.label_font).unwrap();
 		s.labels.insert("BSOD".to_string(), bsod_text);
 
 		// Begin playing intro music
@@ -223,53 +347,67 @@ impl MainState {
 
         Ok(s)
     }
+
+	/// The player a gamepad controls, handing out the free slots to new
+	/// gamepads as they are first used.
+	fn gamepad_slot(&mut self, instance_id: i32) -> Option<usize> {
+		if let Some(slot) = self.gamepads.iter().position(|&id| id == instance_id) {
+			return Some(slot);
+		}
+		if self.gamepads.len() < player::MAX_PLAYERS {
+			self.gamepads.push(instance_id);
+			return Some(self.gamepads.len() - 1);
+		}
+		None
+	}
 }
 	
 /// This function starts a new game
 pub fn new_game(state: &mut MainState, ctx: &mut Context) {
-    // Clear out old entities
-    state.entities.clear();
-
-	// Reset time
-	state.elapsed_ms = 0;
-	state.start_time = std::time::SystemTime::now();
+	// Reset time, starting partway into the run if the mode asks for it
+	let start_ms = state.world.play_mode.start_ms(&state.world.practice);
+	state.elapsed_ms = start_ms;
+	state.start_time = std::time::SystemTime::now() - std::time::Duration::from_millis(start_ms);
 	
-	// Reset spawner
-	state.spawner.reset();
-	
-    // Reset the score and powerups
-    state.score = 0;
-    state.gun_level = 1;
-    state.shield_active = false;
-
-    // Create a new player object
-    let player = entity::Entity {
-        angle: 0.0,
-        bounds: graphics::Rect {
-            x: 60.0,
-            y: 40.0,
-            w: 10.0,
-            h: 18.0,
-        },
-        bullet_cooldown: PLAYER_BULLET_COOLDOWN,
-        damage: 0,
-        entity_type: entity::EntityType::Player,
-        hp: 5,
-        lifetime: Lifetime::Forever,
-        movement: Movement::None,
-        name: "Ferris".to_string(),
-        seed: 0.0,
-        timer: 0,
-        vel: 400.0,
-        x: (ctx.conf.window_mode.width as f32 / 2.0)
-            - (state.textures[&entity::EntityType::Player][0].width() as f32 / 2.0),
-        y: ctx.conf.window_mode.height as f32
-            - state.textures[&entity::EntityType::Player][0].height() as f32,
-    };
+	// Online runs are always two players, with both peers seeding the
+	// world the same way
+	if let Some(ref mut options) = state.netplay {
+		state.session = match options.connect(start_ms, netplay::Settings::of(&state.world)) {
+			Ok((session, seed)) => {
+				state.world.player_count = 2;
+				state.world.seed(seed);
+				unsafe { GOD_MODE = false; }
+				Some(session)
+			}
+			Err(e) => {
+				println!("Could not start netplay, playing locally: {}", e);
+				None
+			}
+		};
+	}
+	// Local runs get a seed of their own, sent along with the score so the
+	// run can be checked
+	if state.session.is_none() {
+		state.world.seed(rand::random());
+	}
 
-    state.entities.push(player);
+	// Clear out old entities, reset the score and powerups and
+	// create a new player object
+	state.world.reset();
+	state.toasts.clear();
+	state.replay = match state.world.play_mode {
+		PlayMode::Practice => None,
+		_ => Some(Replay::start(&state.world)),
+	};
+	state.cheated = unsafe { GOD_MODE };
+	if state.world.play_mode != PlayMode::Practice {
+		state.profile.start_run();
+	}
+	start_bgm(state, ctx);
+}
 
-    // Stop intro music and begin bgm
+/// Stops the intro music and begins the bgm
+fn start_bgm(state: &mut MainState, ctx: &mut Context) {
     if !DISABLE_SFX {
         state.sfx["intro"].stop();
         // The `.stop()` method for a ggez audio source doesn't seem to work
@@ -281,213 +419,342 @@ pub fn new_game(state: &mut MainState, ctx: &mut Context) {
     }
 }
 
-/// This function handles all entity-entity interactions when colliding
-fn handle_collisions(state: &mut MainState) {
-	let mut play_hit_sound = false;
-	// Iterate through all entities
-	for entity_idx in 0..state.entities.len() {
-	
-		match state.entities[entity_idx].entity_type {
-		
-			// In the case of player
-			EntityType::Player => {
-                for threat_idx in 0..state.entities.len() {
-                    match state.entities[threat_idx].entity_type {
-                        
-						// When player collides with enemy
-						EntityType::Enemy | EntityType::EnemyBlueScreen | EntityType::Boss | EntityType::Special => {
-                            if colliding(state, entity_idx, threat_idx) {
-							
-								// If shield is active
-								if state.shield_active {
-									
-									// Remove shield
-									state.shield_active = false;
-								} else {
-								
-									// Otherwise hurt player
-									unsafe {
-										if !GOD_MODE {
-											state.entities[entity_idx].hp -= state.entities[threat_idx].damage;
-											if state.gun_level > 1 {
-												state.gun_level -= 1;
-											}
-										}
-									}
-								}
-								
-								// Destroy enemies other than boss
-								if state.entities[threat_idx].entity_type != EntityType::Boss {
-									state.entities[threat_idx].lifetime = Lifetime::Milliseconds(0);
-								}
-								
-								// SFX
-								play_hit_sound = true;
-                            }
-                        },
-						
-						// When player collides with enemy bullet
-                        EntityType::EnemyBullet => {
-                            if colliding(state, entity_idx, threat_idx) {
-								
-								// If shield is active
-								if state.shield_active {
-								
-									// Disable shield
-									state.shield_active = false;
-								
-								} else {
-								
-									// Otherwise hurt player
-									unsafe {
-										if !GOD_MODE {
-											state.entities[entity_idx].hp -=
-											state.entities[threat_idx].damage;
-										}
-									}
-								}
-								
-								// Destroy bullet
-                                state.entities[threat_idx].lifetime = Lifetime::Milliseconds(0);
-                                
-								// SFX
-								play_hit_sound = true;
-                            }
-                        },
-						
-						// When player collides with power bomb
-                        EntityType::Powerbomb => {
-                            if colliding(state, entity_idx, threat_idx) {
-							
-								// Destroy all bullets and enemies other than boss
-                                for enemy_idx in 0..state.entities.len() {
-                                    if state.entities[enemy_idx].entity_type == EntityType::Enemy
-                                        || state.entities[enemy_idx].entity_type
-                                            == EntityType::EnemyBlueScreen
-										|| state.entities[enemy_idx].entity_type == EntityType::Special
-                                        || state.entities[enemy_idx].entity_type
-                                            == EntityType::EnemyBullet
-                                    {
-                                        state.entities[enemy_idx].lifetime =
-                                            Lifetime::Milliseconds(0);
-                                        state.entities[enemy_idx].hp = 0;
-                                    }
-                                }
-								
-								if !DISABLE_SFX {
-									state.sfx["powerbomb"].play().unwrap();
-								}
-								
-								// Kill powerbomb
-                                state.entities[threat_idx].lifetime = Lifetime::Milliseconds(0);
-                            }
-                        },
-						
-						// When player collides with gun upgrade
-                        EntityType::GunUpgrade => {
-                            if colliding(state, entity_idx, threat_idx) {
-							
-                                // Upgrade the player's gun
-                                if state.gun_level < MAX_UPGRADE_LEVEL {
-									state.gun_level += 1;
-								}
-									
-								if !DISABLE_SFX {
-									state.sfx["upgrade"].play().unwrap();
-								}
-								
-								// Remove upgrade
-                                state.entities[threat_idx].lifetime = Lifetime::Milliseconds(0);
-                            }
-                        },
-						
-						// When player collides with shield
-                        EntityType::Shield => {
-                            if colliding(state, entity_idx, threat_idx) {
-							
-                                // Enable shield
-                                state.shield_active = true;
-								
-								if !DISABLE_SFX {
-									state.sfx["shield"].play().unwrap();
-								}
-								
-								// Remove shield powerup
-                                state.entities[threat_idx].lifetime = Lifetime::Milliseconds(0);
-                            }
-                        },
-                        
-						_ => (),
-                    }
-				}
-            },
-			
-			// In the case of an enemy or boss
-			EntityType::Enemy | EntityType::EnemyBlueScreen | EntityType::Boss | EntityType::Special => {
-				for threat_idx in 0..state.entities.len() {
-					match state.entities[threat_idx].entity_type {
-						
-						// When an enemy collides with a player bullet
-						EntityType::PlayerBullet => {
-							if colliding(state, entity_idx, threat_idx) {
-								
-								// Hurt the enemy by bullet damage amount
-								state.entities[entity_idx].hp -= state.entities[threat_idx].damage;
-								
-								// Kill the bullet
-								state.entities[threat_idx].lifetime = Lifetime::Milliseconds(0);
-								
-								play_hit_sound = true;
-                            }
-						},
-						
-						_ => (),
-					}
-				}
-			},
+/// Stops the bgm and replays the intro music
+fn start_intro(state: &mut MainState, ctx: &mut Context) {
+    if !DISABLE_SFX {
+        state.sfx["bgm"].stop();
+        // The `.stop()` method for a ggez audio source doesn't seem to work
+        // correctly, so this is an ugly method of stopping and restarting the
+        // audio. Reload from disk and overwrite existing. Eeewww!
+        *state.sfx.get_mut("intro").unwrap() =
+            audio::Source::new(ctx, "/sounds/intro.ogg").expect("Could not load intro music");
+        state.sfx["intro"].play().unwrap();
+    }
+}
 
-			_ => (),
+/// Picks a run back up from a snapshot, with the clock where it left off.
+fn restore_game(state: &mut MainState, snapshot: Snapshot) {
+	state.world = snapshot.world;
+	state.replay = snapshot.replay;
+	state.cheated = snapshot.cheated || unsafe { GOD_MODE };
+	state.elapsed_ms = snapshot.elapsed_ms;
+	state.delta_ms = 0;
+	state.start_time = std::time::SystemTime::now() - std::time::Duration::from_millis(snapshot.elapsed_ms);
+}
+
+/// Keeps the current run so it can be resumed from the title screen.
+/// Online runs can't be suspended, as the other player carries on.
+fn suspend_game(state: &mut MainState) {
+	if state.session.is_some() {
+		return;
+	}
+	let snapshot = Snapshot::capture(&state.world, state.elapsed_ms, state.cheated, state.replay.as_ref());
+	match snapshot.save(SUSPEND_FILE) {
+		Ok(()) => state.resumable = true,
+		Err(e) => println!("Could not suspend the run: {}", e),
+	}
+	save_profile(state);
+}
+
+/// Resumes the run suspended on the last quit. The save is removed either
+/// way, so a run can only be resumed once.
+fn resume_game(state: &mut MainState, ctx: &mut Context) {
+	let loaded = Snapshot::load(SUSPEND_FILE);
+	let _ = std::fs::remove_file(SUSPEND_FILE);
+	state.resumable = false;
+	match loaded {
+		Ok(snapshot) => {
+			restore_game(state, snapshot);
+			state.game_mode = GameMode::Game;
+			start_bgm(state, ctx);
 		}
+		Err(e) => println!("Could not resume the run: {}", e),
+	}
+}
+
+/// Practice runs can be saved with F5 and loaded back with F9.
+fn quicksave(state: &mut MainState, load: bool) {
+	let message = if load {
+		match Snapshot::load(QUICKSAVE_FILE) {
+			Ok(snapshot) => {
+				restore_game(state, snapshot);
+				"LOADED"
+			}
+			Err(e) => {
+				println!("Could not load the quicksave: {}", e);
+				"NO SAVE"
+			}
+		}
+	} else {
+		let snapshot = Snapshot::capture(&state.world, state.elapsed_ms, state.cheated, state.replay.as_ref());
+		match snapshot.save(QUICKSAVE_FILE) {
+			Ok(()) => "SAVED",
+			Err(e) => {
+				println!("Could not save: {}", e);
+				"SAVE FAILED"
+			}
+		}
+	};
+	let popup = state.world.spawner.spawn_popup(state.world.screen_width / 2.0, state.world.screen_height / 2.0, message.to_string());
+	state.world.spawn(popup);
+}
+
+/// Sets up a game for the bot to play on the title screen, starting
+/// partway into the run.
+fn start_demo(state: &mut MainState) {
+	state.elapsed_ms = attract::DEMO_START_MS;
+	state.start_time = std::time::SystemTime::now() - std::time::Duration::from_millis(attract::DEMO_START_MS);
+	state.world.reset();
+}
+
+/// Lets the bot play one update of the demo, with the sound off. The demo
+/// ends early once every player is out.
+fn play_demo(state: &mut MainState) {
+	let inputs: Vec<Input> = (0..state.world.players.len())
+		.map(|slot| bot::think(&state.world, slot))
+		.collect();
+	state.world.update(&inputs, state.elapsed_ms, state.delta_ms);
+	state.world.events.clear();
+	if !state.world.player_alive() {
+		state.attract.next();
+	}
+}
+
+/// Handles what happened in the world since the last frame: adds it to
+/// the user's profile, queues up any achievements unlocked and plays the
+/// sound effects, each sound once.
+fn handle_world_events(state: &mut MainState, ctx: &mut Context, elapsed_ms: u64) {
+	let events: Vec<GameEvent> = state.world.events.drain(..).collect();
+	if state.world.play_mode != PlayMode::Practice {
+		let unlocked = state.profile.record(&events, &state.world, elapsed_ms, state.delta_ms);
+		state.toasts.extend(unlocked.into_iter().map(|achievement| (achievement, TOAST_MS)));
+	}
+	if let Some(toast) = state.toasts.first_mut() {
+		toast.1 -= state.delta_ms as i64;
+	}
+	if state.toasts.first().is_some_and(|toast| toast.1 <= 0) {
+		state.toasts.remove(0);
 	}
-	
 
-	if !DISABLE_SFX && play_hit_sound {
-		state.sfx["hit"].play().unwrap();
+	for name in events::sounds(&events) {
+		if DISABLE_SFX {
+			continue;
+		}
+		match name {
+			// Nasty means of playing sounds quickly on the same channel.
+			// The `.stop()` method for a ggez audio source doesn't seem to work
+			// correctly, so reload from disk and overwrite existing. Eeewww!
+			"enemy_shot" | "player_shot" | "explode" => {
+				*state.sfx.get_mut(name).unwrap() =
+					audio::Source::new(ctx, format!("/sounds/{}.wav", name))
+						.expect("Could not load sound effect");
+				state.sfx[name].play().unwrap();
+			},
+			_ => state.sfx[name].play().unwrap(),
+		}
 	}
 }
 
-/// Returns true if the two entities are colliding. Collision is calculated
-/// using the `bounds` dimensions of the entity, not the sprite.
-fn colliding(state: &mut MainState, a: usize, b: usize) -> bool{
-	// If bounding boxes collide
-	let e1_x = state.entities[a].x + state.entities[a].bounds.x;
-	let e1_w = state.entities[a].bounds.w;
-	let e1_y = state.entities[a].y + state.entities[a].bounds.y;
-	let e1_h = state.entities[a].bounds.h;
-	let e2_x = state.entities[b].x + state.entities[b].bounds.x;
-	let e2_w = state.entities[b].bounds.w;
-	let e2_y = state.entities[b].y + state.entities[b].bounds.y;
-	let e2_h = state.entities[b].bounds.h;
-	if e1_x < e2_x + e2_w &&
-		e1_x + e1_w > e2_x &&
-		e1_y < e2_y + e2_h &&
-		e1_h + e1_y > e2_y {
-			true
+/// Loads the high scores of every mode and difficulty, bringing over the
+/// score file of older versions the first time
+fn load_high_scores() -> Scores {
+	if std::path::Path::new(scores::SCORES_FILE).exists() {
+		return match Scores::load(scores::SCORES_FILE) {
+			Ok(scores) => scores,
+			Err(e) => {
+				println!("Could not load the high scores: {}", e);
+				Scores::new()
+			}
+		};
+	}
+
+	let mut high_scores = Scores::new();
+	if !std::path::Path::new(scores::LEGACY_SCORES_FILE).exists() {
+		return high_scores;
 	}
-	else {
-		return false;
+	match high_scores.import_legacy(scores::LEGACY_SCORES_FILE) {
+		Ok(()) => {
+			if let Err(e) = high_scores.save(scores::SCORES_FILE) {
+				println!("Could not save the high scores: {}", e);
+			}
+		}
+		Err(e) => println!("Could not bring over {}: {}", scores::LEGACY_SCORES_FILE, e),
 	}
+	high_scores
 }
 
-/// Write high score
+/// Write high score, along with the replay of the run if it has one and
+/// the score makes the table
 fn save_score(state: &mut MainState) {
-	let user = std::env::var("USERNAME").unwrap();
-    let total = state.elapsed_ms / 1000;
-	let minutes = total / 60;
-	let seconds = total % 60;
-	let time = format!("{:02}:{:02}", minutes, seconds);
-	state.high_scores.add_score(state.score, user.to_string(), time.to_string());
-	state.high_scores.save("scores.txt");
+	let user = user_name();
+	// Online runs are recorded at the end, with the inputs the world last
+	// ran each frame with
+	if let (Some(session), Some(replay)) = (state.session.as_ref(), state.replay.as_mut()) {
+		session.record(replay);
+	}
+	// A recorded run ends at its last update, so the time matches the replay
+	let elapsed_ms = state.replay.as_ref().map_or(state.elapsed_ms, |replay| replay.end_ms());
+	if !state.world.play_mode.keeps_scores() {
+		return;
+	}
+	if state.cheated {
+		println!("Runs using the debug keys don't make the high scores");
+		return;
+	}
+	let seed = state.world.run_seed;
+	let date = std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)
+		.map_or(0, |since| since.as_secs());
+	let origin = match state.session {
+		Some(_) => Origin::Online,
+		None => Origin::Local,
+	};
+	let replay = match state.replay {
+		Some(_) => replay::replay_file(seed, date, elapsed_ms),
+		None => String::new(),
+	};
+	let record = ScoreRecord {
+		score: state.world.score,
+		name: user.clone(),
+		time_ms: elapsed_ms,
+		date,
+		play_mode: state.world.play_mode,
+		difficulty: state.world.difficulty,
+		max_gun_level: state.world.players.iter()
+			.flat_map(|player| player.arsenal.levels.values())
+			.cloned()
+			.max()
+			.unwrap_or(1),
+		kills: state.world.stats.kills.values().sum(),
+		max_combo: state.world.combo.max,
+		seed,
+		replay: replay.clone(),
+		origin,
+		version: env!("CARGO_PKG_VERSION").to_string(),
+	};
+	let dropped = state.high_scores.add(record);
+	if let Err(e) = state.high_scores.save(scores::SCORES_FILE) {
+		println!("Could not save the high scores: {}", e);
+	}
+
+	// Only the replays of scores still on the table are kept
+	let mut kept = true;
+	for old in dropped {
+		if old.replay == replay {
+			kept = false;
+		} else if !old.replay.is_empty() {
+			let _ = std::fs::remove_file(&old.replay);
+		}
+	}
+	if let (true, Some(recorded)) = (kept, state.replay.as_ref()) {
+		if let Err(e) = recorded.save(&replay) {
+			println!("Could not save the replay: {}", e);
+		}
+	}
+	submit_run(state, user, elapsed_ms);
+}
+
+/// Sends the finished run to the online leaderboard, if there is one. It
+/// goes from a thread of its own so a slow server doesn't hold up the game.
+/// Runs without a replay can't be checked, so they aren't sent.
+fn submit_run(state: &MainState, user: String, elapsed_ms: u64) {
+	let (client, recorded) = match (state.leaderboard.as_ref(), state.replay.as_ref()) {
+		(Some(client), Some(recorded)) => (client.clone(), recorded),
+		_ => return,
+	};
+	let entry = leaderboard::Entry {
+		user,
+		mode: state.world.play_mode.name().to_string(),
+		difficulty: state.world.difficulty.name().to_string(),
+		score: state.world.score,
+		time_ms: elapsed_ms,
+		max_combo: state.world.combo.max,
+		seed: state.world.run_seed,
+		replay_hash: recorded.hash(),
+	};
+	std::thread::spawn(move || match client.submit(&entry) {
+		Ok(rank) => println!("Placed #{} on the online leaderboard", rank.rank),
+		Err(e) => println!("Could not submit the run to the leaderboard: {}", e),
+	});
+}
+
+/// Starts fetching the online top runs of the selected mode and
+/// difficulty, to be picked up by `poll_online_scores`.
+fn fetch_online_scores(state: &mut MainState) {
+	let client = match state.leaderboard {
+		Some(ref client) => client.clone(),
+		None => return,
+	};
+	let mode = state.world.play_mode.name();
+	let difficulty = state.world.difficulty.name();
+	let (sender, receiver) = std::sync::mpsc::channel();
+	std::thread::spawn(move || {
+		let _ = sender.send(client.top(mode, difficulty, MAX_ONLINE_SCORES));
+	});
+	state.online_scores = None;
+	state.online_fetch = Some(receiver);
+}
+
+/// Picks up the online top runs once they have arrived.
+fn poll_online_scores(state: &mut MainState) {
+	let result = match state.online_fetch {
+		Some(ref receiver) => match receiver.try_recv() {
+			Ok(result) => result,
+			Err(std::sync::mpsc::TryRecvError::Empty) => return,
+			Err(std::sync::mpsc::TryRecvError::Disconnected) => Err("the fetch gave up".to_string()),
+		},
+		None => return,
+	};
+	state.online_scores = Some(result);
+	state.online_fetch = None;
+}
+
+/// Name of the user playing, for the high scores and their profile.
+fn user_name() -> String {
+	std::env::var("USERNAME")
+		.or_else(|_| std::env::var("USER"))
+		.unwrap_or("player".to_string())
+}
+
+/// Handles a key in the high-score browser. The arrows pick the mode and
+/// difficulty, tab switches between ordering by score and by time, and
+/// page up and down turn the pages. Returns true if the key was used.
+fn browse_scores(state: &mut MainState, keycode: Keycode) -> bool {
+	let viewer = &mut state.score_viewer;
+	match keycode {
+		Keycode::Left => viewer.change_mode(false),
+		Keycode::Right => viewer.change_mode(true),
+		Keycode::Up => viewer.change_difficulty(false),
+		Keycode::Down => viewer.change_difficulty(true),
+		Keycode::Tab => viewer.change_ranking(),
+		Keycode::PageUp => viewer.turn_page(&state.high_scores, false),
+		Keycode::PageDown => viewer.turn_page(&state.high_scores, true),
+		_ => return false,
+	}
+	// Stay up while it is being used
+	state.attract.show(attract::Screen::Records);
+	true
+}
+
+/// Loads the profile of the user playing, or starts a new one.
+fn load_profile() -> Profile {
+	let file = profile::profile_file(&user_name());
+	if !std::path::Path::new(&file).exists() {
+		return Profile::new();
+	}
+	match Profile::load(&file) {
+		Ok(profile) => profile,
+		Err(e) => {
+			println!("Could not load {}, starting a new profile: {}", file, e);
+			Profile::new()
+		}
+	}
+}
+
+fn save_profile(state: &MainState) {
+	if let Err(e) = state.profile.save(&profile::profile_file(&user_name())) {
+		println!("Could not save the profile: {}", e);
+	}
 }
 
 /// Update the state's `elapsed_ms` and `delta_ms`.
@@ -503,6 +770,303 @@ fn update_time(state: &mut MainState) {
     state.elapsed_ms = current_ms;
 }
 
+/// Draws a player's weapon, bombs, graze meter and power-ups, starting at
+/// `x` and `y`. The power-ups stack up from the bottom of the screen.
+fn draw_player_hud(state: &MainState, ctx: &mut Context, slot: usize, x: f32, y: f32) -> GameResult<()> {
+	let player = &state.world.players[slot];
+	let window_height = ctx.conf.window_mode.height;
+
+	// A downed player waits for the revive instead
+	if player.entity.is_none() {
+		let label = match player.revive_ms {
+			Some(ms) => format!("P{} BACK IN {}s", slot + 1, ms / 1000 + 1),
+			None => format!("P{} OUT", slot + 1),
+		};
+		let text = graphics::Text::new(ctx, &label, &state.score_font).unwrap();
+		graphics::draw(ctx, &text, graphics::Point2::new(x, y), 0.0)?;
+		return Ok(());
+	}
+
+	// Show the current weapon and its level
+	let arsenal = &player.arsenal;
+	let weapon = graphics::Text::new(ctx, &format!("{} LV{}",
+		arsenal.current.name(), arsenal.level()), &state.score_font).unwrap();
+	graphics::draw(ctx, &weapon, graphics::Point2::new(x, y), 0.0)?;
+
+	// Draw an icon and countdown for each power-up
+	let status_texture = &state.textures[&EntityType::Shield][0];
+	let mut row = 0;
+	for &kind in status::StatusKind::all().iter() {
+		let effect = match player.status.active.get(&kind) {
+			Some(effect) => effect,
+			None => continue,
+		};
+		let status_y = window_height as f32 - 40.0 * (row + 1) as f32;
+		let (r, g, b) = render::status_color(kind);
+		graphics::set_color(ctx, graphics::Color::new(r, g, b, 1.0))?;
+		graphics::draw_ex(
+			ctx,
+			status_texture,
+			graphics::DrawParam {
+				dest: graphics::Point2::new(x, status_y),
+				scale: graphics::Point2::new(0.5, 0.5),
+				..Default::default()
+			},
+		)?;
+		graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
+		let label = match (kind, effect.remaining_ms) {
+			(status::StatusKind::Shield, _) => format!("{} x{}", kind.name(), player.status.shield_charges()),
+			(status::StatusKind::ScoreMultiplier, Some(ms)) => format!("{} x{} {}s", kind.name(), effect.stacks, ms / 1000 + 1),
+			(_, Some(ms)) => format!("{} {}s", kind.name(), ms / 1000 + 1),
+			(_, None) => kind.name().to_string(),
+		};
+		let text = graphics::Text::new(ctx, &label, &state.score_font).unwrap();
+		graphics::draw(ctx, &text, graphics::Point2::new(x + 40.0, status_y + 6.0), 0.0)?;
+		row += 1;
+	}
+
+	// Draw the bomb stock as small powerbombs under the weapon
+	let bomb_texture = &state.textures[&EntityType::Powerbomb][0];
+	for i in 0..player.bombs {
+		graphics::draw_ex(
+			ctx,
+			bomb_texture,
+			graphics::DrawParam {
+				dest: graphics::Point2::new(x + i as f32 * bomb_texture.width() as f32 * 0.6, y + 30.0),
+				scale: graphics::Point2::new(0.5, 0.5),
+				..Default::default()
+			},
+		)?;
+	}
+
+	// Draw the graze meter under the bombs, filling towards the next bomb
+	let graze = graphics::Text::new(ctx, &format!("GRAZE {}", player.grazes), &state.score_font).unwrap();
+	graphics::draw(ctx, &graze, graphics::Point2::new(x, y + 70.0), 0.0)?;
+	let meter = graphics::Rect {
+		x,
+		y: y + 70.0 + graze.height() as f32 + 4.0,
+		w: 200.0,
+		h: 6.0,
+	};
+	graphics::rectangle(ctx, graphics::DrawMode::Line(1.0), meter)?;
+	graphics::set_color(ctx, graphics::Color::new(0.6, 0.9, 1.0, 1.0))?;
+	graphics::rectangle(ctx, graphics::DrawMode::Fill, graphics::Rect {
+		w: meter.w * player.graze_meter as f32 / GRAZE_METER_MAX as f32,
+		..meter
+	})?;
+	graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
+	Ok(())
+}
+
+/// Draws the user's lifetime stats down the left and their achievements
+/// down the right, with the locked ones greyed out.
+fn draw_stats(state: &MainState, ctx: &mut Context) -> GameResult<()> {
+	let window_width = ctx.conf.window_mode.width;
+	let profile = &state.profile;
+	let heading = graphics::Text::new(ctx, &format!("{} - LIFETIME STATS", user_name().to_uppercase()), &state.score_font).unwrap();
+	graphics::draw(ctx, &heading, graphics::Point2::new(window_width as f32 / 2.0 - heading.width() as f32 / 2.0, 60.0), 0.0)?;
+
+	let minutes = profile.play_ms / 60_000;
+	let mut lines = vec![
+		format!("{:16} {}", "RUNS", profile.runs),
+		format!("{:16} {}:{:02}", "PLAY TIME", minutes / 60, minutes % 60),
+		format!("{:16} {}", "GRAZES", profile.grazes),
+		format!("{:16} {}", "PICKUPS", profile.pickups.values().sum::<u32>()),
+		String::new(),
+		"KILLS".to_string(),
+	];
+	for name in ENEMY_NAMES.iter() {
+		lines.push(format!("{:16} {}", name, profile.kills.get(*name).cloned().unwrap_or(0)));
+	}
+	for (name, kills) in profile.kills.iter().filter(|&(name, _)| !ENEMY_NAMES.contains(&name.as_str())) {
+		lines.push(format!("{:16} {}", name, kills));
+	}
+	for (i, line) in lines.iter().enumerate() {
+		let text = graphics::Text::new(ctx, line, &state.score_font).unwrap();
+		graphics::draw(ctx, &text, graphics::Point2::new(120.0, 120.0 + i as f32 * 26.0), 0.0)?;
+	}
+
+	let x = window_width as f32 / 2.0 + 20.0;
+	let mut y = 120.0;
+	let text = graphics::Text::new(ctx, &format!("ACHIEVEMENTS {}/{}", profile.unlocked.len(), Achievement::all().len()), &state.score_font).unwrap();
+	graphics::draw(ctx, &text, graphics::Point2::new(x, y), 0.0)?;
+	for achievement in Achievement::all().iter() {
+		y += 26.0;
+		if !profile.unlocked.contains(achievement) {
+			graphics::set_color(ctx, graphics::Color::new(0.5, 0.5, 0.5, 1.0))?;
+		}
+		let text = graphics::Text::new(ctx, &format!("{} - {}", achievement.name(), achievement.description()), &state.score_font).unwrap();
+		graphics::draw(ctx, &text, graphics::Point2::new(x, y), 0.0)?;
+		graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
+	}
+
+	// How the user's runs have ended
+	y += 52.0;
+	let text = graphics::Text::new(ctx, "DEATHS", &state.score_font).unwrap();
+	graphics::draw(ctx, &text, graphics::Point2::new(x, y), 0.0)?;
+	for (&cause, deaths) in profile.deaths.iter() {
+		y += 26.0;
+		let text = graphics::Text::new(ctx, &format!("{:16} {}", profile::cause_name(cause), deaths), &state.score_font).unwrap();
+		graphics::draw(ctx, &text, graphics::Point2::new(x, y), 0.0)?;
+	}
+	Ok(())
+}
+
+/// Draws the high-score browser: a page of the table picked, with the keys
+/// to pick another.
+fn draw_records(state: &MainState, ctx: &mut Context) -> GameResult<()> {
+	let window_width = ctx.conf.window_mode.width;
+	let viewer = &state.score_viewer;
+	let heading = graphics::Text::new(ctx, &viewer.heading(), &state.score_font).unwrap();
+	graphics::draw(ctx, &heading, graphics::Point2::new(window_width as f32 / 2.0 - heading.width() as f32 / 2.0, 60.0), 0.0)?;
+
+	let row = |rank: &str, score: &str, name: &str, time: &str, gun: &str, kills: &str, mode: &str, difficulty: &str, day: &str, version: &str| {
+		format!("{:>3} {:>8} {:10} {:>5} {:>3} {:>5} {:11} {:6} {:10} {:7}", rank, score, name, time, gun, kills, mode, difficulty, day, version)
+	};
+	let header = row("#", "SCORE", "NAME", "TIME", "GUN", "KILLS", "MODE", "DIFF", "DATE", "VERSION");
+	let text = graphics::Text::new(ctx, &header, &state.label_font).unwrap();
+	graphics::draw(ctx, &text, graphics::Point2::new(50.0, 120.0), 0.0)?;
+	let entries = viewer.entries(&state.high_scores);
+	for (i, &(rank, record)) in entries.iter().enumerate() {
+		let line = row(
+			&rank.to_string(),
+			&record.score.to_string(),
+			&record.name,
+			&record.time(),
+			&record.max_gun_level.to_string(),
+			&record.kills.to_string(),
+			record.play_mode.name(),
+			record.difficulty.name(),
+			&record.day(),
+			&record.version,
+		);
+		let text = graphics::Text::new(ctx, &line, &state.label_font).unwrap();
+		graphics::draw(ctx, &text, graphics::Point2::new(50.0, 150.0 + i as f32 * 24.0), 0.0)?;
+	}
+	if entries.is_empty() {
+		let text = graphics::Text::new(ctx, "- NO SCORES YET -", &state.score_font).unwrap();
+		graphics::draw(ctx, &text, graphics::Point2::new(window_width as f32 / 2.0 - text.width() as f32 / 2.0, 200.0), 0.0)?;
+	}
+
+	let footer = format!(
+		"PAGE {}/{}   ARROWS - TABLE   TAB - ORDER   PG UP/DN - PAGE",
+		viewer.page + 1,
+		viewer.pages(&state.high_scores)
+	);
+	let text = graphics::Text::new(ctx, &footer, &state.label_font).unwrap();
+	graphics::draw(ctx, &text, graphics::Point2::new(window_width as f32 / 2.0 - text.width() as f32 / 2.0, 530.0), 0.0)?;
+	Ok(())
+}
+
+/// Draws a run in progress: the entities, lives, score and each player's HUD.
+fn draw_game(state: &MainState, ctx: &mut Context) -> GameResult<()> {
+	let window_width = ctx.conf.window_mode.width;
+	let window_height = ctx.conf.window_mode.height;
+
+	// Draw two layers of two background copies staggered according to elapsed_ms
+	let (shake_x, shake_y) = state.world.screen_shake(state.elapsed_ms);
+	let background_y = ( (state.elapsed_ms/40%1920) as i32 / PIXEL_SKIP * PIXEL_SKIP ) as f32 + shake_y;
+	graphics::draw(ctx, &state.background, graphics::Point2::new(shake_x, background_y), 0.0)?;
+	graphics::draw(ctx, &state.background, graphics::Point2::new(shake_x, -1920.0 + background_y), 0.0)?;
+
+	// Draw all entities
+	render::render_system(ctx, state)?;
+
+	// Draw each player's life graphics, one row per player
+	let life_texture = &state.textures[&EntityType::Life][0];
+	let tex_width = life_texture.width();
+	for slot in 0..state.world.players.len() {
+		let y = slot as f32 * life_texture.height() as f32 * 1.1;
+		if slot == 1 {
+			graphics::set_color(ctx, graphics::Color::new(0.6, 0.8, 1.0, 1.0))?;
+		}
+		for i in 0..state.world.player_hp(slot) {
+			graphics::draw(
+				ctx,
+				life_texture,
+				graphics::Point2::new(window_width as f32 - tex_width as f32 * 1.25 * i as f32 - tex_width as f32, y), 0.0)?;
+		}
+		graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
+	}
+	let lives_height = state.world.players.len() as f32 * life_texture.height() as f32 * 1.1;
+
+	// Show how each mode's run is going under the lives
+	let total = state.elapsed_ms / 1000;
+	let goal = match state.world.play_mode {
+		// Endless runs show how long the player has lasted and how far things have escalated
+		PlayMode::Endless => {
+			let escalation = state.world.spawner.escalation(state.elapsed_ms);
+			match escalation {
+				0 => Some(format!("{:02}:{:02}", total / 60, total % 60)),
+				_ => Some(format!("ESCALATION {} {:02}:{:02}", escalation, total / 60, total % 60)),
+			}
+		},
+		PlayMode::BossRush => Some(format!("BOSS {}/{}",
+			std::cmp::min(state.world.bosses_defeated + 1, play_mode::BOSS_RUSH_BOSSES), play_mode::BOSS_RUSH_BOSSES)),
+		PlayMode::TimeAttack => {
+			let left = play_mode::TIME_ATTACK_SECONDS.saturating_sub(total);
+			Some(format!("TIME {:02}:{:02}", left / 60, left % 60))
+		},
+		PlayMode::Practice => Some(format!("PRACTICE {:02}:{:02}", total / 60, total % 60)),
+		PlayMode::Classic => None,
+	};
+	if let Some(label) = goal {
+		let text = graphics::Text::new(ctx, &label, &state.score_font).unwrap();
+		let y = lives_height + 10.0;
+		graphics::draw(ctx, &text, graphics::Point2::new(window_width as f32 - text.width() as f32 - 10.0, y), 0.0)?;
+	}
+
+	// Draw "message text" for excitement
+	if state.world.players.iter().any(|p| p.arsenal.is_maxed()) {
+		let text = graphics::Text::new(ctx, "- RUST FULLY UPGRADED -", &state.score_font).unwrap();
+		let blink = (state.elapsed_ms as f64 / 1000.0 * ANIMATION_FRAMERATE) as usize % 4 < 2;
+		if blink {
+			graphics::draw(ctx, &text, graphics::Point2::new(window_width as f32 / 2.0 - text.width() as f32 / 2.0, window_height as f32 - text.height() as f32), 0.0)?;
+		}				
+	}
+		
+	// Generate the score text graphics and draw to screen
+	let score = graphics::Text::new(ctx, &format!("Score: {}", 
+		&state.world.score.to_string()), &state.score_font).unwrap();
+	graphics::draw(ctx, &score, graphics::Point2::new(10.0, 10.0), 0.0)?;
+
+	// Show the combo chain next to the score while it's going
+	if state.world.combo.count > 1 {
+		let combo = graphics::Text::new(ctx, &format!("{} CHAIN x{}",
+			state.world.combo.count, state.world.combo.multiplier()), &state.score_font).unwrap();
+		let fraction = state.world.combo.timer_ms as f32 / combo::COMBO_WINDOW as f32;
+		graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 0.6, 0.5 + fraction / 2.0))?;
+		graphics::draw(ctx, &combo, graphics::Point2::new(score.width() as f32 + 40.0, 10.0), 0.0)?;
+		graphics::rectangle(ctx, graphics::DrawMode::Fill, graphics::Rect {
+			x: score.width() as f32 + 40.0,
+			y: 10.0 + combo.height() as f32 + 2.0,
+			w: combo.width() as f32 * fraction,
+			h: 4.0,
+		})?;
+		graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
+	}
+
+	// Player 1's weapon, bombs and power-ups go down the left side,
+	// and player 2's down the right
+	draw_player_hud(state, ctx, 0, 10.0, 40.0)?;
+	if state.world.players.len() > 1 {
+		draw_player_hud(state, ctx, 1, window_width as f32 - PLAYER_HUD_WIDTH, lives_height + 50.0)?;
+	}
+
+	// Announce the latest achievement at the top, fading out at the end
+	if let Some(&(achievement, remaining_ms)) = state.toasts.first() {
+		let alpha = (remaining_ms as f32 / 500.0).min(1.0);
+		let title = graphics::Text::new(ctx, &format!("ACHIEVEMENT UNLOCKED: {}", achievement.name()), &state.score_font).unwrap();
+		let description = graphics::Text::new(ctx, achievement.description(), &state.score_font).unwrap();
+		graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 0.4, alpha))?;
+		graphics::draw(ctx, &title, graphics::Point2::new(window_width as f32 / 2.0 - title.width() as f32 / 2.0, 60.0), 0.0)?;
+		graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, alpha))?;
+		graphics::draw(ctx, &description, graphics::Point2::new(window_width as f32 / 2.0 - description.width() as f32 / 2.0, 60.0 + title.height() as f32 + 4.0), 0.0)?;
+		graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
+	}
+	Ok(())
+}
+
 /// We implement the `ggez:event::EventHandler` trait on `MainState`, which
 /// requires callbacks for updating and drawing the game state each frame.
 ///
@@ -519,168 +1083,97 @@ impl event::EventHandler for MainState {
         match self.game_mode {
             // If we are in the menu
 			GameMode::Menu => {
-                if self.input.shoot {
+                if self.inputs.iter().any(|input| input.shoot) {
+                    self.attract = Attract::new();
                     self.game_mode = GameMode::Game;
                     new_game(self, ctx);
+                } else {
+                    if self.attract.update(self.delta_ms) {
+                        match self.attract.screen {
+                            attract::Screen::Online if self.leaderboard.is_none() => self.attract.next(),
+                            attract::Screen::Online => fetch_online_scores(self),
+                            _ => (),
+                        }
+                        if self.attract.screen == attract::Screen::Demo {
+                            start_demo(self);
+                        }
+                    }
+                    poll_online_scores(self);
+                    if self.attract.screen == attract::Screen::Demo {
+                        play_demo(self);
+                    }
                 }
             },
 			
 			// If we are in the game
             GameMode::Game => {
-                handle_collisions(self);
 
-                // If the player died, gameover!
-                if self.entities.len() == 0 || self.entities[0].entity_type != EntityType::Player {
+                // If every player is out, gameover!
+                if !self.world.player_alive() {
                     self.game_mode = GameMode::Menu;
                     
 					save_score(self);
+					save_profile(self);
+					self.session = None;
 					
                     // Pause game for a moment
                     let pause = std::time::Duration::from_millis(500);
                     std::thread::sleep(pause);
 
-                    // Stop bgm and replay intro music
-                    if !DISABLE_SFX {
-                        self.sfx["bgm"].stop();
-                        // The `.stop()` method for a ggez audio source doesn't seem to work
-                        // correctly, so this is an ugly method of stopping and restarting the
-                        // audio. Reload from disk and overwrite existing. Eeewww!
-                        *self.sfx.get_mut("intro").unwrap() =
-                            audio::Source::new(ctx, "/sounds/intro.ogg")
-                                .expect("Could not load intro music");
-                        self.sfx["intro"].play().unwrap();
-                    }
-                }
-
-                match self.spawner.update(self.elapsed_ms, self.delta_ms) {
-                    Some(e) => {
-                        self.entities.push(e);
-                    },
-                    None => (),
+                    start_intro(self, ctx);
                 }
 
-                // Run each entity's update function
-                for i in 0..self.entities.len() {
-                    let mut e = self.entities.remove(i);
-                    e.update(self, ctx);
-                    self.entities.insert(i, e);
+                if AUTOPLAY {
+                    self.inputs[0] = bot::think(&self.world, 0);
                 }
 
-				// If player is firing
-                if self.input.shoot {
-                    if self.entities[0].bullet_cooldown == 0 {
-						// Reset cooldown
-						self.entities[0].bullet_cooldown = PLAYER_BULLET_COOLDOWN;
-						
-						let pi = std::f64::consts::PI;
-						let angle_step = pi / 16.0;
-						let player_tex = &self.textures[&entity::EntityType::Player][0];
-						let bullet_tex = &self.textures[&entity::EntityType::PlayerBullet][0];
-						for i in 0..self.gun_level {
-							let angle = pi / 2.0 + (i as f64 - self.gun_level as f64 / 2.0) * angle_step + angle_step / 2.0;
-							let x = self.entities[0].x + player_tex.width() as f32 / 2.0 - bullet_tex.width() as f32 + bullet_tex.width() as f32 / 2.0 + player_tex.width() as f32 / 2.0 * angle.cos() as f32;
-							let y = self.entities[0].y + player_tex.height() as f32 / 2.0 - bullet_tex.height() as f32 / 2.0 - player_tex.width() as f32 / 2.0 * angle.sin() as f32;
-							let mut bullet = self.spawner.player_bullet_spawner(x, y);
-							bullet.movement = Movement::Linear(
-								PLAYER_BULLET_SPEED * angle.cos() as f32,
-								-PLAYER_BULLET_SPEED * angle.sin() as f32
-							);
-							self.entities.push(bullet);
-						}						
-                        
-                        if !DISABLE_SFX {
-                            // Nasty means of playing shot sounds quickly on the same channel.
-                            *self.sfx.get_mut("player_shot").unwrap() =
-                                audio::Source::new(ctx, "/sounds/player_shot.wav")
-                                    .expect("Could not load enemy shot");
-                            self.sfx["player_shot"].play().unwrap();
+                // Run all of the gameplay systems for this frame. Online
+                // runs step in fixed frames, with the local player on the
+                // player 1 controls.
+                let frames = match self.session {
+                    Some(ref mut session) => session.run(&mut self.world, self.inputs[0], self.delta_ms),
+                    None => {
+                        if let Some(ref mut replay) = self.replay {
+                            replay.record(&self.inputs, self.elapsed_ms, self.delta_ms);
                         }
+                        self.world.update(&self.inputs, self.elapsed_ms, self.delta_ms);
+                        1
                     }
+                };
+                // Peers that picked different settings would each play a game
+                // of their own, so the run ends before it starts
+                if let Some(other) = self.session.as_ref().and_then(|session| session.mismatch) {
+                    println!(
+                        "The other player picked {} on {} with adaptive difficulty {}, pick the same to play online",
+                        other.play_mode.name(),
+                        other.difficulty.name(),
+                        if other.adaptive { "on" } else { "off" }
+                    );
+                    self.session = None;
+                    self.game_mode = GameMode::Menu;
+                    start_intro(self, ctx);
+                    return Ok(());
                 }
-
-				// Boolean to sound explosion if necessary
-				let mut play_explosion_sound = false;
-				
-				// Create vector of dying entities
-                let mut dying_entities: Vec<usize> = vec![];
-
-                // Grab the dying entities.
-                for all_idx in 0..self.entities.len() {
-                    let e = &mut self.entities[all_idx];
-
-                    let mut dying = match e.lifetime {
-                        Lifetime::Forever => false,
-                        Lifetime::Milliseconds(r) => r <= 0,
-                    };
-
-                    if !dying {
-                        if e.hp <= 0 || e.y > ctx.conf.window_mode.height as f32 {
-                            dying = true;
-                        }
+                let elapsed_ms = self.session.as_ref().map_or(self.elapsed_ms, |session| session.elapsed_ms());
+                if frames > 0 {
+                    for input in self.inputs.iter_mut() {
+                        input.bomb = false;
                     }
-
-                    if dying {
-                        // Check for any entities dying by low hp.
-                        if e.hp <= 0 {
-							match e.entity_type {
-								EntityType::Enemy => {self.score += 10;},
-								EntityType::EnemyBlueScreen => {self.score += 30;},
-								EntityType::Boss => {self.score += 200;},
-								EntityType::Special => {self.score += 50;},
-								_ => (),
-							}
-							play_explosion_sound = true;
-						}		
-						
-						
-                        // 100% guarentee we can kill off the target by hp alone.
-						e.hp = 0;
-						dying_entities.push(all_idx);
-					}
-				}
-
-				// Spawn some on_death effects.
-				for i in 0..dying_entities.len() {
-					let x = self.entities[dying_entities[i]].x;
-					let y = self.entities[dying_entities[i]].y;
-					match self.entities[dying_entities[i]].entity_type {
-						entity::EntityType::Boss => self.entities.push(self.spawner.spawn_splat(x, y)),
-						entity::EntityType::Enemy => self.entities.push(self.spawner.spawn_splat(x, y)),
-						entity::EntityType::EnemyBlueScreen => self.entities.push(self.spawner.spawn_shutoff(x, y)),
-						entity::EntityType::Special => {
-							let mut item = self.spawner.spawn_item();
-							item.x = x;
-							item.y = y;
-							self.entities.push(item);
-						},
-						_ => (), 
-					}
-				}
-
-				// Now we can just kill off stuff if it doesnt have hp.
-				self.entities.retain(|e| {
-					e.hp > 0
-				});
-
-				// If at least one entity has died from low hp, we should make an explosion sound
-				if !DISABLE_SFX && play_explosion_sound {
-					// The `.stop()` method for a ggez audio source doesn't seem to work
-					// correctly, so this is an ugly method of stopping and restarting the
-					// audio. Reload from disk and overwrite existing. Eeewww!
-					*self.sfx.get_mut("explode").unwrap() = audio::Source::new(ctx, "/sounds/explode.wav").expect("Could not load explode.wav");
-					self.sfx["explode"].play().unwrap();
-				}
+                }
+                handle_world_events(self, ctx, elapsed_ms);
 				
         		// Keep bgm playing in a loop
 				if !DISABLE_SFX && !self.sfx["bgm"].playing() {
 					self.sfx["bgm"].play().unwrap();
 				}
 				
-				// Win the game if time is up
-				if self.elapsed_ms / 1000 > SECONDS_UNTIL_MAX_DIFFICULTY + 5 {
+				// Win the game once the mode's goal is met
+				if self.world.won(elapsed_ms) {
 					self.game_mode = GameMode::Win;
 					
 					save_score(self);
+					save_profile(self);
+					self.session = None;
 					
 					if !DISABLE_SFX {
 						self.sfx["bgm"].stop();
@@ -713,184 +1206,117 @@ impl event::EventHandler for MainState {
 			// If in the menu
 			GameMode::Menu => {
 			
-				// Draw two layers of two background copies staggered according to elapsed_ms
-				let background_y = ( (self.elapsed_ms/40%1920) as i32 / PIXEL_SKIP * PIXEL_SKIP ) as f32;
-				graphics::draw(ctx, &self.background, graphics::Point2::new(0.0, background_y), 0.0)?;
-				graphics::draw(ctx, &self.background, graphics::Point2::new(0.0, -1920.0 + background_y), 0.0)?;
-				
-				// Draw title
-				graphics::draw(ctx, &self.title, graphics::Point2::new(229.0, 100.0), 0.0)?;
+				// The demo is drawn just like a game, with the title's prompt over it
+				if self.attract.screen == attract::Screen::Demo {
+					draw_game(self, ctx)?;
+				} else {
+					// Draw two layers of two background copies staggered according to elapsed_ms
+					let background_y = ( (self.elapsed_ms/40%1920) as i32 / PIXEL_SKIP * PIXEL_SKIP ) as f32;
+					graphics::draw(ctx, &self.background, graphics::Point2::new(0.0, background_y), 0.0)?;
+					graphics::draw(ctx, &self.background, graphics::Point2::new(0.0, -1920.0 + background_y), 0.0)?;
+				}
 				
 				// Draw "press spacebar" text blinking
-				let mut text = graphics::Text::new(ctx, &format!("- PRESS SPACEBAR -"), &self.score_font).unwrap();
+				let mut text = graphics::Text::new(ctx, "- PRESS SPACEBAR -", &self.score_font).unwrap();
 				if self.elapsed_ms % 1000 < 500 {
 					
-					graphics::draw(ctx, &text, graphics::Point2::new(400.0, 650.0), 0.0)?;
+					graphics::draw(ctx, &text, graphics::Point2::new(400.0, 625.0), 0.0)?;
 				}
-					
-				// Draw high scores
-				text = graphics::Text::new(ctx, &format!("{:10} {:12} {:5}", "Score", "User", "Time"), &self.score_font).unwrap();
-				graphics::draw(ctx, &text, graphics::Point2::new(200.0, 300.0), 0.0)?;
-				let scores = self.high_scores.get_scores();
-				for i in 0 .. scores.len() {
-					let (score, name, time) = &scores[i];
-					let score_text = format!("{:<10} {:10}   {}", score, &name, &time);
-					let drawing_text = graphics::Text::new(ctx, &score_text, &self.score_font).unwrap();
-					graphics::draw(ctx, &drawing_text, graphics::Point2::new(200.0, 330.0 + (i as f32) * 30_f32), 0.0)?;
-				}
-			},
-			
-			// If in the game loop
-			GameMode::Game => {
 
-				// Draw two layers of two background copies staggered according to elapsed_ms
-				let background_y = ( (self.elapsed_ms/40%1920) as i32 / PIXEL_SKIP * PIXEL_SKIP ) as f32;
-				graphics::draw(ctx, &self.background, graphics::Point2::new(0.0, background_y), 0.0)?;
-				graphics::draw(ctx, &self.background, graphics::Point2::new(0.0, -1920.0 + background_y), 0.0)?;
-
-				// Draw all entities
-				for e in &mut self.entities {
-					let pos = graphics::Point2::new((e.x as i32 / PIXEL_SKIP * PIXEL_SKIP ) as f32, (e.y as i32 / PIXEL_SKIP * PIXEL_SKIP) as f32);
-
-					// If the texure is animated, grab the right frame, otherwise grab frame 0.
-					let total_frames = self.textures[&e.entity_type].len();
-					let texture = match total_frames {
-						1 => &self.textures[&e.entity_type][0],
-						_ => {
-							let frame = (self.elapsed_ms as f64 / 1000.0 * ANIMATION_FRAMERATE)
-								as usize % total_frames;
-							&self.textures[&e.entity_type][frame]
+				match self.attract.screen {
+					attract::Screen::Title => {
+						// Draw title
+						graphics::draw(ctx, &self.title, graphics::Point2::new(229.0, 100.0), 0.0)?;
+
+						if self.resumable {
+							text = graphics::Text::new(ctx, "- PRESS R TO RESUME -", &self.score_font).unwrap();
+							graphics::draw(ctx, &text, graphics::Point2::new(window_width as f32 / 2.0 - text.width() as f32 / 2.0, 590.0), 0.0)?;
 						}
-					};
+						text = graphics::Text::new(ctx, "S - STATS", &self.score_font).unwrap();
+						graphics::draw(ctx, &text, graphics::Point2::new(10.0, window_height as f32 - text.height() as f32 - 10.0), 0.0)?;
+						text = graphics::Text::new(ctx, "H - HIGH SCORES", &self.score_font).unwrap();
+						graphics::draw(ctx, &text, graphics::Point2::new(window_width as f32 - text.width() as f32 - 10.0, window_height as f32 - text.height() as f32 - 10.0), 0.0)?;
 
-					// Special drawing conditions start
-					match e.entity_type {
-						entity::EntityType::Player => {
-							if self.shield_active {
-								graphics::set_color(ctx, graphics::Color::new(0.3, 1.0, 0.3, 1.0))?
-							}
-						},
-						entity::EntityType::Boss => match e.hp {
-							0...10 => graphics::set_color(
-								ctx,
-								graphics::Color::new(1.0, 0.25, 0.25, 1.0),
-							)?,
-							10...20 => {
-								graphics::set_color(ctx, graphics::Color::new(1.0, 0.5, 0.5, 1.0))?
-							}
-							_ => {
-								graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?
+						// Draw the mode settings, one centered row at a time, highlighting the selected one
+						let selected = self.menu.current(&self.world);
+						for (row, items) in TitleMenu::rows(&self.world).iter().enumerate() {
+							let texts: Vec<(menu::MenuItem, graphics::Text)> = items.iter()
+								.map(|&item| (item, graphics::Text::new(ctx, &TitleMenu::label(&self.world, item), &self.score_font).unwrap()))
+								.collect();
+							let spacing = 40.0;
+							let width = texts.iter().map(|(_, t)| t.width() as f32 + spacing).sum::<f32>() - spacing;
+							let mut x = window_width as f32 / 2.0 - width / 2.0;
+							for &(item, ref text) in texts.iter() {
+								if item == selected {
+									graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 0.4, 1.0))?;
+								}
+								graphics::draw(ctx, text, graphics::Point2::new(x, 655.0 + row as f32 * 30.0), 0.0)?;
+								graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
+								x += text.width() as f32 + spacing;
 							}
-						},
-						entity::EntityType::Splat | entity::EntityType::Shutoff => {
-							let mut alpha: f32 = match e.lifetime {
-								Lifetime::Forever => 1.0_f32,
-								Lifetime::Milliseconds(r) => r as f32 / SPLAT_LIFETIME as f32,
-							};
-							graphics::set_color(
-								ctx,
-								graphics::Color::new(alpha, alpha, alpha, alpha),
-							)?;
-						},
-						_ => {}
-					}
+						}
+					},
 
-					// Draw the entity sprite rotated around center of sprite if needed
-					// Non-square sprites may not rotate correctly
-					if e.angle == 0.0 {
-						graphics::draw(ctx, texture, pos, e.angle)?;
-					} else {
-						let half_width = texture.width() as f64 / 2.0;
-						let angle = -e.angle as f64 + (5.0 * std::f64::consts::PI / 4.0);
-						let x = (half_width + half_width * (2.0_f64).sqrt() * angle.cos()) as f32;
-						let y = (half_width + half_width * (2.0_f64).sqrt() * angle.sin()) as f32;
-						graphics::draw(
-							ctx,
-							texture,
-							graphics::Point2::new(pos.x + x, pos.y + y),
-							-e.angle,
-						)?;
-					}
+					// Draw high scores of the selected mode and difficulty
+					attract::Screen::Scores => {
+						let heading = format!("{} {} HIGH SCORES", self.world.play_mode.name(), self.world.difficulty.name());
+						text = graphics::Text::new(ctx, &heading, &self.score_font).unwrap();
+						graphics::draw(ctx, &text, graphics::Point2::new(window_width as f32 / 2.0 - text.width() as f32 / 2.0, 200.0), 0.0)?;
+						if self.world.play_mode.keeps_scores() {
+							text = graphics::Text::new(ctx, &format!("{:10} {:12} {:5} {:5}", "Score", "User", "Time", "Combo"), &self.score_font).unwrap();
+							graphics::draw(ctx, &text, graphics::Point2::new(200.0, 300.0), 0.0)?;
+							let scores = self.high_scores.table(Some(self.world.play_mode), Some(self.world.difficulty), self.world.play_mode.ranking());
+							for (i, record) in scores.iter().take(MAX_TITLE_SCORES).enumerate() {
+								let score_text = format!("{:<10} {:10}   {:5} {}", record.score, &record.name, &record.time(), record.max_combo);
+								let drawing_text = graphics::Text::new(ctx, &score_text, &self.score_font).unwrap();
+								graphics::draw(ctx, &drawing_text, graphics::Point2::new(200.0, 330.0 + (i as f32) * 30_f32), 0.0)?;
+							}
+						} else {
+							text = graphics::Text::new(ctx, "- SCORES AREN'T SAVED IN PRACTICE -", &self.score_font).unwrap();
+							graphics::draw(ctx, &text, graphics::Point2::new(window_width as f32 / 2.0 - text.width() as f32 / 2.0, 300.0), 0.0)?;
+						}
+					},
 
-					// End drawing conditions: Reset drawing conditions
-					graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
-
-					// If this is an enemy, include a name tag.
-					if e.entity_type == entity::EntityType::Enemy ||
-						e.entity_type == entity::EntityType::EnemyBlueScreen {
-						
-						// Dim label after a while
-						match e.lifetime {
-							Lifetime::Forever => (),
-							Lifetime::Milliseconds(r) => {
-								let fraction_of_life = ( ENEMY_LIFETIME as f32 - r as f32 ) / ENEMY_LIFETIME as f32;
-								let mut alpha = 1.0 - fraction_of_life * 15.0;
-								if alpha < 0.0 {
-									alpha = 0.0;
+					// Draw the online top runs, or why there aren't any
+					attract::Screen::Online => {
+						let heading = format!("{} {} ONLINE", self.world.play_mode.name(), self.world.difficulty.name());
+						text = graphics::Text::new(ctx, &heading, &self.score_font).unwrap();
+						graphics::draw(ctx, &text, graphics::Point2::new(window_width as f32 / 2.0 - text.width() as f32 / 2.0, 200.0), 0.0)?;
+						let status = match self.online_scores {
+							Some(Ok(ref entries)) if !entries.is_empty() => {
+								text = graphics::Text::new(ctx, &format!("{:4} {:10} {:12} {:5}", "", "Score", "User", "Time"), &self.score_font).unwrap();
+								graphics::draw(ctx, &text, graphics::Point2::new(160.0, 300.0), 0.0)?;
+								for (i, entry) in entries.iter().enumerate() {
+									let seconds = entry.time_ms / 1000;
+									let line = format!("{:<4} {:<10} {:12} {:02}:{:02}", i + 1, entry.score, entry.user, seconds / 60, seconds % 60);
+									text = graphics::Text::new(ctx, &line, &self.score_font).unwrap();
+									graphics::draw(ctx, &text, graphics::Point2::new(160.0, 330.0 + i as f32 * 30.0), 0.0)?;
 								}
-								graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, alpha))?;
-							},
-						};	
-
-						// Calculate label position
-						let offset = 30;
-						let text_pos = graphics::Point2::new(
-							((e.x as i32 + texture.width() as i32 + offset + 6) / PIXEL_SKIP * PIXEL_SKIP ) as f32, 
-							((e.y as i32 - offset - 6) / PIXEL_SKIP * PIXEL_SKIP) as f32);
-						
-						// Draw the label
-						graphics::draw(ctx, &self.labels[&e.name], text_pos, 0.0)?;
-						
-						// Draw a line connecting it to entity
-						graphics::line(ctx, &[
-							graphics::Point2::new(text_pos.x - 6.0, text_pos.y + self.labels[&e.name].height() as f32),
-							graphics::Point2::new(text_pos.x - offset as f32, text_pos.y + offset as f32)
-						], 4.0)?;
-						
-						// Reset color
-						graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
-					}
-					
-					// Draw collision boxes if they are enabled.
-					if DRAW_BOUNDING_BOXES {
-						graphics::rectangle(
-							ctx,
-							graphics::DrawMode::Line(1.0),
-							graphics::Rect {
-								x: e.x + e.bounds.x,
-								y: e.y + e.bounds.y,
-								w: e.bounds.w,
-								h: e.bounds.h,
-							},
-						)?;
-					}
-				}
+								None
+							}
+							Some(Ok(_)) => Some("- NO RUNS YET -"),
+							Some(Err(_)) => Some("- OFFLINE -"),
+							None => Some("- LOADING -"),
+						};
+						if let Some(status) = status {
+							text = graphics::Text::new(ctx, status, &self.score_font).unwrap();
+							graphics::draw(ctx, &text, graphics::Point2::new(window_width as f32 / 2.0 - text.width() as f32 / 2.0, 300.0), 0.0)?;
+						}
+					},
 
-				// Draw the player's life graphics
-				let player = &self.entities[0];
-				let tex_width = self.textures[&EntityType::Life][0].width();
-				if player.hp > 0 {
-					for i in 0..player.hp {
-						graphics::draw(
-							ctx,
-							&self.textures[&EntityType::Life][0],
-							graphics::Point2::new(window_width as f32 - tex_width as f32 * 1.25 * i as f32 - tex_width as f32, 0.0), 0.0)?;
-					}
-				}
+					attract::Screen::Demo => {
+						text = graphics::Text::new(ctx, "- DEMO -", &self.score_font).unwrap();
+						graphics::draw(ctx, &text, graphics::Point2::new(window_width as f32 / 2.0 - text.width() as f32 / 2.0, 300.0), 0.0)?;
+					},
 
-				// Draw "message text" for excitement
-				if self.gun_level == MAX_UPGRADE_LEVEL {
-					let mut text = graphics::Text::new(ctx, &format!("- RUST FULLY UPGRADED -"), &self.score_font).unwrap();
-					let blink = (self.elapsed_ms as f64 / 1000.0 * ANIMATION_FRAMERATE) as usize % 4 < 2;
-					if blink {
-						graphics::draw(ctx, &text, graphics::Point2::new(window_width as f32 / 2.0 - text.width() as f32 / 2.0, window_height as f32 - text.height() as f32), 0.0)?;
-					}				
+					attract::Screen::Stats => draw_stats(self, ctx)?,
+					attract::Screen::Records => draw_records(self, ctx)?,
 				}
-					
-				// Generate the score text graphics and draw to screen
-				let score = graphics::Text::new(ctx, &format!("Score: {}", 
-					&self.score.to_string()), &self.score_font).unwrap();
-				graphics::draw(ctx, &score, graphics::Point2::new(10.0, 10.0), 0.0)?;
+			},
+			
+			// If in the game loop
+			GameMode::Game => {
+				draw_game(self, ctx)?;
 			},
 		
 			// If in the win state
@@ -929,9 +1355,9 @@ impl event::EventHandler for MainState {
 				
 				// Draw text
 				let font = graphics::Font::new(ctx, DEFAULT_FONT, 16)?;
-				let green_text = graphics::Text::new(ctx, &format!("Finished"), &font).unwrap();
+				let green_text = graphics::Text::new(ctx, "Finished", &font).unwrap();
 				let green_text_width = green_text.width() as f32;
-				let white_text = graphics::Text::new(ctx, &format!("release [optimized] target(s) in {} points", &self.score.to_string()), &font).unwrap();
+				let white_text = graphics::Text::new(ctx, &format!("release [optimized] target(s) in {} points", &self.world.score.to_string()), &font).unwrap();
 				let white_text_width = white_text.width() as f32;
 				graphics::set_color(ctx, graphics::Color::new(0.0, 1.0, 0.0, 1.0))?;
 				graphics::draw(
@@ -1011,7 +1437,7 @@ impl event::EventHandler for MainState {
 	}
 
 	// Event is triggered when the player presses keydowns
-	fn key_down_event(&mut self, _ctx: &mut Context, keycode: Keycode, keymod: Mod, repeat: bool) {
+	fn key_down_event(&mut self, ctx: &mut Context, keycode: Keycode, keymod: Mod, repeat: bool) {
 		if SHOW_INPUT_DEBUG {
 			println!(
 				"Key pressed: {:?}, modifier {:?}, repeat: {}",
@@ -1019,26 +1445,109 @@ impl event::EventHandler for MainState {
 			);
 		}
 
+		// Any key but escape brings the title back from the high scores
+		// or the demo, except for those the high-score browser uses
+		if let GameMode::Menu = self.game_mode {
+			if self.attract.screen == attract::Screen::Records && browse_scores(self, keycode) {
+				return;
+			}
+			if keycode != ggez::event::Keycode::Escape && self.attract.wake() {
+				return;
+			}
+		}
+
 		if keycode == ggez::event::Keycode::Left {
-			self.input.left = true;
+			self.inputs[0].left = true;
 		}
 		if keycode == ggez::event::Keycode::Right {
-			self.input.right = true;
+			self.inputs[0].right = true;
+		}
+
+		// Pick the mode and its settings on the title screen
+		if let GameMode::Menu = self.game_mode {
+			if keycode == ggez::event::Keycode::Left && !repeat {
+				self.menu.change(&mut self.world, false);
+			}
+			if keycode == ggez::event::Keycode::Right && !repeat {
+				self.menu.change(&mut self.world, true);
+			}
+			if keycode == ggez::event::Keycode::Up && !repeat {
+				self.menu.select(&self.world, false);
+			}
+			if keycode == ggez::event::Keycode::Down && !repeat {
+				self.menu.select(&self.world, true);
+			}
+			if keycode == ggez::event::Keycode::R && !repeat && self.resumable {
+				resume_game(self, ctx);
+			}
+			if keycode == ggez::event::Keycode::S && !repeat {
+				self.attract.show(attract::Screen::Stats);
+			}
+			if keycode == ggez::event::Keycode::H && !repeat {
+				self.score_viewer = ScoreViewer::new(self.world.play_mode, self.world.difficulty);
+				self.attract.show(attract::Screen::Records);
+			}
+		}
+
+		// Save and load practice runs
+		if let GameMode::Game = self.game_mode {
+			if self.world.play_mode == PlayMode::Practice && self.session.is_none() && !repeat {
+				if keycode == ggez::event::Keycode::F5 {
+					quicksave(self, false);
+				}
+				if keycode == ggez::event::Keycode::F9 {
+					quicksave(self, true);
+				}
+			}
 		}
 		if keycode == ggez::event::Keycode::Up {
-			self.input.up = true;
+			self.inputs[0].up = true;
 		}
 		if keycode == ggez::event::Keycode::Down {
-			self.input.down = true;
+			self.inputs[0].down = true;
 		}
 		if keycode == ggez::event::Keycode::Space {
-			self.input.shoot = true;
+			self.inputs[0].shoot = true;
+		}
+		if keycode == ggez::event::Keycode::X && !repeat {
+			self.inputs[0].bomb = true;
+		}
+
+		// Player 2 moves with IJKL, shoots with U and bombs with O
+		if keycode == ggez::event::Keycode::J {
+			self.inputs[1].left = true;
+		}
+		if keycode == ggez::event::Keycode::L {
+			self.inputs[1].right = true;
+		}
+		if keycode == ggez::event::Keycode::I {
+			self.inputs[1].up = true;
+		}
+		if keycode == ggez::event::Keycode::K {
+			self.inputs[1].down = true;
+		}
+		if keycode == ggez::event::Keycode::U {
+			self.inputs[1].shoot = true;
+		}
+		if keycode == ggez::event::Keycode::O && !repeat {
+			self.inputs[1].bomb = true;
 		}
 		if keycode == ggez::event::Keycode::Escape {
+			if let GameMode::Game = self.game_mode {
+				suspend_game(self);
+			}
 			self.quit = true;
 		}
 	}
 
+	// Closing the window also suspends a run in progress
+	fn quit_event(&mut self, _ctx: &mut Context) -> bool {
+		if let GameMode::Game = self.game_mode {
+			suspend_game(self);
+		}
+		false
+	}
+
 	// Event is triggered when player lifts up on a keys
 	fn key_up_event(&mut self, _ctx: &mut Context, keycode: Keycode, keymod: Mod, repeat: bool) {
 		if SHOW_INPUT_DEBUG {
@@ -1049,42 +1558,122 @@ impl event::EventHandler for MainState {
 		}
 
 		if keycode == ggez::event::Keycode::Left {
-			self.input.left = false;
+			self.inputs[0].left = false;
 		}
 		if keycode == ggez::event::Keycode::Right {
-			self.input.right = false;
+			self.inputs[0].right = false;
 		}
 		if keycode == ggez::event::Keycode::Up {
-			self.input.up = false;
+			self.inputs[0].up = false;
 		}
 		if keycode == ggez::event::Keycode::Down {
-			self.input.down = false;
+			self.inputs[0].down = false;
 		}
 		if keycode == ggez::event::Keycode::Space {
-			self.input.shoot = false;
-			self.entities[0].bullet_cooldown = 0;
+			self.inputs[0].shoot = false;
+		}
+		if keycode == ggez::event::Keycode::J {
+			self.inputs[1].left = false;
+		}
+		if keycode == ggez::event::Keycode::L {
+			self.inputs[1].right = false;
+		}
+		if keycode == ggez::event::Keycode::I {
+			self.inputs[1].up = false;
+		}
+		if keycode == ggez::event::Keycode::K {
+			self.inputs[1].down = false;
 		}
+		if keycode == ggez::event::Keycode::U {
+			self.inputs[1].shoot = false;
+		}
+
+		// Cheats change the world outside of the players' input, which the
+		// other peer of an online game would never hear about
+		if self.session.is_some() {
+			return;
+		}
+		if !matches!(keycode, Keycode::B | Keycode::E | Keycode::G | Keycode::S | Keycode::W) {
+			return;
+		}
+		// Nor can a replay play them back, so the run can't be scored
+		self.cheated = true;
+		self.replay = None;
 		if keycode == ggez::event::Keycode::B {
-			self.spawner.cooldowns.insert(EntityType::Boss, 0);
+			self.world.spawner.cooldowns.insert(EntityType::Boss, 0);
 		}
 		if keycode == ggez::event::Keycode::E {
-			self.spawner.cooldowns.insert(EntityType::Enemy, 0);
+			self.world.spawner.cooldowns.insert(EntityType::Enemy, 0);
 		}
 		if keycode == ggez::event::Keycode::G {
 			unsafe {
-				if GOD_MODE == false {
-					GOD_MODE = true;
-				}
-				else {
-					GOD_MODE = false;
-				}
+				GOD_MODE = !GOD_MODE;
 			}
 		}
 		if keycode == ggez::event::Keycode::S {
-			self.spawner.cooldowns.insert(EntityType::Special, 0);
+			self.world.spawner.cooldowns.insert(EntityType::Special, 0);
 		}
 		if keycode == ggez::event::Keycode::W {
-			self.elapsed_ms = SECONDS_UNTIL_MAX_DIFFICULTY * 1000 + 6000;
+			self.elapsed_ms = self.world.spawner.preset.ramp_seconds * 1000 + 6000;
+		}
+	}
+
+	// Gamepads are handed to the players in the order they are first used.
+	// A shoots, B bombs, and the d-pad or left stick moves.
+	fn controller_button_down_event(&mut self, _ctx: &mut Context, btn: Button, instance_id: i32) {
+		if SHOW_INPUT_DEBUG {
+			println!("Button pressed: {:?}, gamepad {}", btn, instance_id);
+		}
+		if let GameMode::Menu = self.game_mode {
+			if self.attract.wake() {
+				return;
+			}
+		}
+		let slot = match self.gamepad_slot(instance_id) {
+			Some(slot) => slot,
+			None => return,
+		};
+		match btn {
+			Button::A => self.inputs[slot].shoot = true,
+			Button::B => self.inputs[slot].bomb = true,
+			Button::DPadLeft => self.inputs[slot].left = true,
+			Button::DPadRight => self.inputs[slot].right = true,
+			Button::DPadUp => self.inputs[slot].up = true,
+			Button::DPadDown => self.inputs[slot].down = true,
+			_ => (),
+		}
+	}
+
+	fn controller_button_up_event(&mut self, _ctx: &mut Context, btn: Button, instance_id: i32) {
+		let slot = match self.gamepad_slot(instance_id) {
+			Some(slot) => slot,
+			None => return,
+		};
+		match btn {
+			Button::A => self.inputs[slot].shoot = false,
+			Button::DPadLeft => self.inputs[slot].left = false,
+			Button::DPadRight => self.inputs[slot].right = false,
+			Button::DPadUp => self.inputs[slot].up = false,
+			Button::DPadDown => self.inputs[slot].down = false,
+			_ => (),
+		}
+	}
+
+	fn controller_axis_event(&mut self, _ctx: &mut Context, axis: Axis, value: i16, instance_id: i32) {
+		let slot = match self.gamepad_slot(instance_id) {
+			Some(slot) => slot,
+			None => return,
+		};
+		match axis {
+			Axis::LeftX => {
+				self.inputs[slot].left = value < -STICK_DEADZONE;
+				self.inputs[slot].right = value > STICK_DEADZONE;
+			}
+			Axis::LeftY => {
+				self.inputs[slot].up = value < -STICK_DEADZONE;
+				self.inputs[slot].down = value > STICK_DEADZONE;
+			}
+			_ => (),
 		}
 	}
 }
\ No newline at end of file
@@ -0,0 +1,783 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+//! Online co-op between two peers using rollback. Each peer runs the whole
+//! simulation at a fixed frame rate and only inputs are sent over UDP.
+//! When the other player's input for a frame hasn't arrived yet it is
+//! predicted, and if the prediction turns out wrong the world is restored
+//! from a snapshot and the frames since are simulated again. The events of
+//! a frame are only passed on to the world's queue once both inputs for it
+//! are known, so nothing a rollback undoes is ever reported.
+
+extern crate rand;
+
+use self::rand::Rng;
+use game::{Input, LOG_NETPLAY_CHECKSUMS};
+use game::difficulty::Difficulty;
+use game::events::GameEvent;
+use game::fnv::Fnv1a;
+use game::play_mode::PlayMode;
+use game::replay::Replay;
+use game::world::World;
+use std;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::net::{SocketAddr, UdpSocket};
+
+/// Length of one simulated frame (in ms).
+pub const FRAME_MS: u64 = 16;
+/// Furthest the simulation may run ahead of the other player's inputs.
+const MAX_PREDICTION: u32 = 8;
+/// Most frames of input sent in one packet.
+const MAX_PACKET_INPUTS: u32 = 32;
+/// Frames between checksums printed for comparing the two peers' logs.
+const CHECKSUM_PERIOD: u32 = 300;
+/// Packets start with the session id, the settings picked, how many of the
+/// other peer's inputs have arrived, the frame of the first input sent and
+/// how many follow.
+const HEADER_SIZE: usize = 16;
+/// Most frames simulated in one call to `Session::run`, so a slow machine
+/// doesn't fall further and further behind.
+const MAX_FRAMES_PER_RUN: u32 = 4;
+
+/// Moves packets between the two peers. Sending never blocks and lost
+/// packets are fine, since inputs are resent until acknowledged.
+pub trait Transport {
+    fn send(&mut self, packet: &[u8]);
+    /// The next packet that has arrived, if any.
+    fn receive(&mut self) -> Option<Vec<u8>>;
+    /// Called once per frame with the time that has passed.
+    fn update(&mut self, _delta_ms: u64) {}
+}
+
+/// Sends packets to the other peer over UDP.
+pub struct UdpTransport {
+    socket: UdpSocket,
+    peer: SocketAddr,
+}
+
+impl UdpTransport {
+    /// Listens on `local` for packets from `peer`, for example
+    /// `127.0.0.1:7000` and `127.0.0.1:7001`.
+    pub fn bind(local: &str, peer: &str) -> std::io::Result<UdpTransport> {
+        let peer = match peer.parse() {
+            Ok(peer) => peer,
+            Err(_) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "bad peer address")),
+        };
+        UdpTransport::new(UdpSocket::bind(local)?, peer)
+    }
+
+    /// Talks to `peer` through a socket that is already bound.
+    pub fn new(socket: UdpSocket, peer: SocketAddr) -> std::io::Result<UdpTransport> {
+        socket.set_nonblocking(true)?;
+        Ok(UdpTransport { socket, peer })
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send(&mut self, packet: &[u8]) {
+        // A full send buffer is just another lost packet
+        let _ = self.socket.send_to(packet, self.peer);
+    }
+
+    fn receive(&mut self) -> Option<Vec<u8>> {
+        let mut buffer = [0; 512];
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((size, from)) if from == self.peer => return Some(buffer[..size].to_vec()),
+                Ok(_) => continue,
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+/// Wraps another transport to simulate a bad connection, delaying every
+/// packet and dropping some of them. Used to try out netplay over
+/// loopback.
+pub struct LossyTransport<T: Transport> {
+    inner: T,
+    /// Delay added to every packet sent (in ms)
+    pub latency_ms: u64,
+    /// Chance of a packet being dropped, from 0 to 1
+    pub loss: f32,
+    clock_ms: u64,
+    /// Packets waiting to be sent, with the time they are due
+    delayed: VecDeque<(u64, Vec<u8>)>,
+    rng: rand::XorShiftRng,
+}
+
+impl<T: Transport> LossyTransport<T> {
+    pub fn new(inner: T, latency_ms: u64, loss: f32) -> LossyTransport<T> {
+        LossyTransport {
+            inner,
+            latency_ms,
+            loss,
+            clock_ms: 0,
+            delayed: VecDeque::new(),
+            rng: rand::random(),
+        }
+    }
+}
+
+impl<T: Transport> Transport for LossyTransport<T> {
+    fn send(&mut self, packet: &[u8]) {
+        if self.rng.gen_range(0.0, 1.0) < self.loss {
+            return;
+        }
+        self.delayed.push_back((self.clock_ms + self.latency_ms, packet.to_vec()));
+    }
+
+    fn receive(&mut self) -> Option<Vec<u8>> {
+        self.inner.receive()
+    }
+
+    fn update(&mut self, delta_ms: u64) {
+        self.clock_ms += delta_ms;
+        while self.delayed.front().is_some_and(|&(due, _)| due <= self.clock_ms) {
+            let (_, packet) = self.delayed.pop_front().unwrap();
+            self.inner.send(&packet);
+        }
+        self.inner.update(delta_ms);
+    }
+}
+
+/// Settings for an online game, given on the command line as
+/// `--netplay <local address> <peer address> <player 1 or 2>`, optionally
+/// followed by `--seed <n>`, `--latency <ms>` and `--loss <percent>`.
+/// Both peers must use the same seed, and pick the same `Settings` on the
+/// title screen.
+pub struct Options {
+    pub local: String,
+    pub peer: String,
+    /// Which player this peer controls, starting at 0
+    pub slot: usize,
+    pub seed: u32,
+    pub latency_ms: u64,
+    pub loss: f32,
+    /// Runs started so far
+    runs: u32,
+}
+
+impl Options {
+    /// Reads the options from the command line arguments, or returns
+    /// `Ok(None)` if netplay wasn't asked for.
+    pub fn from_args<I: Iterator<Item = String>>(args: I) -> Result<Option<Options>, String> {
+        let args: Vec<String> = args.collect();
+        let start = match args.iter().position(|a| a == "--netplay") {
+            Some(i) => i,
+            None => return Ok(None),
+        };
+        if args.len() < start + 4 {
+            return Err("--netplay needs a local address, a peer address and a player number".to_string());
+        }
+        let slot = match args[start + 3].as_str() {
+            "1" => 0,
+            "2" => 1,
+            other => return Err(format!("player must be 1 or 2, not {}", other)),
+        };
+        let mut options = Options {
+            local: args[start + 1].clone(),
+            peer: args[start + 2].clone(),
+            slot,
+            seed: 0,
+            latency_ms: 0,
+            loss: 0.0,
+            runs: 0,
+        };
+
+        let mut rest = args[start + 4..].iter();
+        while let Some(flag) = rest.next() {
+            let value = rest.next().ok_or(format!("{} needs a value", flag))?;
+            match flag.as_str() {
+                "--seed" => options.seed = parse(flag, value)?,
+                "--latency" => options.latency_ms = parse(flag, value)?,
+                "--loss" => options.loss = parse::<f32>(flag, value)? / 100.0,
+                // Not a netplay option, but may come after them
+                "--leaderboard" => (),
+                _ => return Err(format!("unknown option {}", flag)),
+            }
+        }
+        Ok(Some(options))
+    }
+
+    /// Opens the connection for a new run with the given settings. Every
+    /// run gets its own seed, the same on both peers as long as they play
+    /// the same number of runs. Returns the session along with the seed to
+    /// give the world.
+    pub fn connect(&mut self, start_ms: u64, settings: Settings) -> std::io::Result<(Session<LossyTransport<UdpTransport>>, u32)> {
+        let transport = LossyTransport::new(UdpTransport::bind(&self.local, &self.peer)?, self.latency_ms, self.loss);
+        let seed = self.seed.wrapping_add(self.runs);
+        self.runs += 1;
+        Ok((Session::new(transport, self.slot, start_ms, seed, settings), seed))
+    }
+}
+
+/// The title screen settings that change how the world plays out, which
+/// both peers have to agree on.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Settings {
+    pub play_mode: PlayMode,
+    pub difficulty: Difficulty,
+    /// Whether adaptive difficulty is on
+    pub adaptive: bool,
+}
+
+impl Settings {
+    /// The settings a world is set up with.
+    pub fn of(world: &World) -> Settings {
+        Settings {
+            play_mode: world.play_mode,
+            difficulty: world.difficulty,
+            adaptive: world.adaptive.enabled,
+        }
+    }
+
+    /// Packs the settings into three bytes: the places of the mode and
+    /// difficulty in the lists of each, and the adaptive flag.
+    fn encode(&self) -> [u8; 3] {
+        let mode = PlayMode::all().iter().position(|&m| m == self.play_mode).unwrap_or(0);
+        let difficulty = Difficulty::all().iter().position(|&d| d == self.difficulty).unwrap_or(0);
+        [mode as u8, difficulty as u8, self.adaptive as u8]
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Settings> {
+        Some(Settings {
+            play_mode: PlayMode::all().get(bytes[0] as usize).cloned()?,
+            difficulty: Difficulty::all().get(bytes[1] as usize).cloned()?,
+            adaptive: match bytes[2] {
+                0 => false,
+                1 => true,
+                _ => return None,
+            },
+        })
+    }
+}
+
+fn parse<F: std::str::FromStr>(flag: &str, value: &str) -> Result<F, String> {
+    value.parse().map_err(|_| format!("bad value for {}: {}", flag, value))
+}
+
+/// Packs an input into one byte, one bit per control.
+pub fn encode_input(input: &Input) -> u8 {
+    let controls = [input.left, input.right, input.up, input.down, input.shoot, input.bomb];
+    controls.iter().enumerate().fold(0, |bits, (i, &on)| if on { bits | 1 << i } else { bits })
+}
+
+pub fn decode_input(bits: u8) -> Input {
+    Input {
+        left: bits & 1 != 0,
+        right: bits & 2 != 0,
+        up: bits & 4 != 0,
+        down: bits & 8 != 0,
+        shoot: bits & 16 != 0,
+        bomb: bits & 32 != 0,
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    bytes.iter().take(4).enumerate().fold(0, |n, (i, &b)| n | (b as u32) << (8 * i))
+}
+
+fn write_u32(packet: &mut Vec<u8>, n: u32) {
+    for i in 0..4 {
+        packet.push((n >> (8 * i)) as u8);
+    }
+}
+
+/// Fingerprint of the parts of a world that matter for gameplay, to tell
+/// whether two peers still agree. The hash is fixed, so it comes out the
+/// same on every build.
+pub fn checksum(world: &World) -> u64 {
+    let mut hasher = Fnv1a::new();
+    world.score.hash(&mut hasher);
+    for (id, t) in world.transforms.iter() {
+        id.hash(&mut hasher);
+        t.x.to_bits().hash(&mut hasher);
+        t.y.to_bits().hash(&mut hasher);
+    }
+    for (id, health) in world.healths.iter() {
+        id.hash(&mut hasher);
+        health.hp.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// One side of an online game. Keeps the inputs of both players and
+/// snapshots of recent frames so mispredictions can be rolled back.
+pub struct Session<T: Transport> {
+    transport: T,
+    /// Tells this run's packets apart from those of an earlier run
+    id: u32,
+    /// Settings of this peer's run
+    settings: Settings,
+    /// Settings the other peer picked, if they differ from ours. Their
+    /// inputs are refused, so the run can't get going.
+    pub mismatch: Option<Settings>,
+    /// Which player this peer controls
+    local: usize,
+    /// Clock of the run when the session started (in ms)
+    start_ms: u64,
+    /// Next frame to simulate
+    frame: u32,
+    /// This peer's input for every frame so far
+    local_inputs: Vec<Input>,
+    /// The other player's input for every frame they have sent
+    remote_inputs: Vec<Option<Input>>,
+    /// What the other player's input was taken to be when each frame was
+    /// last simulated
+    used_inputs: Vec<Input>,
+    /// Frames of input the other peer has received from us
+    acked: u32,
+    /// The world as it was before each frame that may still be rolled back
+    snapshots: VecDeque<(u32, World)>,
+    /// Earliest frame simulated with a wrong prediction
+    rollback_to: Option<u32>,
+    /// Time not yet simulated (in ms)
+    pending_ms: u64,
+    /// Events of each frame simulated but not yet confirmed, oldest first
+    held_events: VecDeque<Vec<GameEvent>>,
+    /// Frames whose events have been passed on
+    released: u32,
+    /// Times the simulation has been rolled back, for debugging
+    pub rollbacks: u32,
+}
+
+impl<T: Transport> Session<T> {
+    /// Starts a session at the beginning of a run with the given settings,
+    /// with the run clock at `start_ms`. Both peers must start from
+    /// identical worlds and use the same `id`.
+    pub fn new(transport: T, local: usize, start_ms: u64, id: u32, settings: Settings) -> Session<T> {
+        Session {
+            transport,
+            id,
+            settings,
+            mismatch: None,
+            local,
+            start_ms,
+            frame: 0,
+            local_inputs: Vec::new(),
+            remote_inputs: Vec::new(),
+            used_inputs: Vec::new(),
+            acked: 0,
+            snapshots: VecDeque::new(),
+            rollback_to: None,
+            pending_ms: 0,
+            held_events: VecDeque::new(),
+            released: 0,
+            rollbacks: 0,
+        }
+    }
+
+    /// Time on the run clock the simulation has reached (in ms).
+    pub fn elapsed_ms(&self) -> u64 {
+        self.elapsed_at(self.frame)
+    }
+
+    fn elapsed_at(&self, frame: u32) -> u64 {
+        self.start_ms + frame as u64 * FRAME_MS
+    }
+
+    /// Adds every frame simulated so far to `replay`, with the inputs they
+    /// were last simulated with, so it plays back to the world as it is.
+    pub fn record(&self, replay: &mut Replay) {
+        for frame in 0..self.frame {
+            let mut inputs = [Input::default(); 2];
+            inputs[self.local] = self.local_inputs[frame as usize];
+            inputs[1 - self.local] = self.used_inputs[frame as usize];
+            replay.record(&inputs, self.elapsed_at(frame), FRAME_MS);
+        }
+    }
+
+    /// Frames for which the other player's input has arrived, counting
+    /// from the first without a gap.
+    pub fn confirmed(&self) -> u32 {
+        self.remote_inputs.iter().take_while(|input| input.is_some()).count() as u32
+    }
+
+    /// Simulates the frames due after `delta_ms` of real time, with
+    /// `input` as this player's controls. A bomb press only counts for the
+    /// first of them. Returns how many frames were simulated, which is 0
+    /// while waiting for the other player.
+    pub fn run(&mut self, world: &mut World, input: Input, delta_ms: u64) -> u32 {
+        self.pending_ms += delta_ms;
+        let mut input = input;
+        let mut frames = 0;
+        while self.pending_ms >= FRAME_MS && frames < MAX_FRAMES_PER_RUN {
+            self.transport.update(FRAME_MS);
+            if !self.advance(world, input) {
+                break;
+            }
+            input.bomb = false;
+            self.pending_ms -= FRAME_MS;
+            frames += 1;
+        }
+        // Don't build up a backlog while stalled
+        self.pending_ms = std::cmp::min(self.pending_ms, FRAME_MS * MAX_FRAMES_PER_RUN as u64);
+        frames
+    }
+
+    /// Simulates one frame with `input` as this player's controls, unless
+    /// the simulation is too far ahead of the other player. Returns true if
+    /// the frame was simulated.
+    pub fn advance(&mut self, world: &mut World, input: Input) -> bool {
+        self.poll(world);
+        if self.frame >= self.confirmed() + MAX_PREDICTION {
+            return false;
+        }
+
+        self.local_inputs.push(input);
+        self.send();
+        self.snapshots.push_back((self.frame, world.clone()));
+        let frame = self.frame;
+        self.simulate(world, frame);
+        self.frame += 1;
+
+        // Frames with both inputs known can never be rolled back again,
+        // so both peers must have ended up with the same world
+        let confirmed = self.confirmed();
+        while self.snapshots.front().is_some_and(|&(f, _)| f < confirmed) {
+            let (f, snapshot) = self.snapshots.pop_front().unwrap();
+            if LOG_NETPLAY_CHECKSUMS && f % CHECKSUM_PERIOD == 0 {
+                println!("[netplay] frame {} checksum {:016x}", f, checksum(&snapshot));
+            }
+        }
+        self.release_events(world);
+        true
+    }
+
+    /// Passes the events of the frames confirmed since the last call on to
+    /// the world's queue.
+    fn release_events(&mut self, world: &mut World) {
+        let confirmed = std::cmp::min(self.confirmed(), self.frame);
+        while self.released < confirmed {
+            world.events.extend(self.held_events.pop_front().unwrap_or_default());
+            self.released += 1;
+        }
+    }
+
+    /// Takes in the packets that have arrived, rolling back and simulating
+    /// again from the first frame that was predicted wrong.
+    pub fn poll(&mut self, world: &mut World) {
+        while let Some(packet) = self.transport.receive() {
+            self.receive(&packet);
+        }
+        self.send();
+
+        if let Some(from) = self.rollback_to.take() {
+            self.roll_back(world, from);
+        }
+        self.release_events(world);
+    }
+
+    /// Restores the world from before `from` and simulates the frames since
+    /// again, replacing the events they had.
+    fn roll_back(&mut self, world: &mut World, from: u32) {
+        let snapshot = match self.snapshots.iter().position(|&(f, _)| f == from) {
+            Some(i) => i,
+            None => return,
+        };
+        self.rollbacks += 1;
+        // The queue only holds events already confirmed, which stay
+        let events = std::mem::take(&mut world.events);
+        *world = self.snapshots[snapshot].1.clone();
+        world.events = events;
+        self.snapshots.truncate(snapshot + 1);
+        for frame in from..self.frame {
+            if frame > from {
+                self.snapshots.push_back((frame, world.clone()));
+            }
+            self.simulate(world, frame);
+        }
+    }
+
+    /// Runs one frame of the world with the inputs known for it.
+    fn simulate(&mut self, world: &mut World, frame: u32) {
+        let remote = match self.remote_inputs.get(frame as usize) {
+            Some(&Some(input)) => input,
+            // Guess the other player is still holding what they last held,
+            // without setting off another bomb
+            _ => Input {
+                bomb: false,
+                ..self.last_remote_input(frame)
+            },
+        };
+        if self.used_inputs.len() <= frame as usize {
+            self.used_inputs.push(remote);
+        } else {
+            self.used_inputs[frame as usize] = remote;
+        }
+
+        let mut inputs = [Input::default(); 2];
+        inputs[self.local] = self.local_inputs[frame as usize];
+        inputs[1 - self.local] = remote;
+        let first_event = world.events.len();
+        world.update(&inputs, self.elapsed_at(frame), FRAME_MS);
+
+        // Hold on to the frame's events until it is confirmed
+        let events = world.events.split_off(first_event);
+        let held = (frame - self.released) as usize;
+        if held < self.held_events.len() {
+            self.held_events[held] = events;
+        } else {
+            self.held_events.push_back(events);
+        }
+    }
+
+    fn last_remote_input(&self, frame: u32) -> Input {
+        self.remote_inputs[..std::cmp::min(frame as usize, self.remote_inputs.len())]
+            .iter()
+            .rev()
+            .filter_map(|&input| input)
+            .next()
+            .unwrap_or_default()
+    }
+
+    /// Sends every input the other peer hasn't acknowledged yet, along
+    /// with how many of theirs have arrived.
+    fn send(&mut self) {
+        let first = self.acked;
+        let count = std::cmp::min(self.local_inputs.len() as u32 - first, MAX_PACKET_INPUTS);
+        let mut packet = Vec::with_capacity(HEADER_SIZE + count as usize);
+        write_u32(&mut packet, self.id);
+        packet.extend(&self.settings.encode());
+        write_u32(&mut packet, self.confirmed());
+        write_u32(&mut packet, first);
+        packet.push(count as u8);
+        for input in &self.local_inputs[first as usize..(first + count) as usize] {
+            packet.push(encode_input(input));
+        }
+        self.transport.send(&packet);
+    }
+
+    /// Reads a packet of inputs from the other peer.
+    fn receive(&mut self, packet: &[u8]) {
+        if packet.len() < HEADER_SIZE || packet.len() < HEADER_SIZE + packet[HEADER_SIZE - 1] as usize {
+            return;
+        }
+        if read_u32(&packet[0..4]) != self.id {
+            return;
+        }
+        match Settings::decode(&packet[4..7]) {
+            Some(settings) if settings == self.settings => (),
+            Some(settings) => {
+                self.mismatch = Some(settings);
+                return;
+            }
+            None => return,
+        }
+        let acked = read_u32(&packet[7..11]);
+        if acked > self.acked && acked <= self.local_inputs.len() as u32 {
+            self.acked = acked;
+        }
+
+        // The other peer can't be further ahead than it may predict, so
+        // anything past that is a bad packet
+        let first = read_u32(&packet[11..15]);
+        let count = packet[HEADER_SIZE - 1] as usize;
+        if first as u64 + count as u64 > (self.frame + MAX_PREDICTION) as u64 {
+            return;
+        }
+        for (i, &bits) in packet[HEADER_SIZE..HEADER_SIZE + count].iter().enumerate() {
+            let frame = first + i as u32;
+            if self.remote_inputs.len() <= frame as usize {
+                self.remote_inputs.resize(frame as usize + 1, None);
+            }
+            if self.remote_inputs[frame as usize].is_some() {
+                continue;
+            }
+            let input = decode_input(bits);
+            self.remote_inputs[frame as usize] = Some(input);
+
+            // Frames already simulated with a different guess need redoing
+            let mispredicted = self.used_inputs.get(frame as usize).is_some_and(|used| encode_input(used) != bits);
+            if frame < self.frame && mispredicted {
+                self.rollback_to = Some(self.rollback_to.map_or(frame, |f| std::cmp::min(f, frame)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::events::GameEvent;
+
+    /// Two peers talking over loopback, each through a lossy, laggy link.
+    fn connected_peers(latency_ms: u64, loss: f32) -> (LossyTransport<UdpTransport>, LossyTransport<UdpTransport>) {
+        let a = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let b = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let (a_addr, b_addr) = (a.local_addr().unwrap(), b.local_addr().unwrap());
+        (
+            LossyTransport::new(UdpTransport::new(a, b_addr).unwrap(), latency_ms, loss),
+            LossyTransport::new(UdpTransport::new(b, a_addr).unwrap(), latency_ms, loss),
+        )
+    }
+
+    fn co_op_world(seed: u32) -> World {
+        let mut world = World::new(1024, 768);
+        world.player_count = 2;
+        world.seed(seed);
+        world.reset();
+        world
+    }
+
+    const CLASSIC: Settings = Settings {
+        play_mode: PlayMode::Classic,
+        difficulty: Difficulty::Normal,
+        adaptive: false,
+    };
+
+    /// A packet of the run `id` with the inputs of frames from `first` on.
+    fn packet(id: u32, settings: Settings, first: u32, inputs: &[Input]) -> Vec<u8> {
+        let mut packet = vec![];
+        write_u32(&mut packet, id);
+        packet.extend(&settings.encode());
+        write_u32(&mut packet, 0);
+        write_u32(&mut packet, first);
+        packet.push(inputs.len() as u8);
+        packet.extend(inputs.iter().map(encode_input));
+        packet
+    }
+
+    /// Made-up controls that change often enough to get mispredicted.
+    fn scripted_input(slot: usize, frame: u32) -> Input {
+        decode_input(((frame / (7 + slot as u32 * 4)) as u8).wrapping_mul(37 + slot as u8) & 0b11_1111)
+    }
+
+    #[test]
+    fn inputs_survive_a_round_trip() {
+        for bits in 0..64 {
+            assert_eq!(encode_input(&decode_input(bits)), bits);
+        }
+        let mut packet = vec![];
+        write_u32(&mut packet, 123_456_789);
+        assert_eq!(read_u32(&packet), 123_456_789);
+    }
+
+    #[test]
+    fn options_are_read_from_the_command_line() {
+        let args = "ferris --netplay 127.0.0.1:7000 127.0.0.1:7001 2 --latency 80 --loss 5";
+        let options = Options::from_args(args.split(' ').map(|s| s.to_string())).unwrap().unwrap();
+        assert_eq!(options.slot, 1);
+        assert_eq!(options.latency_ms, 80);
+        assert!((options.loss - 0.05).abs() < 0.001);
+        assert!(Options::from_args(vec!["ferris".to_string()].into_iter()).unwrap().is_none());
+    }
+
+    #[test]
+    fn packets_from_other_runs_are_ignored() {
+        let (a, _) = connected_peers(0, 0.0);
+        let mut session = Session::new(a, 0, 0, 2, CLASSIC);
+        let shoot = [Input { shoot: true, ..Input::default() }];
+        session.receive(&packet(1, CLASSIC, 0, &shoot));
+        assert_eq!(session.confirmed(), 0);
+
+        // Nor are inputs too far ahead to be real
+        session.receive(&packet(2, CLASSIC, u32::MAX - 1, &shoot));
+        session.receive(&packet(2, CLASSIC, MAX_PREDICTION, &shoot));
+        assert_eq!(session.remote_inputs.len(), 0);
+
+        session.receive(&packet(2, CLASSIC, 0, &shoot));
+        assert_eq!(session.confirmed(), 1);
+    }
+
+    #[test]
+    fn peers_with_other_settings_are_refused() {
+        let (a, _) = connected_peers(0, 0.0);
+        let mut session = Session::new(a, 0, 0, 3, CLASSIC);
+        let endless = Settings { play_mode: PlayMode::Endless, difficulty: Difficulty::Hard, ..CLASSIC };
+        session.receive(&packet(3, endless, 0, &[Input::default()]));
+        assert_eq!(session.confirmed(), 0);
+        assert_eq!(session.mismatch, Some(endless));
+
+        // Adaptive difficulty changes the enemies too
+        let adaptive = Settings { adaptive: true, ..CLASSIC };
+        session.mismatch = None;
+        session.receive(&packet(3, adaptive, 0, &[Input::default()]));
+        assert_eq!((session.confirmed(), session.mismatch), (0, Some(adaptive)));
+
+        // Unknown settings are just a bad packet
+        let mut bad = packet(3, CLASSIC, 0, &[Input::default()]);
+        bad[4] = 200;
+        session.mismatch = None;
+        session.receive(&bad);
+        assert_eq!((session.confirmed(), session.mismatch), (0, None));
+    }
+
+    #[test]
+    fn events_wait_for_the_frames_to_be_confirmed() {
+        let (a, _) = connected_peers(0, 0.0);
+        let mut session = Session::new(a, 0, 0, 4, CLASSIC);
+        let mut world = co_op_world(4);
+        for _ in 0..3 {
+            session.advance(&mut world, Input::default());
+        }
+        assert!(world.events.is_empty());
+
+        // The other player set off a bomb straight away, which only the
+        // events of simulating the frames again know about
+        let bomb = Input { bomb: true, ..Input::default() };
+        session.receive(&packet(4, CLASSIC, 0, &[bomb, Input::default(), Input::default()]));
+        session.poll(&mut world);
+        assert_eq!(session.rollbacks, 1);
+        assert_eq!(world.events, vec![GameEvent::BombUsed { slot: 1 }]);
+    }
+
+    #[test]
+    fn one_bomb_press_sets_off_one_bomb() {
+        let (a, _) = connected_peers(0, 0.0);
+        let mut session = Session::new(a, 0, 0, 5, CLASSIC);
+        let mut world = co_op_world(5);
+        let bombs = world.players[0].bombs;
+
+        // A stall leaves several frames to catch up on at once
+        let bomb = Input { bomb: true, ..Input::default() };
+        assert_eq!(session.run(&mut world, bomb, FRAME_MS * MAX_FRAMES_PER_RUN as u64), MAX_FRAMES_PER_RUN);
+        assert_eq!(world.players[0].bombs, bombs - 1);
+        assert_eq!(session.local_inputs.iter().filter(|input| input.bomb).count(), 1);
+    }
+
+    #[test]
+    fn peers_agree_after_rolling_back_over_a_bad_connection() {
+        const FRAMES: u32 = 600;
+        let (a, b) = connected_peers(100, 0.2);
+        let mut peers = [
+            (Session::new(a, 0, 0, 7, CLASSIC), co_op_world(7)),
+            (Session::new(b, 1, 0, 7, CLASSIC), co_op_world(7)),
+        ];
+
+        // Keep both peers going until each has every input of the other
+        let mut ticks = 0;
+        while peers.iter().any(|(session, _)| session.frame < FRAMES || session.confirmed() < FRAMES) {
+            for (slot, (session, world)) in peers.iter_mut().enumerate() {
+                session.transport.update(FRAME_MS);
+                if session.frame < FRAMES {
+                    let input = scripted_input(slot, session.frame);
+                    session.advance(world, input);
+                } else {
+                    session.poll(world);
+                }
+            }
+            // Give the packets a moment to cross the loopback
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            ticks += 1;
+            assert!(ticks < FRAMES * 20, "peers never caught up");
+        }
+
+        assert!(peers[0].0.rollbacks > 0 && peers[1].0.rollbacks > 0);
+        assert_eq!(checksum(&peers[0].1), checksum(&peers[1].1));
+        assert_eq!(peers[0].1.score, peers[1].1.score);
+
+        // Either side's recording plays back to the same world
+        for (session, world) in peers.iter() {
+            let mut replay = Replay::start(&co_op_world(7));
+            session.record(&mut replay);
+            assert_eq!(replay.play().unwrap().checksum, checksum(world));
+        }
+    }
+}
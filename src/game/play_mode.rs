@@ -0,0 +1,223 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+use game::entity::EntityType;
+use game::scores::Ranking;
+
+/// Bosses to beat to win a boss rush.
+pub const BOSS_RUSH_BOSSES: u32 = 5;
+/// Length of a time attack run (in seconds).
+pub const TIME_ATTACK_SECONDS: u64 = 3 * 60;
+/// Latest time a practice run can start at (in minutes).
+const MAX_PRACTICE_START: u64 = 7;
+
+/// The rules a run is played by.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, Serialize, Deserialize)]
+pub enum PlayMode {
+    /// Survive until the difficulty has fully ramped up to win.
+    Classic,
+    /// There is no win, the game keeps getting harder until the player dies.
+    Endless,
+    /// Bosses one after another, each arriving once the last is gone.
+    BossRush,
+    /// Start anywhere in a run with any set of enemies and never die.
+    /// Scores aren't saved.
+    Practice,
+    /// Score as much as possible before the time runs out.
+    TimeAttack,
+}
+
+/// Which enemies the spawner sends.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, Serialize, Deserialize)]
+pub enum EnemySet {
+    All,
+    Bugs,
+    BlueScreens,
+    Bosses,
+}
+
+/// Settings picked on the title screen for practice runs.
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub struct PracticeOptions {
+    /// How far into a run to start (in minutes).
+    pub start_minutes: u64,
+    pub enemies: EnemySet,
+}
+
+impl PlayMode {
+    /// Every mode, in the order they are shown on the title screen.
+    pub fn all() -> [PlayMode; 5] {
+        [
+            PlayMode::Classic,
+            PlayMode::Endless,
+            PlayMode::BossRush,
+            PlayMode::TimeAttack,
+            PlayMode::Practice,
+        ]
+    }
+
+    /// Name shown on the title screen.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            PlayMode::Classic => "CLASSIC",
+            PlayMode::Endless => "ENDLESS",
+            PlayMode::BossRush => "BOSS RUSH",
+            PlayMode::Practice => "PRACTICE",
+            PlayMode::TimeAttack => "TIME ATTACK",
+        }
+    }
+
+    /// The next mode on the title screen, wrapping around to the first.
+    pub fn next(&self) -> PlayMode {
+        let modes = PlayMode::all();
+        let i = modes.iter().position(|m| m == self).unwrap();
+        modes[(i + 1) % modes.len()]
+    }
+
+    /// The previous mode on the title screen, wrapping around to the last.
+    pub fn previous(&self) -> PlayMode {
+        let modes = PlayMode::all();
+        let i = modes.iter().position(|m| m == self).unwrap();
+        modes[(i + modes.len() - 1) % modes.len()]
+    }
+
+    /// Returns true if the game keeps getting harder once the difficulty
+    /// has fully ramped up.
+    pub fn escalates(&self) -> bool {
+        *self == PlayMode::Endless
+    }
+
+    /// Returns true if the player can't die.
+    pub fn infinite_lives(&self) -> bool {
+        *self == PlayMode::Practice
+    }
+
+    /// Which enemies get spawned.
+    pub fn enemies(&self, practice: &PracticeOptions) -> EnemySet {
+        match *self {
+            PlayMode::BossRush => EnemySet::Bosses,
+            PlayMode::Practice => practice.enemies,
+            _ => EnemySet::All,
+        }
+    }
+
+    /// How far into a run the clock starts (in ms).
+    pub fn start_ms(&self, practice: &PracticeOptions) -> u64 {
+        match *self {
+            PlayMode::Practice => practice.start_minutes * 60 * 1000,
+            _ => 0,
+        }
+    }
+
+    /// Returns true if runs of this mode go on the high-score tables.
+    pub fn keeps_scores(&self) -> bool {
+        *self != PlayMode::Practice
+    }
+
+    /// How the leaderboard for this mode is ordered.
+    pub fn ranking(&self) -> Ranking {
+        match *self {
+            PlayMode::Endless => Ranking::Survival,
+            _ => Ranking::Score,
+        }
+    }
+}
+
+impl EnemySet {
+    /// Every set, in the order they are shown on the title screen.
+    pub fn all() -> [EnemySet; 4] {
+        [EnemySet::All, EnemySet::Bugs, EnemySet::BlueScreens, EnemySet::Bosses]
+    }
+
+    /// Name shown on the title screen.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            EnemySet::All => "ALL",
+            EnemySet::Bugs => "BUGS",
+            EnemySet::BlueScreens => "BSOD",
+            EnemySet::Bosses => "BOSSES",
+        }
+    }
+
+    /// The next set on the title screen, wrapping around to the first.
+    pub fn next(&self) -> EnemySet {
+        let sets = EnemySet::all();
+        let i = sets.iter().position(|s| s == self).unwrap();
+        sets[(i + 1) % sets.len()]
+    }
+
+    /// The previous set on the title screen, wrapping around to the last.
+    pub fn previous(&self) -> EnemySet {
+        let sets = EnemySet::all();
+        let i = sets.iter().position(|s| s == self).unwrap();
+        sets[(i + sets.len() - 1) % sets.len()]
+    }
+
+    /// Returns true if enemies of this type are part of the set. Specials
+    /// carrying power-ups come with every set.
+    pub fn includes(&self, entity_type: EntityType) -> bool {
+        matches!(
+            (*self, entity_type),
+            (_, EntityType::Special)
+                | (EnemySet::All, _)
+                | (EnemySet::Bugs, EntityType::Enemy)
+                | (EnemySet::BlueScreens, EntityType::EnemyBlueScreen)
+                | (EnemySet::Bosses, EntityType::Boss)
+        )
+    }
+}
+
+impl PracticeOptions {
+    /// Start at the beginning with every enemy.
+    pub fn new() -> PracticeOptions {
+        PracticeOptions {
+            start_minutes: 0,
+            enemies: EnemySet::All,
+        }
+    }
+
+    /// Starts a minute later, wrapping around to the beginning.
+    pub fn later_start(&mut self) {
+        self.start_minutes = (self.start_minutes + 1) % (MAX_PRACTICE_START + 1);
+    }
+
+    /// Starts a minute earlier, wrapping around to the latest start.
+    pub fn earlier_start(&mut self) {
+        self.start_minutes = (self.start_minutes + MAX_PRACTICE_START) % (MAX_PRACTICE_START + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modes_cycle_both_ways() {
+        for &mode in PlayMode::all().iter() {
+            assert_eq!(mode.next().previous(), mode);
+        }
+        assert_eq!(PlayMode::Practice.next(), PlayMode::Classic);
+    }
+
+    #[test]
+    fn enemy_sets_always_include_specials() {
+        for &set in EnemySet::all().iter() {
+            assert!(set.includes(EntityType::Special));
+        }
+        assert!(!EnemySet::Bosses.includes(EntityType::Enemy));
+        assert!(EnemySet::All.includes(EntityType::Boss));
+    }
+
+    #[test]
+    fn practice_start_wraps() {
+        let mut practice = PracticeOptions::new();
+        practice.earlier_start();
+        assert_eq!(practice.start_minutes, MAX_PRACTICE_START);
+        practice.later_start();
+        assert_eq!(practice.start_minutes, 0);
+    }
+}
@@ -0,0 +1,77 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+use game::entity_store::EntityId;
+use game::status::StatusEffects;
+use game::weapons::Arsenal;
+use game::STARTING_BOMBS;
+
+/// Most players that can play at once.
+pub const MAX_PLAYERS: usize = 2;
+/// How long a downed player waits to come back, as long as a partner is
+/// still alive (in ms).
+pub const REVIVE_DELAY: i64 = 10_000;
+/// Hit points a revived player comes back with.
+pub const REVIVE_HP: i32 = 2;
+
+/// Everything about one of the players that lasts for the whole run. The
+/// crab itself is an entity in the world, which goes away when it dies.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Player {
+    /// Handle of this player's crab, or `None` while downed.
+    pub entity: Option<EntityId>,
+    /// The player's guns and their levels
+    pub arsenal: Arsenal,
+    /// Shields and timed power-ups on the player
+    pub status: StatusEffects,
+    /// Bombs the player has in stock
+    pub bombs: u32,
+    /// Time left during which the player can't be hurt (in ms)
+    pub invulnerable_ms: i64,
+    /// Speed the player is being pushed at after a hit (in pixels per second)
+    pub knockback: (f32, f32),
+    /// Bullets grazed this run
+    pub grazes: u32,
+    /// Charge towards the next bomb earned by grazing
+    pub graze_meter: u32,
+    /// Time left for the player to glow after a graze (in ms)
+    pub graze_flash_ms: i64,
+    /// Time left until a downed player comes back (in ms), or `None` if
+    /// the player is in play or out for good.
+    pub revive_ms: Option<i64>,
+    /// Whether shoot was held on the last update, so letting go of it can
+    /// be told apart from not shooting
+    pub trigger_held: bool,
+}
+
+impl Player {
+    /// A fresh player with a level 1 gun and the starting bombs.
+    pub fn new() -> Player {
+        Player {
+            entity: None,
+            arsenal: Arsenal::new(),
+            status: StatusEffects::new(),
+            bombs: STARTING_BOMBS,
+            invulnerable_ms: 0,
+            knockback: (0.0, 0.0),
+            grazes: 0,
+            graze_meter: 0,
+            graze_flash_ms: 0,
+            revive_ms: None,
+            trigger_held: false,
+        }
+    }
+
+    /// Counts down the player's power-ups and timers.
+    pub fn update(&mut self, delta_ms: u64) {
+        self.status.update(delta_ms);
+        self.invulnerable_ms -= delta_ms as i64;
+        if self.invulnerable_ms < 0 {
+            self.invulnerable_ms = 0;
+        }
+    }
+}
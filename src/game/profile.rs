@@ -0,0 +1,254 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+//! Lifetime stats and achievements. Each user has a profile that adds up
+//! every run they play, kept in its own file next to the high scores.
+
+extern crate serde_json;
+
+use game::entity::EntityType;
+use game::events::GameEvent;
+use game::player::MAX_PLAYERS;
+use game::world::World;
+use game::{ENEMY_NAMES, MAX_UPGRADE_LEVEL};
+use std;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::prelude::*;
+
+/// SEGFAULTs to kill for `Achievement::Exterminator`.
+const EXTERMINATOR_KILLS: u32 = 100;
+/// Bullets to graze for `Achievement::CloseCalls`.
+const CLOSE_CALL_GRAZES: u32 = 1_000;
+/// How long one run has to last for `Achievement::Survivor` (in ms).
+const SURVIVOR_MS: u64 = 10 * 60 * 1000;
+
+/// Goals that unlock once and stay unlocked.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Achievement {
+    Exterminator,
+    Flawless,
+    FullyUpgraded,
+    BugCollector,
+    CloseCalls,
+    Survivor,
+}
+
+impl Achievement {
+    pub fn all() -> [Achievement; 6] {
+        [
+            Achievement::Exterminator,
+            Achievement::Flawless,
+            Achievement::FullyUpgraded,
+            Achievement::BugCollector,
+            Achievement::CloseCalls,
+            Achievement::Survivor,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Achievement::Exterminator => "EXTERMINATOR",
+            Achievement::Flawless => "ZERO COST ABSTRACTION",
+            Achievement::FullyUpgraded => "FULLY UPGRADED",
+            Achievement::BugCollector => "BUG COLLECTOR",
+            Achievement::CloseCalls => "UNSAFE",
+            Achievement::Survivor => "LONG RUNNING PROCESS",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match *self {
+            Achievement::Exterminator => "KILL 100 SEGFAULTS",
+            Achievement::Flawless => "BEAT ANSI C WITHOUT TAKING DAMAGE",
+            Achievement::FullyUpgraded => "UPGRADE THE SPREAD GUN ALL THE WAY",
+            Achievement::BugCollector => "KILL ONE OF EVERY BUG",
+            Achievement::CloseCalls => "GRAZE 1000 BULLETS",
+            Achievement::Survivor => "LAST 10 MINUTES IN ONE RUN",
+        }
+    }
+}
+
+/// What a user has done over every run they have played, along with the
+/// achievements they have unlocked. Practice runs don't count.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub runs: u32,
+    /// Time spent in runs (in ms)
+    pub play_ms: u64,
+    /// Enemies killed, by name tag.
+    pub kills: BTreeMap<String, u32>,
+    /// Items collected, by name.
+    pub pickups: BTreeMap<String, u32>,
+    /// Times a player went down, by what dealt the last hit.
+    pub deaths: BTreeMap<EntityType, u32>,
+    pub grazes: u32,
+    pub unlocked: BTreeSet<Achievement>,
+    /// Progress through the current run, which only matters while it goes.
+    #[serde(skip)]
+    run: RunProgress,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct RunProgress {
+    /// What last hurt each player.
+    last_hit: [Option<EntityType>; MAX_PLAYERS],
+    /// While a boss is up, whether the players have gone unhurt since it
+    /// arrived.
+    unhurt_by_boss: Option<bool>,
+}
+
+impl Profile {
+    pub fn new() -> Profile {
+        Profile::default()
+    }
+
+    /// Reads a profile saved with `save`.
+    pub fn load(file: &str) -> Result<Profile, String> {
+        let mut json = String::new();
+        File::open(file)
+            .and_then(|mut f| f.read_to_string(&mut json))
+            .map_err(|e| e.to_string())?;
+        serde_json::from_str(&json).map_err(|e| e.to_string())
+    }
+
+    /// Writes the profile to a file, replacing what was there.
+    pub fn save(&self, file: &str) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self).expect("Could not serialize the profile");
+        File::create(file)?.write_all(json.as_bytes())
+    }
+
+    /// Counts a new run and forgets the progress of the last one.
+    pub fn start_run(&mut self) {
+        self.runs += 1;
+        self.run = RunProgress::default();
+    }
+
+    /// Adds what happened during a frame of a run `elapsed_ms` in, and
+    /// returns any achievements that were unlocked by it.
+    pub fn record(&mut self, events: &[GameEvent], world: &World, elapsed_ms: u64, delta_ms: u64) -> Vec<Achievement> {
+        self.play_ms += delta_ms;
+        let mut achieved = vec![];
+
+        for event in events {
+            match *event {
+                GameEvent::EnemyKilled { kind, ref name, .. } => {
+                    let name = name.clone().unwrap_or(format!("{:?}", kind).to_uppercase());
+                    *self.kills.entry(name).or_insert(0) += 1;
+                }
+                GameEvent::PickupCollected { kind, .. } => *self.pickups.entry(format!("{:?}", kind)).or_insert(0) += 1,
+                GameEvent::Grazed { bullets, .. } => self.grazes += bullets,
+                GameEvent::PlayerHit { slot, source, absorbed: false, .. } => {
+                    self.run.last_hit[slot] = Some(source);
+                    if self.run.unhurt_by_boss.is_some() {
+                        self.run.unhurt_by_boss = Some(false);
+                    }
+                }
+                GameEvent::PlayerDown { slot } => {
+                    if let Some(cause) = self.run.last_hit[slot] {
+                        *self.deaths.entry(cause).or_insert(0) += 1;
+                    }
+                }
+                GameEvent::BossArrived if self.run.unhurt_by_boss.is_none() => {
+                    self.run.unhurt_by_boss = Some(true);
+                }
+                GameEvent::BossDefeated => {
+                    if self.run.unhurt_by_boss == Some(true) {
+                        achieved.push(Achievement::Flawless);
+                    }
+                    self.run.unhurt_by_boss = None;
+                }
+                _ => (),
+            }
+        }
+
+        if self.kills.get("SEGFAULT").is_some_and(|&kills| kills >= EXTERMINATOR_KILLS) {
+            achieved.push(Achievement::Exterminator);
+        }
+        if world.players.iter().any(|p| p.arsenal.level() >= MAX_UPGRADE_LEVEL) {
+            achieved.push(Achievement::FullyUpgraded);
+        }
+        if ENEMY_NAMES.iter().all(|name| self.kills.contains_key(*name)) {
+            achieved.push(Achievement::BugCollector);
+        }
+        if self.grazes >= CLOSE_CALL_GRAZES {
+            achieved.push(Achievement::CloseCalls);
+        }
+        if elapsed_ms >= SURVIVOR_MS {
+            achieved.push(Achievement::Survivor);
+        }
+
+        // Only report each achievement the first time
+        achieved.retain(|achievement| self.unlocked.insert(*achievement));
+        achieved
+    }
+}
+
+/// Where a user's profile is saved.
+pub fn profile_file(user: &str) -> String {
+    format!("profile-{}.json", user)
+}
+
+/// Name of what took a player out, for the stats page.
+pub fn cause_name(cause: EntityType) -> &'static str {
+    match cause {
+        EntityType::Enemy => "BUGS",
+        EntityType::EnemyBullet => "BULLETS",
+        EntityType::EnemyBlueScreen => "BLUE SCREENS",
+        EntityType::Boss => "ANSI C",
+        EntityType::Special => "SPECIALS",
+        _ => "OTHER",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kill(name: &str) -> GameEvent {
+        GameEvent::EnemyKilled { kind: EntityType::Enemy, name: Some(name.to_string()), x: 0.0, y: 0.0, bounty: 10 }
+    }
+
+    #[test]
+    fn unlocks_each_achievement_once() {
+        let mut world = World::new(1024, 768);
+        world.reset();
+        let mut profile = Profile::new();
+        profile.start_run();
+
+        let kills: Vec<GameEvent> = (0..EXTERMINATOR_KILLS - 1).map(|_| kill("SEGFAULT")).collect();
+        assert!(profile.record(&kills, &world, 0, 16).is_empty());
+        assert_eq!(profile.record(&[kill("SEGFAULT")], &world, 0, 16), vec![Achievement::Exterminator]);
+        assert!(profile.record(&[kill("SEGFAULT")], &world, 0, 16).is_empty());
+        assert_eq!(profile.kills["SEGFAULT"], EXTERMINATOR_KILLS + 1);
+        assert_eq!(profile.play_ms, 48);
+    }
+
+    #[test]
+    fn beating_the_boss_unhurt_counts_and_deaths_keep_their_cause() {
+        let mut world = World::new(1024, 768);
+        world.reset();
+        let mut profile = Profile::new();
+        let hit = GameEvent::PlayerHit { slot: 0, source: EntityType::EnemyBullet, damage: 1, absorbed: false };
+
+        // Hurt during the first boss fight, but not the second
+        profile.start_run();
+        profile.record(&[GameEvent::BossArrived, hit.clone()], &world, 0, 16);
+        assert!(profile.record(&[GameEvent::BossDefeated], &world, 0, 16).is_empty());
+        profile.record(&[GameEvent::BossArrived], &world, 0, 16);
+        assert_eq!(profile.record(&[GameEvent::BossDefeated], &world, 0, 16), vec![Achievement::Flawless]);
+
+        profile.record(&[hit, GameEvent::PlayerDown { slot: 0 }], &world, 0, 16);
+        assert_eq!(profile.deaths[&EntityType::EnemyBullet], 1);
+
+        // Progress is saved, but the boss fight isn't
+        let json = serde_json::to_string(&profile).unwrap();
+        let loaded: Profile = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.unlocked, profile.unlocked);
+        assert_eq!(loaded.deaths, profile.deaths);
+    }
+}
@@ -0,0 +1,258 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+extern crate ggez;
+
+use ggez::{Context, GameResult};
+use ggez::graphics;
+use game::MainState;
+use game::components::{Lifetime, PickupKind, Transform};
+use game::entity::EntityType;
+use game::status::StatusKind;
+use game::weapons::WeaponKind;
+use game::{ANIMATION_FRAMERATE, DRAW_BOUNDING_BOXES, ENEMY_LIFETIME, GRAZE_FLASH, GRAZE_MARGIN, PIXEL_SKIP, POPUP_LIFETIME, SPLAT_LIFETIME};
+use std;
+
+/// Draws every entity that has a sprite, along with its name tag.
+pub fn render_system(ctx: &mut Context, state: &MainState) -> GameResult<()> {
+    let world = &state.world;
+    let (shake_x, shake_y) = world.screen_shake(state.elapsed_ms);
+
+    for (id, sprite) in world.sprites.iter() {
+        let e = match world.transforms.get(id) {
+            Some(transform) => Transform {
+                x: transform.x + shake_x,
+                y: transform.y + shake_y,
+                ..*transform
+            },
+            None => continue,
+        };
+        let lifetime = world.lifetimes.get(id).cloned().unwrap_or(Lifetime::Forever);
+
+        // Some effects have no texture and are drawn with shapes instead
+        match sprite.texture {
+            EntityType::Laser => {
+                if let Some(hitbox) = world.hitboxes.get(id) {
+                    let beam = graphics::Rect {
+                        x: e.x + hitbox.bounds.x,
+                        y: e.y + hitbox.bounds.y,
+                        w: hitbox.bounds.w,
+                        h: hitbox.bounds.h,
+                    };
+                    graphics::set_color(ctx, graphics::Color::new(1.0, 0.45, 0.1, 0.8))?;
+                    graphics::rectangle(ctx, graphics::DrawMode::Fill, beam)?;
+                    graphics::set_color(ctx, graphics::Color::new(1.0, 0.9, 0.6, 1.0))?;
+                    graphics::rectangle(ctx, graphics::DrawMode::Fill, graphics::Rect {
+                        x: beam.x + beam.w / 3.0,
+                        w: beam.w / 3.0,
+                        ..beam
+                    })?;
+                    graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
+                }
+                continue;
+            },
+            EntityType::Popup => {
+                if let (Some(text), Lifetime::Milliseconds(r)) = (sprite.label.as_ref(), lifetime) {
+                    // Fade out as it floats away
+                    let alpha = r as f32 / POPUP_LIFETIME as f32;
+                    let popup = graphics::Text::new(ctx, text, &state.label_font)?;
+                    graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 0.6, alpha))?;
+                    graphics::draw(ctx, &popup, graphics::Point2::new(e.x, e.y), 0.0)?;
+                    graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
+                }
+                continue;
+            },
+            EntityType::Blast => {
+                if let Some(blast) = world.blasts.get(id) {
+                    // Fade the ring out as it reaches its full size
+                    let alpha = 1.0 - blast.radius / blast.max_radius;
+                    let center = graphics::Point2::new(e.x, e.y);
+                    graphics::set_color(ctx, graphics::Color::new(1.0, 0.6, 0.2, alpha * 0.3))?;
+                    graphics::circle(ctx, graphics::DrawMode::Fill, center, blast.radius, 2.0)?;
+                    graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, alpha))?;
+                    graphics::circle(ctx, graphics::DrawMode::Line(8.0), center, blast.radius, 2.0)?;
+                    graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
+                }
+                continue;
+            },
+            _ => (),
+        }
+
+        let pos = graphics::Point2::new((e.x as i32 / PIXEL_SKIP * PIXEL_SKIP ) as f32, (e.y as i32 / PIXEL_SKIP * PIXEL_SKIP) as f32);
+
+        // If the texure is animated, grab the right frame, otherwise grab frame 0.
+        let frames = &state.textures[&sprite.texture];
+        let texture = match frames.len() {
+            1 => &frames[0],
+            total_frames => {
+                let frame = (state.elapsed_ms as f64 / 1000.0 * ANIMATION_FRAMERATE)
+                    as usize % total_frames;
+                &frames[frame]
+            }
+        };
+
+        // Special drawing conditions start
+        match sprite.texture {
+            EntityType::Player => {
+                let slot = world.player_slot(id).unwrap_or(0);
+                let player = &world.players[slot];
+                // Blink while invulnerable
+                if player.invulnerable_ms > 0 && player.invulnerable_ms / 100 % 2 == 1 {
+                    continue;
+                }
+                // Glow around the hitbox after grazing a bullet
+                if player.graze_flash_ms > 0 {
+                    if let Some(hitbox) = world.hitboxes.get(id) {
+                        let alpha = player.graze_flash_ms as f32 / GRAZE_FLASH as f32;
+                        let center = graphics::Point2::new(
+                            e.x + hitbox.bounds.x + hitbox.bounds.w / 2.0,
+                            e.y + hitbox.bounds.y + hitbox.bounds.h / 2.0,
+                        );
+                        graphics::set_color(ctx, graphics::Color::new(0.6, 0.9, 1.0, alpha))?;
+                        graphics::circle(ctx, graphics::DrawMode::Line(3.0), center, GRAZE_MARGIN, 1.0)?;
+                        graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
+                    }
+                }
+                if player.status.has(StatusKind::Shield) {
+                    graphics::set_color(ctx, graphics::Color::new(0.3, 1.0, 0.3, 1.0))?
+                } else if slot == 1 {
+                    // Tell the second crab apart with a blue tint
+                    graphics::set_color(ctx, graphics::Color::new(0.6, 0.8, 1.0, 1.0))?
+                }
+            },
+            // Elite enemies glow red
+            EntityType::Enemy | EntityType::EnemyBlueScreen if world.elites.contains(id) => {
+                graphics::set_color(ctx, graphics::Color::new(1.0, 0.4, 0.4, 1.0))?
+            },
+            EntityType::Boss => match world.healths.get(id).map_or(0, |h| h.hp) {
+                0..=10 => graphics::set_color(
+                    ctx,
+                    graphics::Color::new(1.0, 0.25, 0.25, 1.0),
+                )?,
+                11..=20 => {
+                    graphics::set_color(ctx, graphics::Color::new(1.0, 0.5, 0.5, 1.0))?
+                }
+                _ => {
+                    graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?
+                }
+            },
+            EntityType::GunUpgrade => {
+                if let Some(PickupKind::Weapon(kind)) = world.pickups.get(id).map(|p| p.kind) {
+                    let (r, g, b) = match kind {
+                        WeaponKind::Spread => (1.0, 1.0, 0.4),
+                        WeaponKind::Laser => (1.0, 0.4, 0.4),
+                        WeaponKind::Homing => (0.4, 1.0, 0.4),
+                        WeaponKind::Piercing => (0.4, 0.6, 1.0),
+                    };
+                    graphics::set_color(ctx, graphics::Color::new(r, g, b, 1.0))?
+                }
+            },
+            EntityType::Shield => {
+                if let Some(PickupKind::Status(kind)) = world.pickups.get(id).map(|p| p.kind) {
+                    let (r, g, b) = status_color(kind);
+                    graphics::set_color(ctx, graphics::Color::new(r, g, b, 1.0))?
+                }
+            },
+            EntityType::Splat | EntityType::Shutoff => {
+                let alpha: f32 = match lifetime {
+                    Lifetime::Forever => 1.0_f32,
+                    Lifetime::Milliseconds(r) => r as f32 / SPLAT_LIFETIME as f32,
+                };
+                graphics::set_color(
+                    ctx,
+                    graphics::Color::new(alpha, alpha, alpha, alpha),
+                )?;
+            },
+            _ => {}
+        }
+
+        // Draw the entity sprite rotated around center of sprite if needed
+        // Non-square sprites may not rotate correctly
+        if e.angle == 0.0 {
+            graphics::draw(ctx, texture, pos, e.angle)?;
+        } else {
+            let half_width = texture.width() as f64 / 2.0;
+            let angle = -e.angle as f64 + (5.0 * std::f64::consts::PI / 4.0);
+            let x = (half_width + half_width * (2.0_f64).sqrt() * angle.cos()) as f32;
+            let y = (half_width + half_width * (2.0_f64).sqrt() * angle.sin()) as f32;
+            graphics::draw(
+                ctx,
+                texture,
+                graphics::Point2::new(pos.x + x, pos.y + y),
+                -e.angle,
+            )?;
+        }
+
+        // End drawing conditions: Reset drawing conditions
+        graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
+
+        // If this entity has a name, include a name tag.
+        if let Some(ref name) = sprite.label {
+            let label = &state.labels[name];
+
+            // Dim label after a while
+            match lifetime {
+                Lifetime::Forever => (),
+                Lifetime::Milliseconds(r) => {
+                    let fraction_of_life = ( ENEMY_LIFETIME as f32 - r as f32 ) / ENEMY_LIFETIME as f32;
+                    let mut alpha = 1.0 - fraction_of_life * 15.0;
+                    if alpha < 0.0 {
+                        alpha = 0.0;
+                    }
+                    graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, alpha))?;
+                },
+            };
+
+            // Calculate label position
+            let offset = 30;
+            let text_pos = graphics::Point2::new(
+                ((e.x as i32 + texture.width() as i32 + offset + 6) / PIXEL_SKIP * PIXEL_SKIP ) as f32,
+                ((e.y as i32 - offset - 6) / PIXEL_SKIP * PIXEL_SKIP) as f32);
+
+            // Draw the label
+            graphics::draw(ctx, label, text_pos, 0.0)?;
+
+            // Draw a line connecting it to entity
+            graphics::line(ctx, &[
+                graphics::Point2::new(text_pos.x - 6.0, text_pos.y + label.height() as f32),
+                graphics::Point2::new(text_pos.x - offset as f32, text_pos.y + offset as f32)
+            ], 4.0)?;
+
+            // Reset color
+            graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
+        }
+
+        // Draw collision boxes if they are enabled.
+        if DRAW_BOUNDING_BOXES {
+            if let Some(hitbox) = world.hitboxes.get(id) {
+                graphics::rectangle(
+                    ctx,
+                    graphics::DrawMode::Line(1.0),
+                    graphics::Rect {
+                        x: e.x + hitbox.bounds.x,
+                        y: e.y + hitbox.bounds.y,
+                        w: hitbox.bounds.w,
+                        h: hitbox.bounds.h,
+                    },
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Tint used for the pickup and HUD icon of each power-up.
+pub fn status_color(kind: StatusKind) -> (f32, f32, f32) {
+    match kind {
+        StatusKind::Shield => (1.0, 1.0, 1.0),
+        StatusKind::RapidFire => (1.0, 0.5, 0.3),
+        StatusKind::SpeedBoost => (0.3, 0.8, 1.0),
+        StatusKind::Magnet => (1.0, 0.3, 1.0),
+        StatusKind::ScoreMultiplier => (1.0, 0.9, 0.2),
+    }
+}
@@ -0,0 +1,245 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+//! Recorded runs. A replay keeps the seed and settings a run started with
+//! and the input of every update, which is all it takes to play the run
+//! again exactly, and so to check the score it claims.
+
+extern crate serde_json;
+
+use game::Input;
+use game::difficulty::Difficulty;
+use game::fnv::Fnv1a;
+use game::netplay;
+use game::play_mode::PlayMode;
+use game::world::World;
+use std;
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::prelude::*;
+
+/// Version of the game that recorded a replay. The same inputs can play
+/// out differently once gameplay changes, so replays only play back on
+/// the version that recorded them.
+const GAME_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The seed, settings and inputs of a run.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+    game_version: String,
+    pub seed: u32,
+    pub play_mode: PlayMode,
+    pub difficulty: Difficulty,
+    /// Whether adaptive difficulty was on
+    pub adaptive: bool,
+    pub player_count: usize,
+    pub screen_width: u32,
+    pub screen_height: u32,
+    /// Every update of the run, oldest first
+    frames: Vec<Frame>,
+}
+
+/// One update of a recorded run.
+#[derive(Clone, Serialize, Deserialize)]
+struct Frame {
+    elapsed_ms: u64,
+    delta_ms: u64,
+    /// Input of each player, packed as in netplay
+    inputs: Vec<u8>,
+}
+
+/// How a replay played out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    pub score: i32,
+    /// Time on the run clock after the last update (in ms)
+    pub time_ms: u64,
+    /// `netplay::checksum` of the world at the end
+    pub checksum: u64,
+    /// Whether the run was over by the end, with everyone out or the
+    /// mode's goal met
+    pub finished: bool,
+}
+
+impl Replay {
+    /// Starts recording the run a freshly seeded and reset world is about
+    /// to play.
+    pub fn start(world: &World) -> Replay {
+        Replay {
+            game_version: GAME_VERSION.to_string(),
+            seed: world.run_seed,
+            play_mode: world.play_mode,
+            difficulty: world.difficulty,
+            adaptive: world.adaptive.enabled,
+            player_count: world.player_count,
+            screen_width: world.screen_width as u32,
+            screen_height: world.screen_height as u32,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Adds an update, with the same arguments given to `World::update`.
+    pub fn record(&mut self, inputs: &[Input], elapsed_ms: u64, delta_ms: u64) {
+        self.frames.push(Frame {
+            elapsed_ms,
+            delta_ms,
+            inputs: inputs.iter().map(netplay::encode_input).collect(),
+        });
+    }
+
+    /// Time on the run clock at the last update recorded (in ms).
+    pub fn end_ms(&self) -> u64 {
+        self.frames.last().map_or(0, |frame| frame.elapsed_ms)
+    }
+
+    /// Plays the run again from the start, without a window.
+    pub fn play(&self) -> Result<Outcome, String> {
+        if self.game_version != GAME_VERSION {
+            return Err(format!("replay is from version {}, expected {}", self.game_version, GAME_VERSION));
+        }
+        let mut world = World::new(self.screen_width, self.screen_height);
+        world.play_mode = self.play_mode;
+        world.difficulty = self.difficulty;
+        world.adaptive.enabled = self.adaptive;
+        world.player_count = self.player_count;
+        world.seed(self.seed);
+        world.reset();
+
+        for frame in self.frames.iter() {
+            let inputs: Vec<Input> = frame.inputs.iter().map(|&bits| netplay::decode_input(bits)).collect();
+            world.update(&inputs, frame.elapsed_ms, frame.delta_ms);
+            world.events.clear();
+        }
+
+        Ok(Outcome {
+            score: world.score,
+            time_ms: self.end_ms(),
+            checksum: netplay::checksum(&world),
+            finished: !world.player_alive() || world.won(self.end_ms()),
+        })
+    }
+
+    /// Fingerprint of the whole replay, in hex. Ties a leaderboard entry to
+    /// the inputs it claims to come from.
+    pub fn hash(&self) -> String {
+        let mut hasher = Fnv1a::new();
+        hasher.write(self.to_json().as_bytes());
+        format!("{:016x}", hasher.finish())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Could not serialize the replay")
+    }
+
+    pub fn from_json(json: &str) -> Result<Replay, String> {
+        serde_json::from_str(json).map_err(|e| e.to_string())
+    }
+
+    /// Writes the replay to a file, replacing what was there.
+    pub fn save(&self, file: &str) -> std::io::Result<()> {
+        File::create(file)?.write_all(self.to_json().as_bytes())
+    }
+
+    /// Reads a replay saved with `save`.
+    pub fn load(file: &str) -> Result<Replay, String> {
+        let mut json = String::new();
+        File::open(file)
+            .and_then(|mut f| f.read_to_string(&mut json))
+            .map_err(|e| e.to_string())?;
+        Replay::from_json(&json)
+    }
+}
+
+/// Where the replay of a run is saved, named after its seed, when it ended
+/// (in seconds since the Unix epoch) and how long it lasted (in ms), so
+/// runs that happen to share a seed don't share a file.
+pub fn replay_file(seed: u32, date: u64, time_ms: u64) -> String {
+    format!("replay-{:08x}-{}-{}.json", seed, date, time_ms)
+}
+
+/// Plays a replay again and checks that it is the run a score claims:
+/// same seed, over by the end, and the same score and time. Returns how it
+/// played out, or what doesn't match.
+pub fn verify(replay: &Replay, seed: u32, score: i32, time_ms: u64) -> Result<Outcome, String> {
+    if replay.seed != seed {
+        return Err(format!("replay has seed {}, but the score has {}", replay.seed, seed));
+    }
+    let outcome = replay.play()?;
+    if !outcome.finished {
+        return Err("the run isn't over at the end of the replay".to_string());
+    }
+    if outcome.score != score {
+        return Err(format!("replay scores {}, not {}", outcome.score, score));
+    }
+    if outcome.time_ms != time_ms {
+        return Err(format!("replay lasts {}ms, not {}ms", outcome.time_ms, time_ms));
+    }
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::netplay::FRAME_MS;
+
+    /// Records an insane run of someone moving left and shooting, until
+    /// they are out.
+    fn recorded_run(seed: u32) -> (Replay, World) {
+        let mut world = World::new(1024, 768);
+        world.difficulty = Difficulty::Insane;
+        world.seed(seed);
+        world.reset();
+        let mut replay = Replay::start(&world);
+        let inputs = [Input { shoot: true, left: true, ..Input::default() }];
+        let mut elapsed_ms = 0;
+        while world.player_alive() && elapsed_ms < 30 * 60 * 1000 {
+            elapsed_ms += FRAME_MS;
+            replay.record(&inputs, elapsed_ms, FRAME_MS);
+            world.update(&inputs, elapsed_ms, FRAME_MS);
+            world.events.clear();
+        }
+        (replay, world)
+    }
+
+    #[test]
+    fn replays_play_back_the_recorded_run() {
+        let (replay, world) = recorded_run(5);
+        let loaded = Replay::from_json(&replay.to_json()).unwrap();
+        assert_eq!(loaded.hash(), replay.hash());
+        assert_eq!(loaded.hash().len(), 16);
+        let replay = loaded;
+        let outcome = verify(&replay, 5, world.score, replay.end_ms()).unwrap();
+        assert_eq!(outcome.checksum, netplay::checksum(&world));
+        assert!(outcome.finished);
+    }
+
+    #[test]
+    fn edited_scores_are_caught() {
+        let (replay, world) = recorded_run(6);
+        let time_ms = replay.end_ms();
+        assert!(verify(&replay, 6, world.score + 100, time_ms).is_err());
+        assert!(verify(&replay, 6, world.score, time_ms + 60_000).is_err());
+        assert!(verify(&replay, 7, world.score, time_ms).is_err());
+
+        // Cutting the run short leaves the player alive
+        let mut short = replay.clone();
+        short.frames.truncate(10);
+        assert_ne!(short.hash(), replay.hash());
+        assert!(verify(&short, 6, 0, 0).is_err());
+    }
+
+    #[test]
+    fn hashes_stay_the_same_across_builds() {
+        let mut world = World::new(1024, 768);
+        world.seed(1);
+        world.reset();
+        let mut replay = Replay::start(&world);
+        replay.game_version = "0.1.0".to_string();
+        replay.record(&[Input { shoot: true, ..Input::default() }], FRAME_MS, FRAME_MS);
+        assert_eq!(replay.hash(), "77538fd99ecd9ce5");
+    }
+}
@@ -0,0 +1,67 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+extern crate rand;
+
+/// The xorshift generator from `rand::XorShiftRng`, with its state out in
+/// the open so it can be saved along with the rest of the game. Given the
+/// same seed it always gives the same numbers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameRng {
+    x: u32,
+    y: u32,
+    z: u32,
+    w: u32,
+}
+
+impl GameRng {
+    /// A generator with a random seed.
+    pub fn new() -> GameRng {
+        GameRng::from_seed(rand::random())
+    }
+
+    /// A generator with a fixed seed.
+    pub fn from_seed(seed: [u32; 4]) -> GameRng {
+        // Xorshift gets stuck at all zeroes
+        if seed == [0; 4] {
+            return GameRng::from_seed([1, 0, 0, 0]);
+        }
+        GameRng {
+            x: seed[0],
+            y: seed[1],
+            z: seed[2],
+            w: seed[3],
+        }
+    }
+}
+
+impl rand::Rng for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        let t = self.x ^ (self.x << 11);
+        self.x = self.y;
+        self.y = self.z;
+        self.z = self.w;
+        self.w = self.w ^ (self.w >> 19) ^ (t ^ (t >> 8));
+        self.w
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::rand::{Rng, SeedableRng, XorShiftRng};
+
+    #[test]
+    fn matches_the_rand_xorshift() {
+        let seed = [1, 2, 3, 4];
+        let mut ours = GameRng::from_seed(seed);
+        let mut theirs = XorShiftRng::from_seed(seed);
+        for _ in 0..100 {
+            assert_eq!(ours.next_u32(), theirs.next_u32());
+        }
+    }
+}
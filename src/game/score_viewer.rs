@@ -0,0 +1,183 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+use game::difficulty::Difficulty;
+use game::play_mode::PlayMode;
+use game::scores::{Ranking, ScoreRecord, Scores};
+
+/// Scores shown on each page
+pub const PAGE_SIZE: usize = 15;
+
+/// The high-score browser on the title screen. Picks which table to show,
+/// how to order it and which page of it is up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreViewer {
+    /// Mode to show the scores of, or `None` for every mode
+    pub play_mode: Option<PlayMode>,
+    /// Difficulty to show the scores of, or `None` for every difficulty
+    pub difficulty: Option<Difficulty>,
+    pub ranking: Ranking,
+    /// Page showing, starting at 0
+    pub page: usize,
+}
+
+impl ScoreViewer {
+    /// Opens on the table of a mode and difficulty, ordered the way the
+    /// mode ranks its runs.
+    pub fn new(play_mode: PlayMode, difficulty: Difficulty) -> ScoreViewer {
+        ScoreViewer {
+            play_mode: Some(play_mode).filter(|mode| mode.keeps_scores()),
+            difficulty: Some(difficulty),
+            ranking: play_mode.ranking(),
+            page: 0,
+        }
+    }
+
+    /// Shows the next or previous mode, with every mode between the last
+    /// and the first.
+    pub fn change_mode(&mut self, forward: bool) {
+        let modes: Vec<Option<PlayMode>> = Some(None).into_iter()
+            .chain(PlayMode::all().iter().filter(|mode| mode.keeps_scores()).map(|&mode| Some(mode)))
+            .collect();
+        self.play_mode = step(&modes, self.play_mode, forward);
+        self.page = 0;
+    }
+
+    /// Shows the next or previous difficulty, with every difficulty
+    /// between the hardest and the easiest.
+    pub fn change_difficulty(&mut self, forward: bool) {
+        let difficulties: Vec<Option<Difficulty>> = Some(None).into_iter()
+            .chain(Difficulty::all().iter().map(|&difficulty| Some(difficulty)))
+            .collect();
+        self.difficulty = step(&difficulties, self.difficulty, forward);
+        self.page = 0;
+    }
+
+    /// Switches between ordering by score and by time.
+    pub fn change_ranking(&mut self) {
+        self.ranking = match self.ranking {
+            Ranking::Score => Ranking::Survival,
+            Ranking::Survival => Ranking::Score,
+        };
+        self.page = 0;
+    }
+
+    /// Turns to the next or previous page, stopping at the first and last.
+    pub fn turn_page(&mut self, scores: &Scores, forward: bool) {
+        let last = self.pages(scores) - 1;
+        self.page = match forward {
+            true => (self.page + 1).min(last),
+            false => self.page.saturating_sub(1),
+        };
+    }
+
+    /// Number of pages in the table, at least one even if it is empty.
+    pub fn pages(&self, scores: &Scores) -> usize {
+        let count = scores.table(self.play_mode, self.difficulty, self.ranking).len();
+        count.div_ceil(PAGE_SIZE).max(1)
+    }
+
+    /// The scores on the page showing, along with their place in the table.
+    pub fn entries<'a>(&self, scores: &'a Scores) -> Vec<(usize, &'a ScoreRecord)> {
+        scores.table(self.play_mode, self.difficulty, self.ranking)
+            .into_iter()
+            .enumerate()
+            .skip(self.page * PAGE_SIZE)
+            .take(PAGE_SIZE)
+            .map(|(i, record)| (i + 1, record))
+            .collect()
+    }
+
+    /// Which table is showing, e.g. "CLASSIC / ALL DIFFICULTIES / BY TIME".
+    pub fn heading(&self) -> String {
+        format!(
+            "{} / {} / BY {}",
+            self.play_mode.map_or("ALL MODES", |mode| mode.name()),
+            self.difficulty.map_or("ALL DIFFICULTIES", |difficulty| difficulty.name()),
+            match self.ranking {
+                Ranking::Score => "SCORE",
+                Ranking::Survival => "TIME",
+            }
+        )
+    }
+}
+
+/// The choice after or before `current`, wrapping around.
+fn step<T: PartialEq + Copy>(choices: &[T], current: T, forward: bool) -> T {
+    let i = choices.iter().position(|&c| c == current).unwrap_or(0);
+    match forward {
+        true => choices[(i + 1) % choices.len()],
+        false => choices[(i + choices.len() - 1) % choices.len()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::scores::Origin;
+
+    fn record(score: i32, play_mode: PlayMode, difficulty: Difficulty) -> ScoreRecord {
+        ScoreRecord {
+            score,
+            name: "ferris".to_string(),
+            time_ms: 1_000 * (100 - score as u64),
+            date: 0,
+            play_mode,
+            difficulty,
+            max_gun_level: 1,
+            kills: 0,
+            max_combo: 0,
+            seed: 0,
+            replay: String::new(),
+            origin: Origin::Local,
+            version: String::new(),
+        }
+    }
+
+    #[test]
+    fn filters_sorts_and_pages() {
+        let mut scores = Scores::new();
+        for score in 0..20 {
+            scores.add(record(score, PlayMode::Classic, Difficulty::Normal));
+        }
+        scores.add(record(50, PlayMode::Endless, Difficulty::Hard));
+
+        let mut viewer = ScoreViewer::new(PlayMode::Classic, Difficulty::Normal);
+        assert_eq!(viewer.pages(&scores), 2);
+        assert_eq!(viewer.entries(&scores)[0].1.score, 19);
+        viewer.turn_page(&scores, true);
+        viewer.turn_page(&scores, true);
+        assert_eq!(viewer.entries(&scores).len(), 20 - PAGE_SIZE);
+        assert_eq!(viewer.entries(&scores)[0].0, PAGE_SIZE + 1);
+
+        // Longest runs first, back on the first page
+        viewer.change_ranking();
+        assert_eq!(viewer.page, 0);
+        assert_eq!(viewer.entries(&scores)[0].1.score, 0);
+
+        // Every mode and difficulty
+        viewer.change_mode(false);
+        assert_eq!(viewer.play_mode, None);
+        viewer.change_difficulty(false);
+        viewer.change_difficulty(false);
+        assert_eq!(viewer.difficulty, None);
+        assert_eq!(viewer.heading(), "ALL MODES / ALL DIFFICULTIES / BY TIME");
+        viewer.change_ranking();
+        assert_eq!(viewer.entries(&scores)[0].1.play_mode, PlayMode::Endless);
+    }
+
+    #[test]
+    fn practice_has_no_table() {
+        let mut viewer = ScoreViewer::new(PlayMode::Practice, Difficulty::Easy);
+        assert_eq!(viewer.play_mode, None);
+        for _ in 0..PlayMode::all().len() {
+            viewer.change_mode(true);
+            assert_ne!(viewer.play_mode, Some(PlayMode::Practice));
+        }
+        assert_eq!(viewer.play_mode, None);
+    }
+}
@@ -5,83 +5,306 @@
 // This program is licensed under the "MIT License". Please see the file
 // LICENSE in the source distribution of this software for license terms.
 
-//use std::fs::File;
-use std::fs::OpenOptions;
+extern crate serde_json;
+
+use game::difficulty::Difficulty;
+use game::play_mode::PlayMode;
+use std;
+use std::fs::File;
 use std::io::prelude::*;
 
-const MAX_SCORES: usize = 10;
+/// File the high scores of every mode and difficulty are kept in.
+pub const SCORES_FILE: &str = "scores.json";
+/// File older versions kept their high scores in, which were all classic
+/// runs on normal.
+pub const LEGACY_SCORES_FILE: &str = "scores.txt";
+/// Scores kept for each mode and difficulty
+const MAX_SCORES: usize = 50;
+
+/// How a score table is ordered.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Ranking {
+	/// Highest score first.
+	Score,
+	/// Longest survival time first, with score breaking ties.
+	Survival,
+}
+
+/// Where a score came from. Runs played on this version are all recorded,
+/// so only imported scores may go without a replay.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Origin {
+	/// Played on this machine.
+	Local,
+	/// Played online with someone else.
+	Online,
+	/// Brought over from the score file of an older version, which kept
+	/// no replays.
+	Imported,
+}
+
+/// A finished run on the high-score tables.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoreRecord {
+	pub score: i32,
+	pub name: String,
+	/// How long the run lasted (in ms)
+	pub time_ms: u64,
+	/// When the run ended, in seconds since the Unix epoch, or 0 for scores
+	/// from before dates were kept
+	pub date: u64,
+	pub play_mode: PlayMode,
+	pub difficulty: Difficulty,
+	/// Highest level any player's gun reached
+	pub max_gun_level: u32,
+	/// Enemies and bosses killed
+	pub kills: u32,
+	pub max_combo: u32,
+	/// Seed the run was started with
+	pub seed: u32,
+	/// File the replay of the run is saved in, or empty if it has none
+	pub replay: String,
+	pub origin: Origin,
+	/// Version of the game the run was played on, or empty for scores from
+	/// before versions were kept
+	pub version: String,
+}
+
+impl ScoreRecord {
+	/// The length of the run as "MM:SS".
+	pub fn time(&self) -> String {
+		let seconds = self.time_ms / 1000;
+		format!("{:02}:{:02}", seconds / 60, seconds % 60)
+	}
+
+	/// The day the run ended as "YYYY-MM-DD", or "-" if it isn't known.
+	pub fn day(&self) -> String {
+		if self.date == 0 {
+			return "-".to_string();
+		}
+		// Count the days from 1 March 0000, so leap days fall at the end
+		// of each year
+		let days = self.date / 86_400 + 719_468;
+		let era = days / 146_097;
+		let day_of_era = days % 146_097;
+		let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+		let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+		let shifted_month = (5 * day_of_year + 2) / 153;
+		let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+		let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+		let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+		format!("{:04}-{:02}-{:02}", year, month, day)
+	}
+}
 
-/// Loads and saves scores in an external file.
+/// Loads and saves the high scores of every mode and difficulty.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Scores {
-	scores: Vec<(i32, String, String)>,
+	records: Vec<ScoreRecord>,
 }
 
 impl Scores {
-	/// Create a new scores object.
-    pub fn new(file: &str) -> Scores {        
-        let mut f = match OpenOptions::new()
-			.read(true)
-			.write(true)
-			.create(true)
-			.open(file) {
-			Ok(f) => f,
-			Err(e) => panic!(e),
-		};
-
-		let mut scores = Vec::new();
-
-		let mut file_contents = String::new();
-		match f.read_to_string(&mut file_contents) {
-			Ok(_o) => (),
-			Err(e) => panic!(e),
-		}
-		let lines: Vec<&str> = file_contents.split("\n").collect();
-		for i in 0 .. lines.len()-1 {
-			let values: Vec<&str> = lines[i].split("|").collect();
-			println!("length = {}, {:?}", values.len(), values);
-		
-			scores.push( (
-				values[0].parse::<i32>().unwrap(),
-				values[1].to_string(),
-				values[2].to_string()
-			) );
+	/// Create an empty set of high scores.
+	pub fn new() -> Scores {
+		Scores::default()
+	}
+
+	/// Reads high scores saved with `save`.
+	pub fn load(file: &str) -> Result<Scores, String> {
+		let mut json = String::new();
+		File::open(file)
+			.and_then(|mut f| f.read_to_string(&mut json))
+			.map_err(|e| e.to_string())?;
+		serde_json::from_str(&json).map_err(|e| e.to_string())
+	}
+
+	/// Saves scores to file, replacing what was there.
+	pub fn save(&self, file: &str) -> std::io::Result<()> {
+		let json = serde_json::to_string_pretty(self).expect("Could not serialize the scores");
+		File::create(file)?.write_all(json.as_bytes())
+	}
+
+	/// Adds the scores of a file from an older version, kept as
+	/// "score|name|MM:SS" lines, to the classic table on normal.
+	pub fn import_legacy(&mut self, file: &str) -> Result<(), String> {
+		let mut contents = String::new();
+		File::open(file)
+			.and_then(|mut f| f.read_to_string(&mut contents))
+			.map_err(|e| e.to_string())?;
+		for line in contents.lines().filter(|line| !line.is_empty()) {
+			let values: Vec<&str> = line.split('|').collect();
+			let score = match (values[0].parse(), values.len()) {
+				(Ok(score), 3) => score,
+				_ => return Err(format!("bad score in {}: {}", file, line)),
+			};
+			self.add(ScoreRecord {
+				score,
+				name: values[1].to_string(),
+				time_ms: seconds(values[2]) * 1000,
+				date: 0,
+				play_mode: PlayMode::Classic,
+				difficulty: Difficulty::Normal,
+				max_gun_level: 0,
+				kills: 0,
+				max_combo: 0,
+				seed: 0,
+				replay: String::new(),
+				origin: Origin::Imported,
+				version: String::new(),
+			});
 		}
-		
-		Scores {
-			scores: scores,
+		Ok(())
+	}
+
+	/// Adds a score. Returns the scores that no longer make the table of
+	/// its mode and difficulty, which may be the new one.
+	pub fn add(&mut self, record: ScoreRecord) -> Vec<ScoreRecord> {
+		let (play_mode, difficulty) = (record.play_mode, record.difficulty);
+		self.records.push(record);
+		let (mut table, others): (Vec<ScoreRecord>, Vec<ScoreRecord>) = self.records.drain(..)
+			.partition(|r| r.play_mode == play_mode && r.difficulty == difficulty);
+		table.sort_by(|a, b| compare(a, b, play_mode.ranking()));
+		let dropped = if table.len() > MAX_SCORES { table.split_off(MAX_SCORES) } else { vec![] };
+		self.records = others;
+		self.records.extend(table);
+		dropped
+	}
+
+	/// The scores of a mode and difficulty, or of all of them when `None`,
+	/// best first. Ties go to whoever got there first.
+	pub fn table(&self, play_mode: Option<PlayMode>, difficulty: Option<Difficulty>, ranking: Ranking) -> Vec<&ScoreRecord> {
+		let mut table: Vec<&ScoreRecord> = self.records.iter()
+			.filter(|r| play_mode.is_none_or(|m| r.play_mode == m))
+			.filter(|r| difficulty.is_none_or(|d| r.difficulty == d))
+			.collect();
+		table.sort_by(|a, b| compare(a, b, ranking));
+		table
+	}
+}
+
+/// Orders two scores, the better one first.
+fn compare(a: &ScoreRecord, b: &ScoreRecord, ranking: Ranking) -> std::cmp::Ordering {
+	match ranking {
+		Ranking::Score => b.score.cmp(&a.score),
+		Ranking::Survival => b.time_ms.cmp(&a.time_ms).then(b.score.cmp(&a.score)),
+	}
+}
+
+/// Converts a "MM:SS" time into seconds.
+fn seconds(time: &str) -> u64 {
+	let parts: Vec<u64> = time.split(":").map(|p| p.parse::<u64>().unwrap_or(0)).collect();
+	parts.iter().fold(0, |total, part| total * 60 + part)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use proptest::prelude::*;
+
+	fn record(score: i32, time_ms: u64) -> ScoreRecord {
+		ScoreRecord {
+			score,
+			name: "ferris".to_string(),
+			time_ms,
+			date: 0,
+			play_mode: PlayMode::Classic,
+			difficulty: Difficulty::Normal,
+			max_gun_level: 1,
+			kills: 0,
+			max_combo: 0,
+			seed: 0,
+			replay: String::new(),
+			origin: Origin::Local,
+			version: String::new(),
 		}
-    }
-
-	/// Adds a score to the list
-    pub fn add_score(&mut self, number: i32, name: String, time: String) {
-        self.scores.push( (number, name, time) );
-		self.scores.sort_by(|a,b| b.0.cmp(&a.0));
-		while self.scores.len() > MAX_SCORES {
-			self.scores.remove(MAX_SCORES);
+	}
+
+	#[test]
+	fn reads_minutes_and_seconds() {
+		assert_eq!(seconds("00:00"), 0);
+		assert_eq!(seconds("03:07"), 187);
+		assert_eq!(seconds("not a time"), 0);
+	}
+
+	#[test]
+	fn shows_times_and_dates() {
+		let mut score = record(0, 187_999);
+		assert_eq!(score.time(), "03:07");
+		assert_eq!(score.day(), "-");
+		score.date = 951_782_400;
+		assert_eq!(score.day(), "2000-02-29");
+		score.date = 1_792_281_600 + 86_399;
+		assert_eq!(score.day(), "2026-10-18");
+	}
+
+	#[test]
+	fn old_score_files_are_brought_over() {
+		let file = std::env::temp_dir().join("ferris-legacy-scores.txt");
+		let file = file.to_str().unwrap().to_string();
+		File::create(&file).unwrap().write_all(b"1200|ferris|03:07\n800|corro|01:05\n").unwrap();
+		let mut scores = Scores::new();
+		scores.import_legacy(&file).unwrap();
+
+		let table = scores.table(Some(PlayMode::Classic), Some(Difficulty::Normal), Ranking::Score);
+		assert_eq!(table.len(), 2);
+		assert_eq!((table[0].score, table[0].name.as_str(), table[0].time_ms), (1200, "ferris", 187_000));
+		assert_eq!((table[1].score, table[1].name.as_str(), table[1].time()), (800, "corro", "01:05".to_string()));
+		assert!(table.iter().all(|r| r.origin == Origin::Imported && r.replay.is_empty()));
+
+		File::create(&file).unwrap().write_all(b"lots|ferris|03:07\n").unwrap();
+		assert!(Scores::new().import_legacy(&file).is_err());
+		let _ = std::fs::remove_file(&file);
+	}
+
+	#[test]
+	fn tables_are_kept_per_mode_and_difficulty() {
+		let mut scores = Scores::new();
+		let mut hard = record(50, 0);
+		hard.difficulty = Difficulty::Hard;
+		scores.add(hard.clone());
+		for score in 0..MAX_SCORES as i32 {
+			assert!(scores.add(record(100 + score, 0)).is_empty());
 		}
+
+		// A full table drops its worst score, and leaves the others alone
+		assert_eq!(scores.add(record(99, 0)), vec![record(99, 0)]);
+		assert_eq!(scores.add(record(500, 0)), vec![record(100, 0)]);
+		assert_eq!(scores.table(None, Some(Difficulty::Hard), Ranking::Score), vec![&hard]);
+		assert_eq!(scores.table(Some(PlayMode::Classic), None, Ranking::Score).len(), MAX_SCORES + 1);
+		assert!(scores.table(Some(PlayMode::Endless), None, Ranking::Score).is_empty());
+
+		let json = serde_json::to_string(&scores).unwrap();
+		let loaded: Scores = serde_json::from_str(&json).unwrap();
+		assert_eq!(loaded.records, scores.records);
 	}
 
-	/// Returns a vector of tuples with score, name and time
-    pub fn get_scores(&self) -> &Vec<(i32, String, String)> {
-        &self.scores
-    }
-	
-	/// Saves scores to file
-	pub fn save(&self, file: &str) {
-        let mut f = match OpenOptions::new()
-			.write(true)
-			.create(true)
-			.open(file) {
-			Ok(f) => f,
-			Err(e) => panic!(e),
-		};
-
-		for (score, name, time) in &self.scores {
-			let combined = format!("{}|{}|{}\n", score, name, time);
-			match f.write(combined.as_bytes()) {
-				Ok(_o) => (),
-				Err(e) => panic!(e),
+	proptest! {
+		#[test]
+		fn keeps_the_best_scores_in_order(entries in prop::collection::vec((-1_000i32..100_000, 0u64..3_600_000), 0..80)) {
+			let mut scores = Scores::new();
+			for &(score, time_ms) in entries.iter() {
+				scores.add(record(score, time_ms));
+			}
+
+			let kept: Vec<i32> = scores.table(None, None, Ranking::Score).iter().map(|r| r.score).collect();
+			let mut best: Vec<i32> = entries.iter().map(|e| e.0).collect();
+			best.sort_by(|a, b| b.cmp(a));
+			best.truncate(MAX_SCORES);
+			prop_assert_eq!(kept, best);
+		}
+
+		#[test]
+		fn survival_tables_rank_time_then_score(entries in prop::collection::vec((0i32..1_000, 0u64..600_000), 0..30)) {
+			let mut scores = Scores::new();
+			for &(score, time_ms) in entries.iter() {
+				scores.add(record(score, time_ms));
+			}
+
+			let table = scores.table(None, None, Ranking::Survival);
+			prop_assert_eq!(table.len(), entries.len());
+			for pair in table.windows(2) {
+				prop_assert!((pair[0].time_ms, pair[0].score) >= (pair[1].time_ms, pair[1].score));
 			}
 		}
 	}
-}
\ No newline at end of file
+}
@@ -0,0 +1,286 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+//! The `ferris-sim` balance simulator. It plays seeded games without a
+//! window as fast as it can, and prints stats for every run as CSV or
+//! JSON so constants can be tuned with data.
+
+extern crate serde_json;
+
+use game::Input;
+use game::bot;
+use game::difficulty::Difficulty;
+use game::netplay::FRAME_MS;
+use game::play_mode::PlayMode;
+use game::stats::RunStats;
+use game::world::World;
+use std;
+use std::collections::{BTreeMap, BTreeSet};
+
+const USAGE: &str = "usage: ferris-sim [--runs N] [--seed N] [--mode MODE] [--difficulty DIFFICULTY] \
+[--players 1|2] [--pilot bot|idle|sweep] [--minutes N] [--format csv|json]";
+
+/// Who plays the simulated games.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Pilot {
+    /// The autoplay bot.
+    Bot,
+    /// Stands still at the start and holds down shoot.
+    Idle,
+    /// Holds down shoot and sweeps from one side of the screen to the other.
+    Sweep,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Format {
+    Csv,
+    Json,
+}
+
+/// Settings for a batch of runs, read from the command line.
+#[derive(Debug)]
+struct Options {
+    runs: u32,
+    /// Seed of the first run. Each run after it uses the next seed.
+    seed: u32,
+    mode: PlayMode,
+    difficulty: Difficulty,
+    players: usize,
+    pilot: Pilot,
+    /// Runs still going after this long are stopped (in ms).
+    max_ms: u64,
+    format: Format,
+}
+
+impl Options {
+    fn from_args<I: Iterator<Item = String>>(args: I) -> Result<Options, String> {
+        let mut options = Options {
+            runs: 100,
+            seed: 0,
+            mode: PlayMode::Classic,
+            difficulty: Difficulty::Normal,
+            players: 1,
+            pilot: Pilot::Bot,
+            max_ms: 20 * 60 * 1000,
+            format: Format::Csv,
+        };
+
+        let mut args = args.skip(1);
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or(format!("{} needs a value", flag))?;
+            match flag.as_str() {
+                "--runs" => options.runs = parse(&flag, &value)?,
+                "--seed" => options.seed = parse(&flag, &value)?,
+                "--mode" => {
+                    options.mode = *PlayMode::all().iter()
+                        .find(|mode| matches_name(mode.name(), &value))
+                        .ok_or(format!("unknown mode {}", value))?
+                }
+                "--difficulty" => {
+                    options.difficulty = *Difficulty::all().iter()
+                        .find(|difficulty| matches_name(difficulty.name(), &value))
+                        .ok_or(format!("unknown difficulty {}", value))?
+                }
+                "--players" => {
+                    options.players = parse(&flag, &value)?;
+                    if options.players < 1 || options.players > 2 {
+                        return Err("--players must be 1 or 2".to_string());
+                    }
+                }
+                "--pilot" => {
+                    options.pilot = match value.as_str() {
+                        "bot" => Pilot::Bot,
+                        "idle" => Pilot::Idle,
+                        "sweep" => Pilot::Sweep,
+                        _ => return Err(format!("unknown pilot {}", value)),
+                    }
+                }
+                "--minutes" => options.max_ms = parse::<u64>(&flag, &value)? * 60 * 1000,
+                "--format" => {
+                    options.format = match value.as_str() {
+                        "csv" => Format::Csv,
+                        "json" => Format::Json,
+                        _ => return Err(format!("unknown format {}", value)),
+                    }
+                }
+                _ => return Err(format!("unknown option {}", flag)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn parse<F: std::str::FromStr>(flag: &str, value: &str) -> Result<F, String> {
+    value.parse().map_err(|_| format!("bad value for {}: {}", flag, value))
+}
+
+/// Compares a name from the title screen with one typed on the command
+/// line, e.g. "BOSS RUSH" and "boss-rush".
+fn matches_name(name: &str, arg: &str) -> bool {
+    name.replace(' ', "-").eq_ignore_ascii_case(arg)
+}
+
+/// How one simulated game went.
+#[derive(Debug, Serialize)]
+struct Report {
+    seed: u32,
+    mode: &'static str,
+    difficulty: &'static str,
+    players: usize,
+    /// How long the run lasted (in ms)
+    survived_ms: u64,
+    score: i32,
+    won: bool,
+    #[serde(flatten)]
+    stats: RunStats,
+}
+
+/// Plays one game to the end, or until it runs out of time.
+fn play(options: &Options, seed: u32) -> Report {
+    let mut world = World::new(1024, 768);
+    world.play_mode = options.mode;
+    world.difficulty = options.difficulty;
+    world.player_count = options.players;
+    world.seed(seed);
+    world.reset();
+
+    let start_ms = world.play_mode.start_ms(&world.practice);
+    let mut elapsed_ms = start_ms;
+    while elapsed_ms - start_ms < options.max_ms && world.player_alive() && !world.won(elapsed_ms) {
+        let inputs: Vec<Input> = (0..world.players.len())
+            .map(|slot| pilot_input(options.pilot, &world, slot, elapsed_ms - start_ms))
+            .collect();
+        elapsed_ms += FRAME_MS;
+        world.update(&inputs, elapsed_ms, FRAME_MS);
+        world.events.clear();
+    }
+
+    Report {
+        seed,
+        mode: options.mode.name(),
+        difficulty: options.difficulty.name(),
+        players: options.players,
+        survived_ms: elapsed_ms - start_ms,
+        score: world.score,
+        won: world.won(elapsed_ms),
+        stats: world.stats,
+    }
+}
+
+fn pilot_input(pilot: Pilot, world: &World, slot: usize, time_ms: u64) -> Input {
+    match pilot {
+        Pilot::Bot => bot::think(world, slot),
+        Pilot::Idle => Input { shoot: true, ..Input::default() },
+        Pilot::Sweep => {
+            // About three seconds to cross the screen each way
+            let right = (time_ms / 3_000).is_multiple_of(2);
+            Input { shoot: true, left: !right, right, ..Input::default() }
+        }
+    }
+}
+
+/// The damage, kill and pickup tallies of a run, by CSV column.
+fn tally_columns(stats: &RunStats) -> BTreeMap<String, i64> {
+    let mut columns = BTreeMap::new();
+    for (kind, &damage) in stats.damage_taken.iter() {
+        columns.insert(format!("damage_{:?}", kind), damage as i64);
+    }
+    for (kind, &kills) in stats.kills.iter() {
+        columns.insert(format!("kills_{:?}", kind), kills as i64);
+    }
+    for (name, &count) in stats.pickups.iter() {
+        columns.insert(format!("pickups_{}", name), count as i64);
+    }
+    columns
+}
+
+/// One row per run. The tally columns are made from everything that
+/// turned up in any run, so every row lines up.
+fn to_csv(reports: &[Report]) -> String {
+    let tallies: Vec<BTreeMap<String, i64>> = reports.iter().map(|r| tally_columns(&r.stats)).collect();
+    let names: BTreeSet<&String> = tallies.iter().flat_map(|tally| tally.keys()).collect();
+
+    let mut header = ["seed", "mode", "difficulty", "players", "survived_ms", "score", "won"].join(",");
+    for name in names.iter() {
+        header += &format!(",{}", name);
+    }
+    let mut csv = header + "\n";
+    for (report, tally) in reports.iter().zip(tallies.iter()) {
+        csv += &format!(
+            "{},{},{},{},{},{},{}",
+            report.seed, report.mode, report.difficulty, report.players, report.survived_ms, report.score, report.won
+        );
+        for name in names.iter() {
+            csv += &format!(",{}", tally.get(*name).cloned().unwrap_or(0));
+        }
+        csv += "\n";
+    }
+    csv
+}
+
+/// Entry point of the `ferris-sim` binary.
+pub fn main() {
+    let options = match Options::from_args(std::env::args()) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(1);
+        }
+    };
+
+    let reports: Vec<Report> = (0..options.runs)
+        .map(|run| play(&options, options.seed.wrapping_add(run)))
+        .collect();
+    match options.format {
+        Format::Csv => print!("{}", to_csv(&reports)),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&reports).expect("Could not write the reports")),
+    }
+
+    // A summary for a quick look, kept off stdout so it stays parseable
+    let runs = reports.len().max(1) as f64;
+    eprintln!(
+        "{} runs: {:.1}s average survival, {:.0} average score, {} won",
+        reports.len(),
+        reports.iter().map(|r| r.survived_ms as f64 / 1000.0).sum::<f64>() / runs,
+        reports.iter().map(|r| r.score as f64).sum::<f64>() / runs,
+        reports.iter().filter(|r| r.won).count(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> std::vec::IntoIter<String> {
+        let args: Vec<String> = line.split_whitespace().map(String::from).collect();
+        args.into_iter()
+    }
+
+    #[test]
+    fn reads_options_from_the_command_line() {
+        let options = Options::from_args(args("ferris-sim --runs 3 --mode boss-rush --difficulty hard --pilot sweep")).unwrap();
+        assert_eq!(options.runs, 3);
+        assert_eq!(options.mode, PlayMode::BossRush);
+        assert_eq!(options.difficulty, Difficulty::Hard);
+        assert_eq!(options.pilot, Pilot::Sweep);
+        assert!(Options::from_args(args("ferris-sim --mode chess")).is_err());
+        assert!(Options::from_args(args("ferris-sim --runs")).is_err());
+    }
+
+    #[test]
+    fn runs_are_repeatable_and_line_up_in_csv() {
+        let options = Options::from_args(args("ferris-sim --pilot idle --minutes 1")).unwrap();
+        let reports = vec![play(&options, 1), play(&options, 2)];
+        assert_eq!(play(&options, 1).stats, reports[0].stats);
+
+        let csv = to_csv(&reports);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        let columns = lines[0].split(',').count();
+        assert!(lines.iter().all(|line| line.split(',').count() == columns));
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+extern crate serde_json;
+
+use game::replay::Replay;
+use game::world::World;
+use std;
+use std::fs::File;
+use std::io::prelude::*;
+
+/// Bumped whenever the saved world changes shape, so old saves are turned
+/// down instead of loading wrong.
+const SNAPSHOT_VERSION: u32 = 5;
+
+/// Everything needed to pick a run back up exactly where it was: every
+/// entity and its components, the spawner, the players and the run clock,
+/// along with whether the debug keys were used and the run recorded so far.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    version: u32,
+    /// Time into the run (in ms)
+    pub elapsed_ms: u64,
+    pub world: World,
+    /// Whether the debug keys were used on the run
+    pub cheated: bool,
+    /// Updates recorded up to the snapshot, if the run is being recorded
+    pub replay: Option<Replay>,
+}
+
+impl Snapshot {
+    /// Copies the state of a run.
+    pub fn capture(world: &World, elapsed_ms: u64, cheated: bool, replay: Option<&Replay>) -> Snapshot {
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            elapsed_ms,
+            world: world.clone(),
+            cheated,
+            replay: replay.cloned(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Could not serialize the world")
+    }
+
+    pub fn from_json(json: &str) -> Result<Snapshot, String> {
+        let snapshot: Snapshot = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(format!("save is from version {}, expected {}", snapshot.version, SNAPSHOT_VERSION));
+        }
+        Ok(snapshot)
+    }
+
+    /// Writes the snapshot to a file, replacing what was there.
+    pub fn save(&self, file: &str) -> std::io::Result<()> {
+        File::create(file)?.write_all(self.to_json().as_bytes())
+    }
+
+    /// Reads a snapshot saved with `save`.
+    pub fn load(file: &str) -> Result<Snapshot, String> {
+        let mut json = String::new();
+        File::open(file)
+            .and_then(|mut f| f.read_to_string(&mut json))
+            .map_err(|e| e.to_string())?;
+        Snapshot::from_json(&json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::Input;
+    use game::netplay;
+
+    #[test]
+    fn restored_runs_play_on_exactly_the_same() {
+        let mut world = World::new(1024, 768);
+        world.seed(3);
+        world.reset();
+        let shoot = [Input { shoot: true, left: true, ..Input::default() }];
+        for frame in 0..300 {
+            world.update(&shoot, frame * 16, 16);
+        }
+
+        let snapshot = Snapshot::capture(&world, 300 * 16, false, None);
+        let mut restored = Snapshot::from_json(&snapshot.to_json()).unwrap().world;
+        assert_eq!(Snapshot::capture(&restored, 300 * 16, false, None).to_json(), snapshot.to_json());
+
+        for frame in 300..600 {
+            world.update(&shoot, frame * 16, 16);
+            restored.update(&shoot, frame * 16, 16);
+        }
+        assert_eq!(
+            Snapshot::capture(&restored, 0, false, None).to_json(),
+            Snapshot::capture(&world, 0, false, None).to_json()
+        );
+    }
+
+    #[test]
+    fn resumed_runs_keep_their_replay_and_cheats() {
+        let mut world = World::new(1024, 768);
+        world.seed(4);
+        world.reset();
+        let mut replay = Replay::start(&world);
+        let shoot = [Input { shoot: true, right: true, ..Input::default() }];
+        for frame in 1..300 {
+            replay.record(&shoot, frame * 16, 16);
+            world.update(&shoot, frame * 16, 16);
+        }
+
+        // Suspend and resume
+        let json = Snapshot::capture(&world, 299 * 16, true, Some(&replay)).to_json();
+        let resumed = Snapshot::from_json(&json).unwrap();
+        assert!(resumed.cheated);
+        let mut resumed_world = resumed.world;
+        let mut resumed_replay = resumed.replay.unwrap();
+        assert_eq!(resumed_replay.end_ms(), 299 * 16);
+
+        for frame in 300..600 {
+            resumed_replay.record(&shoot, frame * 16, 16);
+            resumed_world.update(&shoot, frame * 16, 16);
+            world.update(&shoot, frame * 16, 16);
+        }
+        // The replay covers the whole run, before and after the break
+        let outcome = resumed_replay.play().unwrap();
+        assert_eq!(outcome.checksum, netplay::checksum(&world));
+        assert_eq!(outcome.score, world.score);
+    }
+
+    #[test]
+    fn saves_from_other_versions_are_turned_down() {
+        let json = Snapshot::capture(&World::new(1024, 768), 0, false, None).to_json();
+        let old = json.replacen(&format!("\"version\":{}", SNAPSHOT_VERSION), "\"version\":0", 1);
+        assert!(Snapshot::from_json(&old).is_err());
+    }
+}
@@ -0,0 +1,72 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+use game::entity::EntityType;
+use game::events::GameEvent;
+use std::collections::BTreeMap;
+
+/// A tally of what happened during a run, for balancing the game.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunStats {
+    /// Hit points lost, by what dealt the damage. Hits in practice count
+    /// even though they don't cost a life.
+    pub damage_taken: BTreeMap<EntityType, i32>,
+    /// Enemies killed, by type.
+    pub kills: BTreeMap<EntityType, u32>,
+    /// Items collected, by name.
+    pub pickups: BTreeMap<String, u32>,
+}
+
+impl RunStats {
+    pub fn new() -> RunStats {
+        RunStats::default()
+    }
+
+    /// Adds an event to the tallies.
+    pub fn record(&mut self, event: &GameEvent) {
+        match *event {
+            GameEvent::PlayerHit { source, damage, absorbed: false, .. } => {
+                *self.damage_taken.entry(source).or_insert(0) += damage;
+            }
+            GameEvent::EnemyKilled { kind, .. } => *self.kills.entry(kind).or_insert(0) += 1,
+            GameEvent::PickupCollected { kind, .. } => *self.pickups.entry(format!("{:?}", kind)).or_insert(0) += 1,
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::Input;
+    use game::components::Transform;
+    use game::world::World;
+
+    #[test]
+    fn tallies_hits_kills_and_pickups() {
+        let mut world = World::new(1024, 768);
+        world.reset();
+        world.spawner.cooldowns.clear();
+        let crab = *world.transforms.get(world.players[0].entity.unwrap()).unwrap();
+
+        // A bullet right on the crab, and an enemy with no hit points left
+        let bullet = world.spawner.spawn_enemy_bullet(crab.x + 55.0, crab.y + 40.0, 0.0);
+        world.insert(bullet);
+        let mut enemy = world.spawner.spawn_enemy(0.0, "SEGFAULT", EntityType::Enemy);
+        enemy.transform = Transform::at(100.0, 100.0);
+        enemy.health.as_mut().unwrap().hp = 0;
+        world.insert(enemy);
+        let mut bomb = world.spawner.spawn_powerbomb();
+        bomb.transform = Transform::at(crab.x + 40.0, crab.y + 30.0);
+        world.insert(bomb);
+
+        world.update(&[Input::default()], 16, 16);
+        assert_eq!(world.stats.damage_taken[&EntityType::EnemyBullet], 1);
+        assert_eq!(world.stats.kills[&EntityType::Enemy], 1);
+        assert_eq!(world.stats.pickups["Powerbomb"], 1);
+    }
+}
@@ -0,0 +1,220 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+use std;
+
+const MAX_SHIELD_CHARGES: u32 = 3;
+const MAX_SCORE_MULTIPLIER: u32 = 4;
+const RAPID_FIRE_DURATION: i64 = 10_000;
+const SPEED_BOOST_DURATION: i64 = 12_000;
+const MAGNET_DURATION: i64 = 15_000;
+const SCORE_MULTIPLIER_DURATION: i64 = 10_000;
+
+/// The different effects a power-up can put on the player.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Copy, Clone, Serialize, Deserialize)]
+pub enum StatusKind {
+    /// Absorbs one hit per charge.
+    Shield,
+    /// Halves the cooldown of the player's gun.
+    RapidFire,
+    /// Makes the player move faster.
+    SpeedBoost,
+    /// Pulls nearby pickups towards the player.
+    Magnet,
+    /// Multiplies every score the player earns.
+    ScoreMultiplier,
+}
+
+impl StatusKind {
+    /// Every effect, in the order they are shown on the HUD.
+    pub fn all() -> [StatusKind; 5] {
+        [
+            StatusKind::Shield,
+            StatusKind::RapidFire,
+            StatusKind::SpeedBoost,
+            StatusKind::Magnet,
+            StatusKind::ScoreMultiplier,
+        ]
+    }
+
+    /// Name shown on the HUD.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            StatusKind::Shield => "FIREWALL",
+            StatusKind::RapidFire => "RAPID",
+            StatusKind::SpeedBoost => "SPEED",
+            StatusKind::Magnet => "MAGNET",
+            StatusKind::ScoreMultiplier => "SCORE",
+        }
+    }
+
+    /// How long one pickup of this effect lasts (in ms), or `None` for
+    /// effects that last until they are used up.
+    pub fn duration(&self) -> Option<i64> {
+        match *self {
+            StatusKind::Shield => None,
+            StatusKind::RapidFire => Some(RAPID_FIRE_DURATION),
+            StatusKind::SpeedBoost => Some(SPEED_BOOST_DURATION),
+            StatusKind::Magnet => Some(MAGNET_DURATION),
+            StatusKind::ScoreMultiplier => Some(SCORE_MULTIPLIER_DURATION),
+        }
+    }
+}
+
+/// One active effect on the player.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Status {
+    /// Time left (in ms), or `None` if the effect doesn't expire.
+    pub remaining_ms: Option<i64>,
+    /// Shield charges, or the multiplier for the score effect.
+    pub stacks: u32,
+}
+
+/// The effects currently on the player. Picking up an effect that is
+/// already active stacks it:
+/// - Shield: adds a charge, up to 3.
+/// - Score multiplier: raises the multiplier by one, up to x4, and
+///   restarts the timer.
+/// - Anything else: adds the full duration to the time left, capped at
+///   twice the duration of one pickup.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StatusEffects {
+    /// Kept sorted so saved runs always come out the same.
+    pub active: std::collections::BTreeMap<StatusKind, Status>,
+}
+
+impl StatusEffects {
+    /// No effects at all.
+    pub fn new() -> StatusEffects {
+        StatusEffects {
+            active: std::collections::BTreeMap::new(),
+        }
+    }
+
+    /// Removes every effect.
+    pub fn clear(&mut self) {
+        self.active.clear();
+    }
+
+    /// Returns true if the effect is active.
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.active.contains_key(&kind)
+    }
+
+    /// Puts an effect on the player, stacking it with any already active.
+    pub fn add(&mut self, kind: StatusKind) {
+        let duration = kind.duration();
+        let status = self.active.entry(kind).or_insert(Status {
+            remaining_ms: Some(0),
+            stacks: 0,
+        });
+        match kind {
+            StatusKind::Shield => {
+                status.remaining_ms = None;
+                status.stacks = std::cmp::min(status.stacks + 1, MAX_SHIELD_CHARGES);
+            }
+            StatusKind::ScoreMultiplier => {
+                // The multiplier starts at x2 on the first pickup
+                status.remaining_ms = duration;
+                status.stacks = (status.stacks + 1).clamp(2, MAX_SCORE_MULTIPLIER);
+            }
+            _ => {
+                let duration = duration.unwrap_or(0);
+                let remaining = status.remaining_ms.unwrap_or(0) + duration;
+                status.remaining_ms = Some(std::cmp::min(remaining, duration * 2));
+                status.stacks = 1;
+            }
+        }
+    }
+
+    /// Counts down every timed effect, removing those that run out.
+    pub fn update(&mut self, delta_ms: u64) {
+        for status in self.active.values_mut() {
+            if let Some(ref mut remaining) = status.remaining_ms {
+                *remaining -= delta_ms as i64;
+            }
+        }
+        self.active.retain(|_, status| match status.remaining_ms {
+            Some(remaining) => remaining > 0,
+            None => status.stacks > 0,
+        });
+    }
+
+    /// Uses up one shield charge. Returns false if there wasn't any.
+    pub fn absorb_hit(&mut self) -> bool {
+        let charges = match self.active.get_mut(&StatusKind::Shield) {
+            Some(shield) => {
+                shield.stacks -= 1;
+                shield.stacks
+            }
+            None => return false,
+        };
+        if charges == 0 {
+            self.active.remove(&StatusKind::Shield);
+        }
+        true
+    }
+
+    /// Shield charges left.
+    pub fn shield_charges(&self) -> u32 {
+        self.active.get(&StatusKind::Shield).map_or(0, |s| s.stacks)
+    }
+
+    /// What every score earned is multiplied by.
+    pub fn score_multiplier(&self) -> i32 {
+        self.active
+            .get(&StatusKind::ScoreMultiplier)
+            .map_or(1, |s| s.stacks as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shields_stack_charges() {
+        let mut status = StatusEffects::new();
+        for _ in 0..5 {
+            status.add(StatusKind::Shield);
+        }
+        assert_eq!(status.shield_charges(), MAX_SHIELD_CHARGES);
+        status.update(1_000_000);
+        assert!(status.absorb_hit());
+        assert!(status.absorb_hit());
+        assert!(status.absorb_hit());
+        assert!(!status.absorb_hit());
+        assert!(!status.has(StatusKind::Shield));
+    }
+
+    #[test]
+    fn timed_effects_extend_up_to_twice_their_duration() {
+        let mut status = StatusEffects::new();
+        status.add(StatusKind::RapidFire);
+        status.update(4_000);
+        status.add(StatusKind::RapidFire);
+        assert_eq!(status.active[&StatusKind::RapidFire].remaining_ms, Some(16_000));
+        status.add(StatusKind::RapidFire);
+        assert_eq!(status.active[&StatusKind::RapidFire].remaining_ms, Some(20_000));
+        status.update(20_000);
+        assert!(!status.has(StatusKind::RapidFire));
+    }
+
+    #[test]
+    fn score_multiplier_stacks_and_expires() {
+        let mut status = StatusEffects::new();
+        assert_eq!(status.score_multiplier(), 1);
+        status.add(StatusKind::ScoreMultiplier);
+        assert_eq!(status.score_multiplier(), 2);
+        for _ in 0..5 {
+            status.add(StatusKind::ScoreMultiplier);
+        }
+        assert_eq!(status.score_multiplier(), MAX_SCORE_MULTIPLIER as i32);
+        status.update(SCORE_MULTIPLIER_DURATION as u64);
+        assert_eq!(status.score_multiplier(), 1);
+    }
+}
@@ -0,0 +1,1274 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+//! The systems that make up the gameplay loop. Each one works on a few
+//! component storages of the `World` and is run in order by `World::update`.
+
+use game::Input;
+use game::adaptive::Sample;
+use game::status::StatusKind;
+use game::components::{FirePattern, Lifetime, Movement, PickupKind};
+use game::entity::{Entity, EntityType};
+use game::entity_store::EntityId;
+use game::events::GameEvent;
+use game::player::{REVIVE_DELAY, REVIVE_HP};
+use game::weapons::Arsenal;
+use game::world::World;
+use game::{BOMB_BOSS_DAMAGE, BOMB_INVULNERABILITY, GOD_MODE, MAX_BOMBS};
+use game::{GRAZE_CHARGE, GRAZE_FLASH, GRAZE_MARGIN, GRAZE_METER_MAX, GRAZE_POINTS};
+use ggez::graphics::Rect;
+use std;
+
+/// How quickly homing missiles turn towards their target, per second.
+const HOMING_TURN_RATE: f32 = 4.0;
+/// Speed of the player while the speed boost is active, relative to normal.
+pub const SPEED_BOOST_FACTOR: f32 = 1.5;
+/// How quickly knockback wears off, per second.
+const KNOCKBACK_DECAY: f32 = 8.0;
+/// Pickups closer than this to a player are pulled in by their magnet.
+const MAGNET_RADIUS: f32 = 350.0;
+/// How quickly the magnet pulls pickups in (in pixels per second).
+const MAGNET_SPEED: f32 = 500.0;
+
+/// Record of an entity removed by the `lifetime_system`.
+pub struct Death {
+    pub id: EntityId,
+    pub entity_type: EntityType,
+    pub x: f32,
+    pub y: f32,
+    /// True if the entity ran out of hit points, rather than expiring
+    /// or leaving the screen.
+    pub killed: bool,
+}
+
+/// Moves every entity with a velocity, steering each player's crab by
+/// their input.
+pub fn movement_system(world: &mut World, inputs: &[Input], delta_ms: u64) {
+    let delta_time = delta_ms as f32 / 1000_f32;
+    let targets = enemy_centers(world);
+    let magnets: Vec<(f32, f32)> = (0..world.players.len())
+        .filter(|&slot| world.players[slot].status.has(StatusKind::Magnet))
+        .filter_map(|slot| player_center(world, slot))
+        .collect();
+
+    for (id, velocity) in world.velocities.iter_mut() {
+        let transform = match world.transforms.get_mut(id) {
+            Some(transform) => transform,
+            None => continue,
+        };
+
+        velocity.timer += delta_ms;
+        transform.angle += velocity.spin * delta_ms as f32;
+
+        match velocity.movement {
+            Movement::Linear(x, y) => transform.translate(x * delta_time, y * delta_time),
+            Movement::Generated(pattern) => {
+                let (x, y) = pattern.velocity(velocity.timer, &mut world.rng, velocity.seed);
+                transform.translate(x * delta_time, y * delta_time);
+            }
+            Movement::Homing(vx, vy) => {
+                // Turn towards the closest enemy, keeping the same speed
+                let speed = (vx * vx + vy * vy).sqrt();
+                let (cx, cy) = (transform.x, transform.y);
+                let closest = targets.iter().min_by(|a, b| {
+                    let da = (a.0 - cx).powi(2) + (a.1 - cy).powi(2);
+                    let db = (b.0 - cx).powi(2) + (b.1 - cy).powi(2);
+                    da.partial_cmp(&db).unwrap_or(std::cmp::Ordering::Equal)
+                });
+                let (mut nvx, mut nvy) = (vx, vy);
+                if let Some(&(tx, ty)) = closest {
+                    let (dx, dy) = (tx - cx, ty - cy);
+                    let distance = (dx * dx + dy * dy).sqrt();
+                    if distance > 0.0 {
+                        let turn = (HOMING_TURN_RATE * delta_time).min(1.0);
+                        nvx += (dx / distance * speed - vx) * turn;
+                        nvy += (dy / distance * speed - vy) * turn;
+                        let new_speed = (nvx * nvx + nvy * nvy).sqrt();
+                        if new_speed > 0.0 {
+                            nvx *= speed / new_speed;
+                            nvy *= speed / new_speed;
+                        }
+                    }
+                }
+                velocity.movement = Movement::Homing(nvx, nvy);
+                transform.translate(nvx * delta_time, nvy * delta_time);
+            }
+            Movement::Controlled(speed) => {
+                let slot = match world.players.iter().position(|p| p.entity == Some(id)) {
+                    Some(slot) => slot,
+                    None => continue,
+                };
+                let player = &world.players[slot];
+                let input = inputs.get(slot).cloned().unwrap_or_default();
+                let mut vel = speed * delta_time;
+                if player.status.has(StatusKind::SpeedBoost) {
+                    vel *= SPEED_BOOST_FACTOR;
+                }
+
+                // Get pushed back after a hit
+                transform.translate(player.knockback.0 * delta_time, player.knockback.1 * delta_time);
+
+                match (input.up, input.right, input.down, input.left) {
+                    // One and two-key combinations
+                    (true, false, false, false) => transform.translate(0.0, -vel),
+                    (true, true, false, false) => transform.translate(vel * 0.707, -vel * 0.707),
+                    (false, true, false, false) => transform.translate(vel, 0.0),
+                    (false, true, true, false) => transform.translate(vel * 0.707, vel * 0.707),
+                    (false, false, true, false) => transform.translate(0.0, vel),
+                    (false, false, true, true) => transform.translate(-vel * 0.707, vel * 0.707),
+                    (false, false, false, true) => transform.translate(-vel, 0.0),
+                    (true, false, false, true) => transform.translate(-vel * 0.707, -vel * 0.707),
+                    // Three-key combinations
+                    (true, true, true, false) => transform.translate(vel, 0.0),
+                    (false, true, true, true) => transform.translate(0.0, vel),
+                    (true, false, true, true) => transform.translate(-vel, 0.0),
+                    (true, true, false, true) => transform.translate(0.0, -vel),
+                    _ => (),
+                }
+
+                // Limit controlled entities to the map.
+                if let Some(hitbox) = world.hitboxes.get(id) {
+                    let bounds = hitbox.bounds;
+                    if transform.x + bounds.x < 0.0 {
+                        transform.x = 0.0 - bounds.x;
+                    }
+                    if transform.x + bounds.x + bounds.w > world.screen_width {
+                        transform.x = world.screen_width - (bounds.x + bounds.w);
+                    }
+                    if transform.y + bounds.y < 0.0 {
+                        transform.y = 0.0 - bounds.y;
+                    }
+                    if transform.y + bounds.y + bounds.h > world.screen_height {
+                        transform.y = world.screen_height - (bounds.y + bounds.h);
+                    }
+                }
+            }
+        }
+    }
+
+    let decay = 1.0 - (KNOCKBACK_DECAY * delta_time).min(1.0);
+    for player in world.players.iter_mut() {
+        player.knockback = (player.knockback.0 * decay, player.knockback.1 * decay);
+    }
+
+    // Pull nearby pickups towards the closest player with a magnet
+    if magnets.is_empty() {
+        return;
+    }
+    for (id, _) in world.pickups.iter() {
+        let transform = match world.transforms.get_mut(id) {
+            Some(transform) => transform,
+            None => continue,
+        };
+        let (x, y) = match world.hitboxes.get(id) {
+            Some(h) => (transform.x + h.bounds.x + h.bounds.w / 2.0, transform.y + h.bounds.y + h.bounds.h / 2.0),
+            None => (transform.x, transform.y),
+        };
+        let (dx, dy, distance) = magnets
+            .iter()
+            .map(|&(px, py)| (px - x, py - y, ((px - x).powi(2) + (py - y).powi(2)).sqrt()))
+            .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal))
+            .unwrap();
+        if distance > 0.0 && distance < MAGNET_RADIUS {
+            let step = (MAGNET_SPEED * delta_time).min(distance);
+            transform.translate(dx / distance * step, dy / distance * step);
+        }
+    }
+}
+
+/// Middle of a player's hitbox, if their crab is alive.
+fn player_center(world: &World, slot: usize) -> Option<(f32, f32)> {
+    let player = world.players[slot].entity?;
+    match (world.transforms.get(player), world.hitboxes.get(player)) {
+        (Some(t), Some(h)) => Some((t.x + h.bounds.x + h.bounds.w / 2.0, t.y + h.bounds.y + h.bounds.h / 2.0)),
+        _ => None,
+    }
+}
+
+/// Where homing projectiles should aim for: the top-left corner a player
+/// bullet would need to reach to sit in the middle of each enemy's hitbox.
+fn enemy_centers(world: &World) -> Vec<(f32, f32)> {
+    let mut centers = vec![];
+    for (id, transform) in world.transforms.iter() {
+        match world.entity_type(id) {
+            EntityType::Enemy | EntityType::EnemyBlueScreen | EntityType::Boss | EntityType::Special => (),
+            _ => continue,
+        }
+        if let Some(hitbox) = world.hitboxes.get(id) {
+            centers.push((
+                transform.x + hitbox.bounds.x + hitbox.bounds.w / 2.0 - 25.0,
+                transform.y + hitbox.bounds.y + hitbox.bounds.h / 2.0 - 25.0,
+            ));
+        }
+    }
+    centers
+}
+
+/// Counts down weapon cooldowns and fires every weapon that is ready.
+pub fn firing_system(world: &mut World, inputs: &[Input], delta_ms: u64) {
+    let pi = std::f64::consts::PI;
+    let mut bullets: Vec<Entity> = vec![];
+    let mut fired: Vec<EntityId> = vec![];
+
+    for (id, weapon) in world.weapons.iter_mut() {
+        // Process bullet cooldowns
+        weapon.cooldown -= delta_ms as i64;
+        if weapon.cooldown < 0 {
+            weapon.cooldown = 0;
+        }
+
+        let transform = match world.transforms.get(id) {
+            Some(&transform) => transform,
+            None => continue,
+        };
+
+        // A player's gun is whatever is selected in their arsenal.
+        if weapon.pattern == FirePattern::Player {
+            let slot = match world.players.iter().position(|p| p.entity == Some(id)) {
+                Some(slot) => slot,
+                None => continue,
+            };
+            let player = &world.players[slot];
+            let shoot = inputs.get(slot).is_some_and(|input| input.shoot);
+            // Letting go of shoot lets the player fire again straight away
+            if !shoot && player.trigger_held {
+                weapon.cooldown = 0;
+            }
+            if shoot && weapon.cooldown == 0 {
+                fired.push(id);
+                weapon.cooldown = player.arsenal.cooldown();
+                if player.status.has(StatusKind::RapidFire) {
+                    weapon.cooldown /= 2;
+                }
+                let origin = (transform.x + weapon.muzzle.0, transform.y + weapon.muzzle.1);
+                bullets.extend(player.arsenal.fire(&world.spawner, origin, weapon.radius));
+            }
+            continue;
+        }
+
+        // Bullet angles for this shot, in radians counterclockwise from the right.
+        let angles: Vec<f64> = match weapon.pattern {
+            _ if weapon.cooldown > 0 => continue,
+            FirePattern::Player => continue,
+            FirePattern::Down => vec![3.0 * pi / 2.0],
+            FirePattern::TripleSpread => vec![5.0 * pi / 4.0, 3.0 * pi / 2.0, 7.0 * pi / 4.0],
+            FirePattern::Ring(count) => {
+                let increment = pi * 2.0 / count as f64;
+                (0..count)
+                    .map(|i| transform.angle as f64 + increment * i as f64)
+                    .collect()
+            }
+        };
+        weapon.cooldown = weapon.period;
+        fired.push(id);
+
+        for angle in angles {
+            let angle = angle as f32;
+            let x = transform.x + weapon.muzzle.0 + weapon.radius * angle.cos();
+            let y = transform.y + weapon.muzzle.1 - weapon.radius * angle.sin();
+            bullets.push(world.spawner.spawn_enemy_bullet(x, y, angle));
+        }
+    }
+
+    for (slot, player) in world.players.iter_mut().enumerate() {
+        player.trigger_held = inputs.get(slot).is_some_and(|input| input.shoot);
+    }
+    for bullet in bullets {
+        world.spawn(bullet);
+    }
+    for id in fired {
+        let event = match world.player_slot(id) {
+            Some(slot) => GameEvent::PlayerFired { slot },
+            None => GameEvent::EnemyFired { kind: world.entity_type(id) },
+        };
+        world.emit(event);
+    }
+}
+
+/// Sets off one of a player's bombs when their bomb key is pressed.
+pub fn bomb_system(world: &mut World, inputs: &[Input]) {
+    for slot in 0..world.players.len() {
+        let bomb = inputs.get(slot).is_some_and(|input| input.bomb);
+        if !bomb || world.players[slot].bombs == 0 {
+            continue;
+        }
+        let (x, y) = match player_center(world, slot) {
+            Some(center) => center,
+            None => continue,
+        };
+
+        world.players[slot].bombs -= 1;
+        world.players[slot].invulnerable_ms = BOMB_INVULNERABILITY;
+        let blast = world.spawner.spawn_blast(x, y);
+        world.spawn(blast);
+        world.emit(GameEvent::BombUsed { slot });
+    }
+}
+
+/// Grows every bomb blast. Enemies and bullets caught in it are destroyed,
+/// while bosses only take some damage.
+pub fn blast_system(world: &mut World, delta_ms: u64) {
+    for blast_id in world.blasts.iter().map(|(id, _)| id).collect::<Vec<_>>() {
+        let center = match world.transforms.get(blast_id) {
+            Some(&t) => t,
+            None => continue,
+        };
+        let radius = {
+            let blast = world.blasts.get_mut(blast_id).unwrap();
+            blast.radius += blast.speed * delta_ms as f32 / 1000.0;
+            if blast.radius > blast.max_radius {
+                blast.radius = blast.max_radius;
+            }
+            blast.radius
+        };
+
+        let mut targets = vec![];
+        for &kind in &[
+            EntityType::Enemy,
+            EntityType::EnemyBlueScreen,
+            EntityType::Special,
+            EntityType::EnemyBullet,
+            EntityType::Boss,
+        ] {
+            targets.extend(world.query(kind));
+        }
+
+        for id in targets {
+            let (x, y) = match (world.transforms.get(id), world.hitboxes.get(id)) {
+                (Some(t), Some(h)) => (t.x + h.bounds.x + h.bounds.w / 2.0, t.y + h.bounds.y + h.bounds.h / 2.0),
+                _ => continue,
+            };
+            if (x - center.x).powi(2) + (y - center.y).powi(2) > radius * radius {
+                continue;
+            }
+            {
+                let blast = world.blasts.get_mut(blast_id).unwrap();
+                if blast.hit.contains(&id) {
+                    continue;
+                }
+                blast.hit.push(id);
+            }
+
+            if world.entity_type(id) == EntityType::Boss {
+                if let Some(health) = world.healths.get_mut(id) {
+                    health.hp -= BOMB_BOSS_DAMAGE;
+                }
+            } else {
+                world.lifetimes.insert(id, Lifetime::Milliseconds(0));
+                if let Some(health) = world.healths.get_mut(id) {
+                    health.hp = 0;
+                }
+            }
+        }
+    }
+}
+
+/// This function handles all entity-entity interactions when colliding
+pub fn collision_system(world: &mut World) {
+    let ids = world.entities.ids();
+
+    // Iterate through all entities
+    for &entity_id in &ids {
+        match world.entity_type(entity_id) {
+            // In the case of player
+            EntityType::Player => {
+                let slot = match world.player_slot(entity_id) {
+                    Some(slot) => slot,
+                    None => continue,
+                };
+                for &threat_id in &ids {
+                    // Skip anything the other player already got to
+                    if expired(world, threat_id) || !colliding(world, entity_id, threat_id) {
+                        continue;
+                    }
+                    match world.entity_type(threat_id) {
+                        // When player collides with enemy
+                        EntityType::Enemy
+                        | EntityType::EnemyBlueScreen
+                        | EntityType::Boss
+                        | EntityType::Special => {
+                            hurt_player(world, slot, threat_id, true);
+
+                            // Destroy enemies other than boss
+                            if world.entity_type(threat_id) != EntityType::Boss {
+                                world.lifetimes.insert(threat_id, Lifetime::Milliseconds(0));
+                            }
+                        }
+
+                        // When player collides with enemy bullet
+                        EntityType::EnemyBullet => {
+                            hurt_player(world, slot, threat_id, false);
+
+                            // Destroy bullet
+                            world.lifetimes.insert(threat_id, Lifetime::Milliseconds(0));
+                        }
+
+                        // When player collides with an item
+                        _ => {
+                            let kind = match world.pickups.get(threat_id) {
+                                Some(pickup) => pickup.kind,
+                                None => continue,
+                            };
+                            collect_pickup(world, slot, kind);
+
+                            // Remove the item
+                            world.lifetimes.insert(threat_id, Lifetime::Milliseconds(0));
+                        }
+                    }
+                }
+            }
+
+            // In the case of an enemy or boss
+            EntityType::Enemy | EntityType::EnemyBlueScreen | EntityType::Boss | EntityType::Special => {
+                for &threat_id in &ids {
+                    // When an enemy collides with a player bullet or laser
+                    match world.entity_type(threat_id) {
+                        EntityType::PlayerBullet | EntityType::Laser => (),
+                        _ => continue,
+                    }
+                    if !colliding(world, entity_id, threat_id) {
+                        continue;
+                    }
+
+                    // Piercing shots only hurt each enemy once
+                    let mut spent = true;
+                    if let Some(piercing) = world.piercings.get_mut(threat_id) {
+                        if piercing.hit.contains(&entity_id) {
+                            continue;
+                        }
+                        piercing.hit.push(entity_id);
+                        if let Some(ref mut hits_left) = piercing.hits_left {
+                            *hits_left = hits_left.saturating_sub(1);
+                        }
+                        spent = piercing.hits_left == Some(0);
+                    }
+
+                    // Hurt the enemy by bullet damage amount
+                    let damage = world.hitboxes.get(threat_id).map_or(0, |h| h.damage);
+                    if let Some(health) = world.healths.get_mut(entity_id) {
+                        health.hp -= damage;
+                    }
+
+                    // Kill the bullet
+                    if spent {
+                        world.lifetimes.insert(threat_id, Lifetime::Milliseconds(0));
+                    }
+
+                    let kind = world.entity_type(entity_id);
+                    world.emit(GameEvent::EnemyHit { kind });
+                }
+            }
+
+            _ => (),
+        }
+    }
+}
+
+/// Damages a player by the threat's contact damage, unless a shield
+/// absorbs the hit. Ramming an enemy also costs a gun upgrade.
+fn hurt_player(world: &mut World, slot: usize, threat_id: EntityId, downgrade: bool) {
+    if world.players[slot].invulnerable_ms > 0 {
+        return;
+    }
+    unsafe {
+        if GOD_MODE && world.players[slot].status.shield_charges() == 0 {
+            return;
+        }
+    }
+
+    // Give the player a moment to recover, and push them away from the threat
+    let feedback = world.difficulty.hit_feedback();
+    world.players[slot].invulnerable_ms = feedback.invulnerability_ms;
+    world.shake_ms = feedback.shake_ms;
+    if let (Some((px, py)), Some(&threat)) = (player_center(world, slot), world.transforms.get(threat_id)) {
+        let (tx, ty) = match world.hitboxes.get(threat_id) {
+            Some(h) => (threat.x + h.bounds.x + h.bounds.w / 2.0, threat.y + h.bounds.y + h.bounds.h / 2.0),
+            None => (threat.x, threat.y),
+        };
+        let (dx, dy) = (px - tx, py - ty);
+        let distance = (dx * dx + dy * dy).sqrt();
+        world.players[slot].knockback = if distance > 0.0 {
+            (dx / distance * feedback.knockback, dy / distance * feedback.knockback)
+        } else {
+            (0.0, feedback.knockback)
+        };
+    }
+
+    // If shield is active, it loses a charge instead
+    let damage = world.hitboxes.get(threat_id).map_or(0, |h| h.damage);
+    let source = world.entity_type(threat_id);
+    let absorbed = world.players[slot].status.absorb_hit() || unsafe { GOD_MODE };
+    world.emit(GameEvent::PlayerHit { slot, source, damage, absorbed });
+    if absorbed {
+        return;
+    }
+
+    world.hitstop_ms = feedback.hitstop_ms;
+    world.combo.reset();
+    world.adaptive.record(Sample::Hit);
+
+    // Practice runs never cost a life
+    if !world.play_mode.infinite_lives() {
+        if let Some(health) = world.players[slot].entity.and_then(|id| world.healths.get_mut(id)) {
+            health.hp -= damage;
+        }
+    }
+    if downgrade {
+        world.players[slot].arsenal.downgrade();
+    }
+}
+
+/// Applies the effect of an item a player picked up.
+fn collect_pickup(world: &mut World, slot: usize, kind: PickupKind) {
+    world.emit(GameEvent::PickupCollected { slot, kind });
+    match kind {
+        PickupKind::Powerbomb => {
+            // Stock the bomb for later
+            if world.players[slot].bombs < MAX_BOMBS {
+                world.players[slot].bombs += 1;
+            }
+        }
+        PickupKind::GunUpgrade => {
+            // Upgrade the player's gun
+            world.players[slot].arsenal.upgrade();
+        }
+        PickupKind::Weapon(kind) => {
+            // Switch to the new gun, or upgrade it if already in use
+            world.players[slot].arsenal.collect(kind);
+        }
+        PickupKind::Status(kind) => {
+            world.players[slot].status.add(kind);
+        }
+    }
+}
+
+/// Returns true if the two entities are colliding. Collision is calculated
+/// using the `Hitbox` bounds of the entity, not the sprite.
+pub fn colliding(world: &World, a: EntityId, b: EntityId) -> bool {
+    match (hitbox_bounds(world, a), hitbox_bounds(world, b)) {
+        (Some(a), Some(b)) => overlapping(&a, &b),
+        _ => false,
+    }
+}
+
+/// Hitbox of an entity in screen coordinates.
+pub fn hitbox_bounds(world: &World, id: EntityId) -> Option<Rect> {
+    match (world.transforms.get(id), world.hitboxes.get(id)) {
+        (Some(t), Some(h)) => Some(Rect {
+            x: t.x + h.bounds.x,
+            y: t.y + h.bounds.y,
+            ..h.bounds
+        }),
+        _ => None,
+    }
+}
+
+pub fn overlapping(a: &Rect, b: &Rect) -> bool {
+    a.x < b.x + b.w && a.x + a.w > b.x && a.y < b.y + b.h && a.y + a.h > b.y
+}
+
+/// Returns true if an entity has been marked to die at the end of this update.
+fn expired(world: &World, id: EntityId) -> bool {
+    match world.lifetimes.get(id) {
+        Some(&Lifetime::Milliseconds(remaining)) => remaining <= 0,
+        _ => false,
+    }
+}
+
+/// Rewards enemy bullets that pass close to a player without hitting
+/// them. Each bullet can only be grazed once, and grazing charges a meter
+/// that gives the player a bomb when full.
+pub fn graze_system(world: &mut World) {
+    for slot in 0..world.players.len() {
+        graze_player(world, slot);
+    }
+}
+
+fn graze_player(world: &mut World, slot: usize) {
+    // No grazing while the player is blinking, it would be too easy
+    if world.players[slot].invulnerable_ms > 0 {
+        return;
+    }
+    let player = match world.players[slot].entity {
+        Some(id) => id,
+        None => return,
+    };
+    let zone = match hitbox_bounds(world, player) {
+        Some(bounds) => Rect {
+            x: bounds.x - GRAZE_MARGIN,
+            y: bounds.y - GRAZE_MARGIN,
+            w: bounds.w + GRAZE_MARGIN * 2.0,
+            h: bounds.h + GRAZE_MARGIN * 2.0,
+        },
+        None => return,
+    };
+
+    let mut grazes = 0;
+    for id in world.query(EntityType::EnemyBullet) {
+        // Skip bullets already grazed, and those that hit the player
+        if world.grazed.contains(id) || expired(world, id) || colliding(world, player, id) {
+            continue;
+        }
+        match hitbox_bounds(world, id) {
+            Some(ref bounds) if overlapping(&zone, bounds) => (),
+            _ => continue,
+        }
+        world.grazed.insert(id, ());
+        world.adaptive.record(Sample::Graze);
+        grazes += 1;
+    }
+    if grazes == 0 {
+        return;
+    }
+
+    world.emit(GameEvent::Grazed { slot, bullets: grazes });
+    let earned_bomb = {
+        let player = &mut world.players[slot];
+        player.grazes += grazes;
+        player.graze_flash_ms = GRAZE_FLASH;
+
+        // Hold a full meter until there is room for another bomb
+        player.graze_meter = std::cmp::min(player.graze_meter + GRAZE_CHARGE * grazes, GRAZE_METER_MAX);
+        player.graze_meter == GRAZE_METER_MAX && player.bombs < MAX_BOMBS
+    };
+    if earned_bomb {
+        world.players[slot].graze_meter = 0;
+        world.players[slot].bombs += 1;
+        world.emit(GameEvent::BombEarned { slot });
+    }
+}
+
+/// Notices players whose crab has died. While a partner is still alive a
+/// downed player comes back after a delay with a few hit points and a fresh
+/// gun, otherwise they are out for good.
+pub fn revive_system(world: &mut World, delta_ms: u64) {
+    for slot in 0..world.players.len() {
+        let partner_alive = (0..world.players.len())
+            .filter(|&other| other != slot)
+            .any(|other| world.players[other].entity.is_some_and(|id| world.contains(id)));
+
+        if let Some(id) = world.players[slot].entity {
+            if !world.contains(id) {
+                let player = &mut world.players[slot];
+                player.entity = None;
+                player.revive_ms = if partner_alive { Some(REVIVE_DELAY) } else { None };
+            }
+            continue;
+        }
+
+        let remaining = match world.players[slot].revive_ms {
+            Some(remaining) if partner_alive => remaining - delta_ms as i64,
+            _ => {
+                world.players[slot].revive_ms = None;
+                continue;
+            }
+        };
+        if remaining > 0 {
+            world.players[slot].revive_ms = Some(remaining);
+            continue;
+        }
+
+        let mut crab = world.spawner.spawn_player(slot, world.players.len());
+        if let Some(ref mut health) = crab.health {
+            health.hp = REVIVE_HP;
+        }
+        let entity = world.insert(crab);
+        let invulnerability = world.difficulty.hit_feedback().invulnerability_ms;
+        let player = &mut world.players[slot];
+        player.entity = Some(entity);
+        player.revive_ms = None;
+        player.arsenal = Arsenal::new();
+        player.status.clear();
+        player.knockback = (0.0, 0.0);
+        player.invulnerable_ms = invulnerability;
+        world.emit(GameEvent::PlayerRevived { slot });
+    }
+}
+
+/// Counts down lifetimes and returns every entity that should now die,
+/// either by expiring, running out of hit points or leaving the screen.
+/// Enemies and players that ran out of hit points are also reported as
+/// events.
+pub fn lifetime_system(world: &mut World, delta_ms: u64) -> Vec<Death> {
+    for (_, lifetime) in world.lifetimes.iter_mut() {
+        if let Lifetime::Milliseconds(remaining) = *lifetime {
+            *lifetime = Lifetime::Milliseconds(remaining - delta_ms as i64);
+        }
+    }
+
+    let mut deaths: Vec<Death> = vec![];
+    for (id, transform) in world.transforms.iter() {
+        let killed = world.healths.get(id).is_some_and(|h| h.hp <= 0);
+        let expired = match world.lifetimes.get(id) {
+            Some(&Lifetime::Milliseconds(r)) => r <= 0,
+            _ => false,
+        };
+        if killed || expired || transform.y > world.screen_height {
+            deaths.push(Death {
+                id,
+                entity_type: world.entity_type(id),
+                x: transform.x,
+                y: transform.y,
+                killed,
+            });
+        }
+    }
+
+    for death in deaths.iter().filter(|d| d.killed) {
+        let event = match world.player_slot(death.id) {
+            Some(slot) => GameEvent::PlayerDown { slot },
+            None => GameEvent::EnemyKilled {
+                kind: death.entity_type,
+                name: world.sprites.get(death.id).and_then(|s| s.label.clone()),
+                x: death.x,
+                y: death.y,
+                bounty: world.healths.get(death.id).map_or(0, |h| h.bounty),
+            },
+        };
+        world.emit(event);
+    }
+    deaths
+}
+
+/// Awards points for the kills and grazes among `events`. Kills are worth
+/// their bounty, shared using the best multiplier among the players.
+pub fn scoring_system(world: &mut World, events: &[GameEvent]) {
+    for event in events {
+        match *event {
+            GameEvent::EnemyKilled { x, y, bounty, .. } => {
+                // Every kill keeps the combo going, then scores at the new multiplier
+                world.combo.add_kill();
+                world.adaptive.record(Sample::Kill);
+                let multiplier = world.players.iter().map(|p| p.status.score_multiplier()).max().unwrap_or(1);
+                let points = bounty * multiplier * world.combo.multiplier();
+                world.score += points;
+
+                let popup = world.spawner.spawn_popup(x, y, format!("+{}", points));
+                world.spawn(popup);
+            }
+            GameEvent::Grazed { slot, bullets } => {
+                world.score += GRAZE_POINTS * bullets as i32 * world.players[slot].status.score_multiplier();
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Spawns the on-death effects and removes the dead entities.
+pub fn death_effects_system(world: &mut World, deaths: &[Death]) {
+    for death in deaths {
+        let (x, y) = (death.x, death.y);
+        match death.entity_type {
+            EntityType::Boss => {
+                let splat = world.spawner.spawn_splat(x, y);
+                world.spawn(splat);
+                if death.killed {
+                    world.bosses_defeated += 1;
+                    world.emit(GameEvent::BossDefeated);
+                }
+                world.spawner.boss_gone();
+            }
+            EntityType::Enemy => {
+                let splat = world.spawner.spawn_splat(x, y);
+                world.spawn(splat);
+            }
+            EntityType::EnemyBlueScreen => {
+                let shutoff = world.spawner.spawn_shutoff(x, y);
+                world.spawn(shutoff);
+            }
+            EntityType::Special => {
+                let mut item = world.spawner.spawn_item();
+                item.transform.x = x;
+                item.transform.y = y;
+                world.spawn(item);
+            }
+            _ => (),
+        }
+        world.despawn(death.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::components::{Health, Transform};
+    use game::{PLAYER_BULLET_COOLDOWN, PLAYER_BULLET_SPEED, STARTING_BOMBS};
+    use game::play_mode::{PlayMode, BOSS_RUSH_BOSSES};
+    use game::weapons::WeaponKind;
+    use proptest::prelude::*;
+
+    fn no_input() -> Input {
+        Input {
+            left: false,
+            right: false,
+            up: false,
+            down: false,
+            shoot: false,
+            bomb: false,
+        }
+    }
+
+    fn new_world() -> World {
+        let mut world = World::new(1024, 768);
+        world.reset();
+        world.spawner.cooldowns.clear();
+        world
+    }
+
+    fn enemy_at(world: &World, x: f32, y: f32) -> Entity {
+        let mut enemy = world.spawner.spawn_enemy(0.0, "SEGFAULT", EntityType::Enemy);
+        enemy.transform = Transform::at(x, y);
+        enemy.velocity = None;
+        enemy.weapon = None;
+        enemy
+    }
+
+    fn player_position(world: &World) -> Transform {
+        *world.transforms.get(world.players[0].entity.unwrap()).unwrap()
+    }
+
+    #[test]
+    fn linear_movement_scales_with_time() {
+        let mut world = new_world();
+        let bullet = world.spawner.player_bullet_spawner(100.0, 500.0);
+        let id = world.insert(bullet);
+        movement_system(&mut world, &[no_input()], 500);
+        let t = world.transforms.get(id).unwrap();
+        assert_eq!((t.x, t.y), (100.0, 500.0 - PLAYER_BULLET_SPEED / 2.0));
+    }
+
+    #[test]
+    fn player_is_kept_on_screen() {
+        let mut world = new_world();
+        let input = Input { left: true, ..no_input() };
+        movement_system(&mut world, &[input], 10_000);
+        let t = player_position(&world);
+        let bounds = world.hitboxes.get(world.players[0].entity.unwrap()).unwrap().bounds;
+        assert_eq!(t.x + bounds.x, 0.0);
+    }
+
+    #[test]
+    fn expired_entities_die_without_scoring() {
+        let mut world = new_world();
+        let id = world.insert(world.spawner.spawn_splat(10.0, 10.0));
+        let deaths = lifetime_system(&mut world, 1_000);
+        assert_eq!(deaths.len(), 1);
+        assert_eq!(deaths[0].id, id);
+        assert!(!deaths[0].killed);
+
+        let events = world.events.clone();
+        scoring_system(&mut world, &events);
+        death_effects_system(&mut world, &deaths);
+        world.flush();
+        assert!(!world.contains(id));
+        assert_eq!(world.score, 0);
+    }
+
+    #[test]
+    fn bullets_damage_enemies_and_score_on_kill() {
+        let mut world = new_world();
+        let enemy = enemy_at(&world, 300.0, 300.0);
+        let enemy = world.insert(enemy);
+        let bullet = world.insert(world.spawner.player_bullet_spawner(310.0, 310.0));
+
+        collision_system(&mut world);
+        assert_eq!(world.healths.get(enemy).unwrap().hp, 0);
+        assert_eq!(world.lifetimes.get(bullet), Some(&Lifetime::Milliseconds(0)));
+
+        world.update(&[no_input()], 0, 0);
+        assert!(!world.contains(enemy));
+        assert!(!world.contains(bullet));
+        assert_eq!(world.score, 10);
+        assert_eq!(world.query(EntityType::Splat).len(), 1);
+    }
+
+    #[test]
+    fn shield_absorbs_a_hit() {
+        let mut world = new_world();
+        world.players[0].status.add(StatusKind::Shield);
+        let t = player_position(&world);
+        let bullet = world.spawner.spawn_enemy_bullet(t.x + 55.0, t.y + 40.0, 0.0);
+        let first = world.insert(bullet);
+
+        collision_system(&mut world);
+        assert!(!world.players[0].status.has(StatusKind::Shield));
+        assert_eq!(world.player_hp(0), 5);
+        world.remove(first);
+        world.players[0].invulnerable_ms = 0;
+
+        let bullet = world.spawner.spawn_enemy_bullet(t.x + 55.0, t.y + 40.0, 0.0);
+        world.insert(bullet);
+        collision_system(&mut world);
+        assert_eq!(world.player_hp(0), 4);
+    }
+
+    #[test]
+    fn power_ups_change_how_the_player_plays() {
+        let mut world = new_world();
+        let shoot = Input { shoot: true, ..no_input() };
+        world.players[0].status.add(StatusKind::RapidFire);
+        firing_system(&mut world, &[shoot], 1_000);
+        let player = world.players[0].entity.unwrap();
+        assert_eq!(world.weapons.get(player).unwrap().cooldown, PLAYER_BULLET_COOLDOWN / 2);
+
+        let t = player_position(&world);
+        let mut pickup = world.spawner.spawn_gun_upgrade();
+        pickup.transform = Transform::at(t.x, t.y - 200.0);
+        pickup.velocity = None;
+        let pickup = world.insert(pickup);
+        world.insert(world.spawner.spawn_status_pickup(StatusKind::Magnet));
+        movement_system(&mut world, &[no_input()], 100);
+        assert_eq!(world.transforms.get(pickup).unwrap().y, t.y - 200.0);
+
+        world.players[0].status.add(StatusKind::Magnet);
+        movement_system(&mut world, &[no_input()], 100);
+        assert!(world.transforms.get(pickup).unwrap().y > t.y - 200.0);
+    }
+
+    #[test]
+    fn score_multiplier_applies_to_bounties() {
+        let mut world = new_world();
+        world.players[0].status.add(StatusKind::ScoreMultiplier);
+        let mut enemy = enemy_at(&world, 300.0, 300.0);
+        enemy.health = Some(Health { hp: 0, bounty: 10 });
+        world.insert(enemy);
+        world.update(&[no_input()], 0, 0);
+        assert_eq!(world.score, 20);
+    }
+
+    #[test]
+    fn boss_rush_sends_bosses_one_after_another() {
+        let mut world = World::new(1024, 768);
+        world.play_mode = PlayMode::BossRush;
+        world.reset();
+        assert_eq!(world.spawner.cooldowns.len(), 2);
+        assert!(world.spawner.cooldowns.contains_key(&EntityType::Boss));
+
+        // Only one boss at a time
+        world.spawner.cooldowns.insert(EntityType::Special, i64::MAX);
+        world.update(&[no_input()], 0, 3_000);
+        world.update(&[no_input()], 0, 100);
+        let bosses = world.query(EntityType::Boss);
+        assert_eq!(bosses.len(), 1);
+        assert!(world.spawner.cooldowns[&EntityType::Boss] > 1_000_000);
+
+        // Beating it brings the next one
+        world.healths.get_mut(bosses[0]).unwrap().hp = 0;
+        world.update(&[no_input()], 0, 0);
+        assert_eq!(world.bosses_defeated, 1);
+        world.update(&[no_input()], 0, 3_000);
+        assert_eq!(world.query(EntityType::Boss).len(), 1);
+        assert!(!world.won(0));
+        world.bosses_defeated = BOSS_RUSH_BOSSES;
+        assert!(world.won(0));
+    }
+
+    #[test]
+    fn practice_runs_never_cost_a_life() {
+        let mut world = World::new(1024, 768);
+        world.play_mode = PlayMode::Practice;
+        world.reset();
+        let hp = world.player_hp(0);
+        let t = player_position(&world);
+        let bullet = world.spawner.spawn_enemy_bullet(t.x + 60.0, t.y + 40.0, 0.0);
+        world.insert(bullet);
+        collision_system(&mut world);
+        assert_eq!(world.player_hp(0), hp);
+        assert!(world.players[0].invulnerable_ms > 0);
+    }
+
+    #[test]
+    fn near_misses_graze_once_and_fill_the_meter() {
+        let mut world = new_world();
+        let t = player_position(&world);
+        let bombs = world.players[0].bombs;
+
+        // A bullet just to the side of the hitbox grazes, but only once
+        let bullet = world.spawner.spawn_enemy_bullet(t.x + 60.0 + 10.0 + 5.0, t.y + 40.0, 0.0);
+        world.insert(bullet);
+        graze_system(&mut world);
+        graze_system(&mut world);
+        assert_eq!(world.players[0].grazes, 1);
+        let events = world.events.clone();
+        scoring_system(&mut world, &events);
+        assert_eq!(world.score, GRAZE_POINTS);
+        assert_eq!(world.players[0].graze_meter, GRAZE_CHARGE);
+
+        // A bullet on the hitbox hits instead of grazing
+        let hit = world.spawner.spawn_enemy_bullet(t.x + 55.0, t.y + 40.0, 0.0);
+        world.insert(hit);
+        graze_system(&mut world);
+        assert_eq!(world.players[0].grazes, 1);
+
+        // Filling the meter gives a bomb
+        world.players[0].graze_meter = GRAZE_METER_MAX - 1;
+        let bullet = world.spawner.spawn_enemy_bullet(t.x + 60.0, t.y, 0.0);
+        world.insert(bullet);
+        graze_system(&mut world);
+        assert_eq!(world.players[0].bombs, bombs + 1);
+        assert_eq!(world.players[0].graze_meter, 0);
+    }
+
+    #[test]
+    fn quick_kills_build_a_combo_that_hits_break() {
+        let mut world = new_world();
+        for i in 0..5 {
+            let mut enemy = enemy_at(&world, 100.0 + 100.0 * i as f32, 300.0);
+            enemy.health = Some(Health { hp: 0, bounty: 10 });
+            world.insert(enemy);
+            world.update(&[no_input()], 0, 100);
+        }
+        // The fifth kill in the chain is worth double
+        assert_eq!(world.combo.count, 5);
+        assert_eq!(world.score, 4 * 10 + 2 * 10);
+        assert_eq!(world.query(EntityType::Popup).len(), 5);
+
+        let t = player_position(&world);
+        let bullet = world.spawner.spawn_enemy_bullet(t.x + 40.0, t.y + 20.0, 0.0);
+        world.insert(bullet);
+        collision_system(&mut world);
+        assert_eq!(world.combo.count, 0);
+        assert_eq!(world.combo.max, 5);
+    }
+
+    #[test]
+    fn hits_give_invulnerability_and_knockback() {
+        let mut world = new_world();
+        let t = player_position(&world);
+        let feedback = world.difficulty.hit_feedback();
+        let mut boss = world.spawner.spawn_enemy(0.0, "ANSI C", EntityType::Boss);
+        boss.transform = Transform::at(t.x - 20.0, t.y - 80.0);
+        boss.velocity = None;
+        boss.weapon = None;
+        world.insert(boss);
+
+        // Overlapping the boss for several frames only hurts once
+        for _ in 0..5 {
+            collision_system(&mut world);
+        }
+        assert_eq!(world.player_hp(0), 4);
+        assert_eq!(world.players[0].invulnerable_ms, feedback.invulnerability_ms);
+        assert_eq!(world.hitstop_ms, feedback.hitstop_ms);
+        assert!(world.players[0].knockback.1 > 0.0);
+
+        // The game freezes during the hit-stop, then the player is pushed away
+        world.update(&[no_input()], 0, feedback.hitstop_ms as u64);
+        assert_eq!(player_position(&world), t);
+        world.update(&[no_input()], 0, 16);
+        assert!(player_position(&world).y > t.y || player_position(&world).x != t.x);
+    }
+
+    #[test]
+    fn weapons_wait_for_their_cooldown() {
+        let mut world = new_world();
+        let shoot = Input { shoot: true, ..no_input() };
+
+        firing_system(&mut world, &[shoot], 0);
+        world.flush();
+        assert!(world.query(EntityType::PlayerBullet).is_empty());
+
+        firing_system(&mut world, &[shoot], 200);
+        world.flush();
+        assert_eq!(world.query(EntityType::PlayerBullet).len(), 1);
+        assert_eq!(world.events, vec![GameEvent::PlayerFired { slot: 0 }]);
+
+        firing_system(&mut world, &[shoot], 100);
+        world.flush();
+        assert_eq!(world.query(EntityType::PlayerBullet).len(), 1);
+
+        // Letting go of shoot skips the rest of the cooldown
+        firing_system(&mut world, &[no_input()], 0);
+        firing_system(&mut world, &[shoot], 0);
+        world.flush();
+        assert_eq!(world.query(EntityType::PlayerBullet).len(), 2);
+    }
+
+    #[test]
+    fn piercing_bullets_hit_each_enemy_once() {
+        let mut world = new_world();
+        let mut first = enemy_at(&world, 300.0, 300.0);
+        first.health = Some(Health { hp: 3, bounty: 10 });
+        let first = world.insert(first);
+        let second = enemy_at(&world, 310.0, 300.0);
+        let second = world.insert(second);
+        let bullet = world.insert(world.spawner.spawn_piercing_bullet(320.0, 310.0, 3));
+
+        collision_system(&mut world);
+        collision_system(&mut world);
+        assert_eq!(world.healths.get(first).unwrap().hp, 2);
+        assert_eq!(world.healths.get(second).unwrap().hp, 0);
+        assert_eq!(world.lifetimes.get(bullet), Some(&Lifetime::Milliseconds(2_000)));
+        assert_eq!(world.piercings.get(bullet).unwrap().hits_left, Some(1));
+    }
+
+    #[test]
+    fn homing_missiles_turn_towards_enemies() {
+        let mut world = new_world();
+        let enemy = enemy_at(&world, 600.0, 300.0);
+        world.insert(enemy);
+        let missile = world.spawner.spawn_homing_missile(300.0, 300.0, std::f32::consts::PI / 2.0);
+        let missile = world.insert(missile);
+
+        for _ in 0..10 {
+            movement_system(&mut world, &[no_input()], 50);
+        }
+        match world.velocities.get(missile).unwrap().movement {
+            Movement::Homing(vx, _) => assert!(vx > 0.0),
+            _ => panic!("missile lost its homing movement"),
+        }
+        assert!(world.transforms.get(missile).unwrap().x > 300.0);
+    }
+
+    #[test]
+    fn weapon_pickups_switch_the_players_gun() {
+        let mut world = new_world();
+        let t = player_position(&world);
+        let mut pickup = world.spawner.spawn_weapon_pickup(WeaponKind::Laser);
+        pickup.transform = Transform::at(t.x + 40.0, t.y + 20.0);
+        world.insert(pickup);
+
+        collision_system(&mut world);
+        assert_eq!(world.players[0].arsenal.current, WeaponKind::Laser);
+
+        let shoot = Input { shoot: true, ..no_input() };
+        firing_system(&mut world, &[shoot], 1_000);
+        world.flush();
+        assert_eq!(world.query(EntityType::Laser).len(), 1);
+    }
+
+    #[test]
+    fn powerbombs_are_stocked_when_collected() {
+        let mut world = new_world();
+        let t = player_position(&world);
+        let enemy = enemy_at(&world, 500.0, 100.0);
+        let enemy = world.insert(enemy);
+        let mut bomb = world.spawner.spawn_powerbomb();
+        bomb.transform = Transform::at(t.x + 40.0, t.y + 20.0);
+        world.insert(bomb);
+
+        world.update(&[no_input()], 0, 0);
+        world.update(&[no_input()], 0, 0);
+        assert_eq!(world.players[0].bombs, STARTING_BOMBS + 1);
+        assert!(world.query(EntityType::Powerbomb).is_empty());
+        assert!(world.contains(enemy));
+    }
+
+    #[test]
+    fn bombs_clear_enemies_and_hurt_the_boss() {
+        let mut world = new_world();
+        let enemy = enemy_at(&world, 500.0, 100.0);
+        world.insert(enemy);
+        let mut boss = world.spawner.spawn_enemy(0.0, "ANSI C", EntityType::Boss);
+        boss.transform = Transform::at(100.0, 100.0);
+        boss.velocity = None;
+        boss.weapon = None;
+        let boss = world.insert(boss);
+
+        let bomb = Input { bomb: true, ..no_input() };
+        world.update(&[bomb], 0, 0);
+        assert_eq!(world.players[0].bombs, STARTING_BOMBS - 1);
+        assert_eq!(world.players[0].invulnerable_ms, BOMB_INVULNERABILITY);
+        assert_eq!(world.query(EntityType::Blast).len(), 1);
+
+        for _ in 0..20 {
+            world.update(&[no_input()], 0, 50);
+        }
+        assert!(world.query(EntityType::Enemy).is_empty());
+        assert!(world.query(EntityType::Blast).is_empty());
+        assert_eq!(world.healths.get(boss).unwrap().hp, 40 - BOMB_BOSS_DAMAGE);
+        assert_eq!(world.score, 10);
+    }
+
+    #[test]
+    fn bombs_make_the_player_invulnerable() {
+        let mut world = new_world();
+        assert_eq!(world.players[0].invulnerable_ms, 0);
+        bomb_system(&mut world, &[Input { bomb: true, ..no_input() }]);
+        assert_eq!(world.players[0].bombs, STARTING_BOMBS - 1);
+        assert_eq!(world.players[0].invulnerable_ms, BOMB_INVULNERABILITY);
+
+        let t = player_position(&world);
+        let bullet = world.spawner.spawn_enemy_bullet(t.x + 55.0, t.y + 40.0, 0.0);
+        world.insert(bullet);
+        collision_system(&mut world);
+        assert_eq!(world.player_hp(0), 5);
+    }
+
+    #[test]
+    fn downed_co_op_players_revive_until_both_are_out() {
+        let mut world = World::new(1024, 768);
+        world.player_count = 2;
+        world.reset();
+        world.spawner.cooldowns.clear();
+        let inputs = [no_input(), no_input()];
+        assert_eq!(world.players.len(), 2);
+
+        // The run goes on while the partner is alive, and the downed player comes back
+        let first = world.players[0].entity.unwrap();
+        world.healths.get_mut(first).unwrap().hp = 0;
+        world.update(&inputs, 0, 0);
+        assert!(world.player_alive());
+        world.update(&inputs, 0, 0);
+        assert_eq!(world.players[0].revive_ms, Some(REVIVE_DELAY));
+        world.update(&inputs, 0, REVIVE_DELAY as u64);
+        assert_eq!(world.player_hp(0), REVIVE_HP);
+        assert!(world.players[0].invulnerable_ms > 0);
+
+        // Nobody comes back once both are down
+        for slot in 0..2 {
+            let id = world.players[slot].entity.unwrap();
+            world.healths.get_mut(id).unwrap().hp = 0;
+        }
+        world.update(&inputs, 0, 0);
+        assert!(!world.player_alive());
+        world.update(&inputs, 0, REVIVE_DELAY as u64);
+        assert!(world.players.iter().all(|p| p.entity.is_none() && p.revive_ms.is_none()));
+    }
+
+    fn rect() -> impl Strategy<Value = Rect> {
+        (-200f32..1200.0, -200f32..900.0, 0f32..300.0, 0f32..300.0).prop_map(|(x, y, w, h)| Rect::new(x, y, w, h))
+    }
+
+    proptest! {
+        #[test]
+        fn overlapping_is_symmetric(a in rect(), b in rect()) {
+            prop_assert_eq!(overlapping(&a, &b), overlapping(&b, &a));
+            if a.w > 0.0 && a.h > 0.0 {
+                prop_assert!(overlapping(&a, &a));
+            }
+        }
+
+        #[test]
+        fn entities_are_culled_once_their_lifetime_runs_out(lifetime in 1i64..5_000, deltas in prop::collection::vec(0u64..500, 1..50)) {
+            let mut world = new_world();
+            let id = world.insert(world.spawner.spawn_splat(10.0, 10.0));
+            *world.lifetimes.get_mut(id).unwrap() = Lifetime::Milliseconds(lifetime);
+
+            let mut total = 0;
+            for delta in deltas {
+                total += delta as i64;
+                let deaths = lifetime_system(&mut world, delta);
+                death_effects_system(&mut world, &deaths);
+                world.flush();
+                prop_assert_eq!(world.contains(id), total < lifetime);
+                if total >= lifetime {
+                    break;
+                }
+            }
+        }
+
+        #[test]
+        fn entities_below_the_screen_are_culled(x in 0f32..1024.0, below in 0.1f32..1_000.0) {
+            let mut world = new_world();
+            let mut enemy = enemy_at(&world, x, 0.0);
+            enemy.transform.y = world.screen_height + below;
+            let id = world.insert(enemy);
+            let deaths = lifetime_system(&mut world, 0);
+            prop_assert!(deaths.iter().any(|d| d.id == id && !d.killed));
+        }
+    }
+}
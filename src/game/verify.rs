@@ -0,0 +1,213 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+//! The `ferris-verify` score checker. It plays the replay of every high
+//! score again without a window, and flags the scores that don't match
+//! what their run actually did.
+
+use game::difficulty::Difficulty;
+use game::play_mode::PlayMode;
+use game::replay::{self, Replay};
+use game::scores::{self, Origin, ScoreRecord, Scores};
+use std;
+
+const USAGE: &str = "usage: ferris-verify [SCORE FILE...]";
+
+/// What checking a score found.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    /// The replay plays out to the same score and time.
+    Verified,
+    /// There is no replay to check against, as with scores imported from
+    /// older versions.
+    Unverified,
+    /// The score doesn't match its replay, or the replay is missing or
+    /// broken. Says what is wrong.
+    Tampered(String),
+}
+
+/// Checks one high score against its replay.
+pub fn verify_score(record: &ScoreRecord) -> Verdict {
+    if record.replay.is_empty() {
+        // Every run played since replays came in is recorded, so a missing
+        // replay means it was taken out of the record
+        return match record.origin {
+            Origin::Imported => Verdict::Unverified,
+            Origin::Local | Origin::Online => Verdict::Tampered("the score has no replay".to_string()),
+        };
+    }
+    let replay = match Replay::load(&record.replay) {
+        Ok(replay) => replay,
+        Err(e) => return Verdict::Tampered(format!("could not load {}: {}", record.replay, e)),
+    };
+    match replay::verify(&replay, record.seed, record.score, record.time_ms) {
+        Ok(_) => Verdict::Verified,
+        Err(e) => Verdict::Tampered(e),
+    }
+}
+
+/// Checks every score in a high-score file, each table best first.
+pub fn verify_scores(file: &str) -> Result<Vec<(ScoreRecord, Verdict)>, String> {
+    let scores = Scores::load(file).map_err(|e| format!("could not load {}: {}", file, e))?;
+    let mut results = vec![];
+    for &mode in PlayMode::all().iter() {
+        for &difficulty in Difficulty::all().iter() {
+            for record in scores.table(Some(mode), Some(difficulty), mode.ranking()) {
+                results.push((record.clone(), verify_score(record)));
+            }
+        }
+    }
+    Ok(results)
+}
+
+/// Entry point of the `ferris-verify` binary. Exits with 1 if any score
+/// was tampered with.
+pub fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg.starts_with('-')) {
+        eprintln!("{}", USAGE);
+        std::process::exit(1);
+    }
+    let files = if args.is_empty() {
+        vec![scores::SCORES_FILE.to_string()]
+    } else {
+        args
+    };
+
+    let mut tampered = 0;
+    for file in files.iter() {
+        let results = match verify_scores(file) {
+            Ok(results) => results,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        println!("{}", file);
+        for (record, verdict) in results {
+            let status = match verdict {
+                Verdict::Verified => "OK".to_string(),
+                Verdict::Unverified => "UNVERIFIED (older version)".to_string(),
+                Verdict::Tampered(reason) => {
+                    tampered += 1;
+                    format!("TAMPERED ({})", reason)
+                }
+            };
+            println!(
+                "  {:11} {:6} {:<10} {:12} {:5}  {}",
+                record.play_mode.name(),
+                record.difficulty.name(),
+                record.score,
+                record.name,
+                record.time(),
+                status
+            );
+        }
+    }
+    if tampered > 0 {
+        eprintln!("{} tampered scores", tampered);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::Input;
+    use game::netplay::FRAME_MS;
+    use game::world::World;
+
+    /// Records a run of someone standing and shooting until they are out,
+    /// saves its replay under `name` and returns the score it makes.
+    fn recorded_score(name: &str, seed: u32, difficulty: Difficulty, adaptive: bool) -> (ScoreRecord, Replay) {
+        let mut world = World::new(1024, 768);
+        world.difficulty = difficulty;
+        world.adaptive.enabled = adaptive;
+        world.seed(seed);
+        world.reset();
+        let mut recorded = Replay::start(&world);
+        let inputs = [Input { shoot: true, ..Input::default() }];
+        let mut elapsed_ms = 0;
+        while world.player_alive() {
+            elapsed_ms += FRAME_MS;
+            recorded.record(&inputs, elapsed_ms, FRAME_MS);
+            world.update(&inputs, elapsed_ms, FRAME_MS);
+            world.events.clear();
+        }
+        let file = std::env::temp_dir().join(name);
+        let file = file.to_str().unwrap().to_string();
+        recorded.save(&file).unwrap();
+
+        let record = ScoreRecord {
+            score: world.score,
+            name: "ferris".to_string(),
+            time_ms: recorded.end_ms(),
+            date: 0,
+            play_mode: PlayMode::Classic,
+            difficulty,
+            max_gun_level: 1,
+            kills: 0,
+            max_combo: 0,
+            seed,
+            replay: file,
+            origin: Origin::Local,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        };
+        (record, recorded)
+    }
+
+    #[test]
+    fn flags_scores_that_their_replays_dont_back_up() {
+        let (record, _) = recorded_score("ferris-verify-test-replay.json", 11, Difficulty::Insane, false);
+        let file = record.replay.clone();
+        assert_eq!(verify_score(&record), Verdict::Verified);
+        let edited = ScoreRecord { score: record.score + 5_000, ..record.clone() };
+        match verify_score(&edited) {
+            Verdict::Tampered(_) => (),
+            verdict => panic!("edited score was {:?}", verdict),
+        }
+
+        let _ = std::fs::remove_file(&file);
+
+        match verify_score(&record) {
+            Verdict::Tampered(_) => (),
+            verdict => panic!("score with a missing replay was {:?}", verdict),
+        }
+        // Only imported scores may go without a replay
+        let unrecorded = ScoreRecord { replay: String::new(), ..record };
+        match verify_score(&unrecorded) {
+            Verdict::Tampered(_) => (),
+            verdict => panic!("score with its replay taken out was {:?}", verdict),
+        }
+        let online = ScoreRecord { origin: Origin::Online, ..unrecorded.clone() };
+        match verify_score(&online) {
+            Verdict::Tampered(_) => (),
+            verdict => panic!("online score with its replay taken out was {:?}", verdict),
+        }
+        // Blanking the version doesn't pass a score off as an old one
+        let blanked = ScoreRecord { version: String::new(), ..unrecorded.clone() };
+        match verify_score(&blanked) {
+            Verdict::Tampered(_) => (),
+            verdict => panic!("score with its version blanked was {:?}", verdict),
+        }
+        let old = ScoreRecord { origin: Origin::Imported, ..unrecorded };
+        assert_eq!(verify_score(&old), Verdict::Unverified);
+    }
+
+    #[test]
+    fn adaptive_runs_are_played_back_adaptive() {
+        let (record, recorded) = recorded_score("ferris-verify-test-adaptive.json", 12, Difficulty::Normal, true);
+        assert!(recorded.adaptive);
+        assert_eq!(verify_score(&record), Verdict::Verified);
+
+        // The same inputs without adaptive difficulty play out differently
+        let mut fixed = recorded.clone();
+        fixed.adaptive = false;
+        assert_ne!(fixed.play().unwrap().checksum, recorded.play().unwrap().checksum);
+        let _ = std::fs::remove_file(&record.replay);
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+use game::components::Movement;
+use game::entity::Entity;
+use game::entity_spawner::EntitySpawner;
+use game::{MAX_UPGRADE_LEVEL, PLAYER_BULLET_COOLDOWN, PLAYER_BULLET_SPEED};
+use std;
+
+const LASER_COOLDOWN: i64 = 300;
+const HOMING_COOLDOWN: i64 = 600;
+const PIERCING_COOLDOWN: i64 = 400;
+
+/// The different guns the player can carry.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Copy, Clone, Serialize, Deserialize)]
+pub enum WeaponKind {
+    /// A fan of bullets, one more per level.
+    Spread,
+    /// A beam straight up the screen that gets wider with each level.
+    Laser,
+    /// Missiles that steer towards the nearest enemy.
+    Homing,
+    /// Bullets that pass through one more enemy per level.
+    Piercing,
+}
+
+impl WeaponKind {
+    /// Every weapon, in the order they are shown to the player.
+    pub fn all() -> [WeaponKind; 4] {
+        [WeaponKind::Spread, WeaponKind::Laser, WeaponKind::Homing, WeaponKind::Piercing]
+    }
+
+    /// Name shown on the HUD.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            WeaponKind::Spread => "SPREAD",
+            WeaponKind::Laser => "LASER",
+            WeaponKind::Homing => "HOMING",
+            WeaponKind::Piercing => "PIERCING",
+        }
+    }
+
+    /// Highest level this weapon can be upgraded to.
+    pub fn max_level(&self) -> u32 {
+        match *self {
+            WeaponKind::Spread => MAX_UPGRADE_LEVEL,
+            WeaponKind::Laser => 6,
+            WeaponKind::Homing => 6,
+            WeaponKind::Piercing => 8,
+        }
+    }
+
+    /// Time between shots (in ms) at the given level.
+    pub fn cooldown(&self, level: u32) -> i64 {
+        match *self {
+            WeaponKind::Spread => PLAYER_BULLET_COOLDOWN,
+            WeaponKind::Laser => LASER_COOLDOWN - 25 * level as i64,
+            WeaponKind::Homing => HOMING_COOLDOWN,
+            WeaponKind::Piercing => PIERCING_COOLDOWN - 25 * level as i64,
+        }
+    }
+}
+
+/// The weapons the player has collected. Each weapon keeps its own level,
+/// so switching away from a gun and back again doesn't lose progress.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Arsenal {
+    pub current: WeaponKind,
+    /// Kept sorted so saved runs always come out the same.
+    pub levels: std::collections::BTreeMap<WeaponKind, u32>,
+}
+
+impl Arsenal {
+    /// A fresh arsenal holding a level 1 spread gun.
+    pub fn new() -> Arsenal {
+        let mut levels = std::collections::BTreeMap::new();
+        for &kind in WeaponKind::all().iter() {
+            levels.insert(kind, 1);
+        }
+        Arsenal {
+            current: WeaponKind::Spread,
+            levels,
+        }
+    }
+
+    /// Level of the weapon currently in use.
+    pub fn level(&self) -> u32 {
+        self.levels[&self.current]
+    }
+
+    /// Returns true if the current weapon can't be upgraded any further.
+    pub fn is_maxed(&self) -> bool {
+        self.level() >= self.current.max_level()
+    }
+
+    /// Raises the level of the current weapon, up to its maximum.
+    pub fn upgrade(&mut self) {
+        let max = self.current.max_level();
+        let level = self.levels.get_mut(&self.current).unwrap();
+        if *level < max {
+            *level += 1;
+        }
+    }
+
+    /// Lowers the level of the current weapon, down to 1.
+    pub fn downgrade(&mut self) {
+        let level = self.levels.get_mut(&self.current).unwrap();
+        if *level > 1 {
+            *level -= 1;
+        }
+    }
+
+    /// Picking up the weapon already in use levels it, any other one
+    /// is switched to.
+    pub fn collect(&mut self, kind: WeaponKind) {
+        if kind == self.current {
+            self.upgrade();
+        } else {
+            self.current = kind;
+        }
+    }
+
+    /// Time until the current weapon can fire again (in ms).
+    pub fn cooldown(&self) -> i64 {
+        self.current.cooldown(self.level())
+    }
+
+    /// Builds the projectiles for one shot of the current weapon. Bullets
+    /// start on a circle of `radius` around `origin`, which is the point
+    /// where a bullet sprite would sit in the middle of the player.
+    pub fn fire(&self, spawner: &EntitySpawner, origin: (f32, f32), radius: f32) -> Vec<Entity> {
+        let pi = std::f32::consts::PI;
+        let level = self.level();
+        let (x, y) = origin;
+
+        match self.current {
+            WeaponKind::Spread => {
+                let angle_step = pi / 16.0;
+                (0..level)
+                    .map(|i| {
+                        let angle = pi / 2.0 + (i as f32 - level as f32 / 2.0) * angle_step + angle_step / 2.0;
+                        let mut bullet = spawner.player_bullet_spawner(
+                            x + radius * angle.cos(),
+                            y - radius * angle.sin(),
+                        );
+                        if let Some(ref mut velocity) = bullet.velocity {
+                            velocity.movement = Movement::Linear(
+                                PLAYER_BULLET_SPEED * angle.cos(),
+                                -PLAYER_BULLET_SPEED * angle.sin(),
+                            );
+                        }
+                        bullet
+                    })
+                    .collect()
+            }
+            WeaponKind::Laser => vec![spawner.spawn_laser(x, y, 6.0 + 6.0 * level as f32)],
+            WeaponKind::Homing => {
+                let missiles = level.div_ceil(2);
+                let angle_step = pi / 6.0;
+                (0..missiles)
+                    .map(|i| {
+                        let angle = pi / 2.0 + (i as f32 - missiles as f32 / 2.0) * angle_step + angle_step / 2.0;
+                        spawner.spawn_homing_missile(
+                            x + radius * angle.cos(),
+                            y - radius * angle.sin(),
+                            angle,
+                        )
+                    })
+                    .collect()
+            }
+            WeaponKind::Piercing => vec![spawner.spawn_piercing_bullet(x, y - radius, level + 1)],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collecting_the_current_weapon_levels_it() {
+        let mut arsenal = Arsenal::new();
+        arsenal.collect(WeaponKind::Spread);
+        assert_eq!(arsenal.level(), 2);
+
+        arsenal.collect(WeaponKind::Laser);
+        assert_eq!(arsenal.current, WeaponKind::Laser);
+        assert_eq!(arsenal.level(), 1);
+
+        arsenal.collect(WeaponKind::Spread);
+        assert_eq!(arsenal.level(), 2);
+    }
+
+    #[test]
+    fn levels_stay_in_range() {
+        let mut arsenal = Arsenal::new();
+        arsenal.downgrade();
+        assert_eq!(arsenal.level(), 1);
+        for _ in 0..100 {
+            arsenal.upgrade();
+        }
+        assert_eq!(arsenal.level(), MAX_UPGRADE_LEVEL);
+        assert!(arsenal.is_maxed());
+    }
+
+    #[test]
+    fn spread_fires_one_bullet_per_level() {
+        let spawner = EntitySpawner::new(1024, 768);
+        let mut arsenal = Arsenal::new();
+        assert_eq!(arsenal.fire(&spawner, (0.0, 0.0), 64.0).len(), 1);
+        arsenal.upgrade();
+        arsenal.upgrade();
+        assert_eq!(arsenal.fire(&spawner, (0.0, 0.0), 64.0).len(), 3);
+    }
+}
@@ -0,0 +1,421 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+use game::Input;
+use game::components::{Blast, Elite, Health, Hitbox, Lifetime, Pickup, Piercing, Sprite, Transform, Velocity, Weapon};
+use game::adaptive::AdaptiveDifficulty;
+use game::combo::Combo;
+use game::difficulty::Difficulty;
+use game::entity::{Entity, EntityType};
+use game::entity_spawner::EntitySpawner;
+use game::entity_store::{ComponentStorage, EntityId, EntityStore};
+use game::events::GameEvent;
+use game::play_mode::{PlayMode, PracticeOptions, BOSS_RUSH_BOSSES, TIME_ATTACK_SECONDS};
+use game::systems;
+use game::player::Player;
+use game::rng::GameRng;
+use game::stats::RunStats;
+
+/// The gameplay simulation. It owns every entity and its components,
+/// along with the state of the current run, and advances them by
+/// running the systems in order. Nothing in here touches the window,
+/// so it can be driven without a ggez `Context`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct World {
+    /// Allocator for entity ids.
+    pub entities: EntityStore,
+    /// What kind of game object each entity is.
+    pub kinds: ComponentStorage<EntityType>,
+    pub transforms: ComponentStorage<Transform>,
+    pub velocities: ComponentStorage<Velocity>,
+    pub healths: ComponentStorage<Health>,
+    pub weapons: ComponentStorage<Weapon>,
+    pub hitboxes: ComponentStorage<Hitbox>,
+    pub lifetimes: ComponentStorage<Lifetime>,
+    pub sprites: ComponentStorage<Sprite>,
+    pub pickups: ComponentStorage<Pickup>,
+    pub piercings: ComponentStorage<Piercing>,
+    pub blasts: ComponentStorage<Blast>,
+    pub elites: ComponentStorage<Elite>,
+    /// Entities waiting to be added on the next flush.
+    pending_spawns: Vec<Entity>,
+    /// Everyone playing, indexed by player number starting at 0
+    pub players: Vec<Player>,
+    /// How many players the next run starts with
+    pub player_count: usize,
+    /// Generator for game objects like enemies and bullets
+    pub spawner: EntitySpawner,
+    /// Random number generator passed to certain functions
+    pub rng: GameRng,
+    /// Seed the current run was started with, given to `seed`
+    pub run_seed: u32,
+    /// Current player score
+    pub score: i32,
+    /// Chain of quick kills, raising the score of each kill
+    pub combo: Combo,
+    /// Enemy bullets that have already grazed a player
+    pub grazed: ComponentStorage<()>,
+    /// Time left for the screen to shake (in ms)
+    pub shake_ms: i64,
+    /// Time left for the game to stay frozen after a hit (in ms)
+    pub hitstop_ms: i64,
+    /// Difficulty of the current run
+    pub difficulty: Difficulty,
+    /// Rules of the current run
+    pub play_mode: PlayMode,
+    /// Settings used when the play mode is practice
+    pub practice: PracticeOptions,
+    /// Bosses killed this run
+    pub bosses_defeated: u32,
+    /// Tunes the spawn and fire rates to how well the player is doing
+    pub adaptive: AdaptiveDifficulty,
+    /// Damage, kills and pickups so far this run
+    pub stats: RunStats,
+    pub screen_width: f32,
+    pub screen_height: f32,
+    /// What happened since the queue was last drained, oldest first. The
+    /// game drains it after every frame to play sounds, and headless runs
+    /// just clear it. It only means anything right after it happened, so
+    /// it isn't saved.
+    #[serde(skip)]
+    pub events: Vec<GameEvent>,
+}
+
+impl World {
+    /// Create an empty world for a window of the given size.
+    pub fn new(screen_width: u32, screen_height: u32) -> World {
+        World {
+            entities: EntityStore::new(),
+            kinds: ComponentStorage::new(),
+            transforms: ComponentStorage::new(),
+            velocities: ComponentStorage::new(),
+            healths: ComponentStorage::new(),
+            weapons: ComponentStorage::new(),
+            hitboxes: ComponentStorage::new(),
+            lifetimes: ComponentStorage::new(),
+            sprites: ComponentStorage::new(),
+            pickups: ComponentStorage::new(),
+            piercings: ComponentStorage::new(),
+            blasts: ComponentStorage::new(),
+            elites: ComponentStorage::new(),
+            pending_spawns: Vec::new(),
+            players: Vec::new(),
+            player_count: 1,
+            spawner: EntitySpawner::new(screen_width, screen_height),
+            rng: GameRng::new(),
+            run_seed: 0,
+            score: 0,
+            combo: Combo::new(),
+            grazed: ComponentStorage::new(),
+            shake_ms: 0,
+            hitstop_ms: 0,
+            difficulty: Difficulty::Normal,
+            play_mode: PlayMode::Classic,
+            practice: PracticeOptions::new(),
+            bosses_defeated: 0,
+            adaptive: AdaptiveDifficulty::new(),
+            stats: RunStats::new(),
+            screen_width: screen_width as f32,
+            screen_height: screen_height as f32,
+            events: Vec::new(),
+        }
+    }
+
+    /// Reseeds the random number generators, so that two worlds given the
+    /// same seed and inputs play out exactly the same.
+    pub fn seed(&mut self, seed: u32) {
+        self.run_seed = seed;
+        let seed = [seed, 0x9E37_79B9, 0x85EB_CA6B, 0xC2B2_AE35];
+        self.rng = GameRng::from_seed(seed);
+        self.spawner.rng = GameRng::from_seed([seed[3], seed[2], seed[1], seed[0]]);
+    }
+
+    /// Clears out the old run and spawns fresh players.
+    pub fn reset(&mut self) {
+        self.entities.clear();
+        self.kinds.clear();
+        self.transforms.clear();
+        self.velocities.clear();
+        self.healths.clear();
+        self.weapons.clear();
+        self.hitboxes.clear();
+        self.lifetimes.clear();
+        self.sprites.clear();
+        self.pickups.clear();
+        self.piercings.clear();
+        self.blasts.clear();
+        self.elites.clear();
+        self.grazed.clear();
+        self.pending_spawns.clear();
+        self.events.clear();
+
+        self.spawner.preset = self.difficulty.preset();
+        self.spawner.endless = self.play_mode.escalates();
+        self.spawner.enemies = self.play_mode.enemies(&self.practice);
+        if self.play_mode == PlayMode::TimeAttack {
+            // Ramp up fully over the length of the run
+            self.spawner.preset.ramp_seconds = TIME_ATTACK_SECONDS;
+        }
+        self.spawner.reset();
+        self.score = 0;
+        self.combo = Combo::new();
+        self.shake_ms = 0;
+        self.hitstop_ms = 0;
+        self.adaptive.reset();
+        self.bosses_defeated = 0;
+        self.stats = RunStats::new();
+
+        self.players.clear();
+        for slot in 0..self.player_count {
+            let mut player = Player::new();
+            let entity = self.spawner.spawn_player(slot, self.player_count);
+            player.entity = Some(self.insert(entity));
+            self.players.push(player);
+        }
+    }
+
+    /// Immediately adds an entity, attaching each of its components.
+    pub fn insert(&mut self, entity: Entity) -> EntityId {
+        let id = self.entities.create();
+        self.kinds.insert(id, entity.entity_type);
+        self.transforms.insert(id, entity.transform);
+        self.lifetimes.insert(id, entity.lifetime);
+        if let Some(velocity) = entity.velocity {
+            self.velocities.insert(id, velocity);
+        }
+        if let Some(health) = entity.health {
+            self.healths.insert(id, health);
+        }
+        if let Some(weapon) = entity.weapon {
+            self.weapons.insert(id, weapon);
+        }
+        if let Some(hitbox) = entity.hitbox {
+            self.hitboxes.insert(id, hitbox);
+        }
+        if let Some(sprite) = entity.sprite {
+            self.sprites.insert(id, sprite);
+        }
+        if let Some(pickup) = entity.pickup {
+            self.pickups.insert(id, pickup);
+        }
+        if let Some(piercing) = entity.piercing {
+            self.piercings.insert(id, piercing);
+        }
+        if let Some(blast) = entity.blast {
+            self.blasts.insert(id, blast);
+        }
+        if let Some(elite) = entity.elite {
+            self.elites.insert(id, elite);
+        }
+        id
+    }
+
+    /// Immediately removes an entity and all of its components.
+    pub fn remove(&mut self, id: EntityId) {
+        if !self.entities.destroy(id) {
+            return;
+        }
+        self.kinds.remove(id);
+        self.transforms.remove(id);
+        self.velocities.remove(id);
+        self.healths.remove(id);
+        self.weapons.remove(id);
+        self.hitboxes.remove(id);
+        self.lifetimes.remove(id);
+        self.sprites.remove(id);
+        self.pickups.remove(id);
+        self.piercings.remove(id);
+        self.blasts.remove(id);
+        self.elites.remove(id);
+        self.grazed.remove(id);
+    }
+
+    /// Queues an entity to be added on the next `flush`.
+    pub fn spawn(&mut self, entity: Entity) {
+        self.pending_spawns.push(entity);
+    }
+
+    /// Queues an entity to be removed on the next `flush`.
+    pub fn despawn(&mut self, id: EntityId) {
+        self.entities.despawn(id);
+    }
+
+    /// Applies all buffered despawns, then all buffered spawns.
+    pub fn flush(&mut self) {
+        for id in self.entities.drain_despawns() {
+            self.remove(id);
+        }
+        let spawns: Vec<Entity> = self.pending_spawns.drain(..).collect();
+        for entity in spawns {
+            self.insert(entity);
+        }
+    }
+
+    /// Returns true if the handle still refers to a live entity.
+    pub fn contains(&self, id: EntityId) -> bool {
+        self.entities.contains(id)
+    }
+
+    /// The type of an entity, or `Empty` if it no longer exists.
+    pub fn entity_type(&self, id: EntityId) -> EntityType {
+        match self.kinds.get(id) {
+            Some(&entity_type) => entity_type,
+            None => EntityType::Empty,
+        }
+    }
+
+    /// Handles of every live entity of the given type.
+    pub fn query(&self, entity_type: EntityType) -> Vec<EntityId> {
+        self.kinds
+            .iter()
+            .filter(|&(_, &kind)| kind == entity_type)
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Returns true while any player's crab is alive. The run is over
+    /// once every player is out.
+    pub fn player_alive(&self) -> bool {
+        self.players.iter().any(|p| p.entity.is_some_and(|id| self.contains(id)))
+    }
+
+    /// Which player an entity is the crab of, if any.
+    pub fn player_slot(&self, id: EntityId) -> Option<usize> {
+        self.players.iter().position(|p| p.entity == Some(id))
+    }
+
+    /// Current hit points of a player, or 0 if they are down.
+    pub fn player_hp(&self, slot: usize) -> i32 {
+        match self.players[slot].entity.and_then(|id| self.healths.get(id)) {
+            Some(health) => health.hp,
+            None => 0,
+        }
+    }
+
+    /// Returns true once the win condition of the play mode has been met.
+    pub fn won(&self, elapsed_ms: u64) -> bool {
+        match self.play_mode {
+            PlayMode::Classic | PlayMode::Practice => elapsed_ms / 1000 > self.spawner.preset.ramp_seconds + 5,
+            PlayMode::Endless => false,
+            PlayMode::BossRush => self.bosses_defeated >= BOSS_RUSH_BOSSES,
+            PlayMode::TimeAttack => elapsed_ms / 1000 >= TIME_ATTACK_SECONDS,
+        }
+    }
+
+    /// How far to offset the screen to shake it after a hit. The shake
+    /// fades out over its duration.
+    pub fn screen_shake(&self, elapsed_ms: u64) -> (f32, f32) {
+        if self.shake_ms <= 0 {
+            return (0.0, 0.0);
+        }
+        let feedback = self.difficulty.hit_feedback();
+        let magnitude = feedback.shake_intensity * self.shake_ms as f32 / feedback.shake_ms as f32;
+        let t = elapsed_ms as f32;
+        ((t * 0.09).sin() * magnitude, (t * 0.13).cos() * magnitude)
+    }
+
+    /// Reports something that happened during the update.
+    pub fn emit(&mut self, event: GameEvent) {
+        self.events.push(event);
+    }
+
+    /// Advances the simulation by `delta_ms` milliseconds.
+    /// `inputs` holds the controls of each player in turn.
+    pub fn update(&mut self, inputs: &[Input], elapsed_ms: u64, delta_ms: u64) {
+        self.shake_ms -= delta_ms as i64;
+        if self.shake_ms < 0 {
+            self.shake_ms = 0;
+        }
+        for player in self.players.iter_mut() {
+            player.graze_flash_ms -= delta_ms as i64;
+            if player.graze_flash_ms < 0 {
+                player.graze_flash_ms = 0;
+            }
+        }
+
+        // Everything stays frozen for a moment after the player is hit
+        if self.hitstop_ms > 0 {
+            self.hitstop_ms -= delta_ms as i64;
+            return;
+        }
+
+        let first_event = self.events.len();
+        for player in self.players.iter_mut() {
+            player.update(delta_ms);
+        }
+        self.combo.update(delta_ms);
+        self.adaptive.update(delta_ms);
+        self.spawner.adjustment = self.adaptive.level();
+
+        systems::revive_system(self, delta_ms);
+        systems::bomb_system(self, inputs);
+        systems::collision_system(self);
+        systems::graze_system(self);
+
+        if let Some(e) = self.spawner.update(elapsed_ms, delta_ms) {
+            if e.entity_type == EntityType::Boss {
+                self.emit(GameEvent::BossArrived);
+            }
+            self.spawn(e);
+        }
+
+        systems::movement_system(self, inputs, delta_ms);
+        systems::firing_system(self, inputs, delta_ms);
+        systems::blast_system(self, delta_ms);
+        self.flush();
+
+        let deaths = systems::lifetime_system(self, delta_ms);
+        let events = self.events[first_event..].to_vec();
+        systems::scoring_system(self, &events);
+        systems::death_effects_system(self, &deaths);
+        self.flush();
+
+        for event in self.events[first_event..].iter() {
+            self.stats.record(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::netplay::FRAME_MS;
+
+    /// Plays a seeded run with the same input every frame, until everyone
+    /// is out or the time is up. Returns how long it lasted and the most
+    /// entities alive at once.
+    fn play(seed: u32, input: Input, max_ms: u64) -> (u64, usize) {
+        let mut world = World::new(1024, 768);
+        world.seed(seed);
+        world.reset();
+        let mut elapsed_ms = 0;
+        let mut most = 0;
+        while elapsed_ms < max_ms && world.player_alive() {
+            elapsed_ms += FRAME_MS;
+            world.update(&[input], elapsed_ms, FRAME_MS);
+            world.events.clear();
+            most = most.max(world.entities.ids().len());
+        }
+        (elapsed_ms, most)
+    }
+
+    #[test]
+    fn idle_players_eventually_die() {
+        for seed in 0..3 {
+            let (survived_ms, _) = play(seed, Input::default(), 30 * 60 * 1000);
+            assert!(survived_ms < 30 * 60 * 1000, "seed {} survived the whole run standing still", seed);
+        }
+    }
+
+    #[test]
+    fn entities_do_not_pile_up() {
+        // Shooting the whole time keeps plenty of bullets and explosions around
+        let input = Input { shoot: true, left: true, ..Input::default() };
+        let (_, most) = play(7, input, 10 * 60 * 1000);
+        assert!(most < 1_000, "{} entities alive at once", most);
+    }
+}
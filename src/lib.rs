@@ -0,0 +1,19 @@
+// Copyright © 2018
+// "River Bartz"<bpg@pdx.edu>
+// "Daniel Dupriest"<kououken@gmail.com>
+// "Brandon Goldbeck"<rbartz@pdx.edu>
+// This program is licensed under the "MIT License". Please see the file
+// LICENSE in the source distribution of this software for license terms.
+
+//! The game itself, shared by the `ferris` game and the `ferris-sim`
+//! balance simulator.
+
+extern crate ggez;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+#[cfg(test)]
+extern crate proptest;
+
+pub mod game;
@@ -5,8 +5,10 @@
 // This program is licensed under the "MIT License". Please see the file
 // LICENSE in the source distribution of this software for license terms.
 
+extern crate ferris;
 extern crate ggez;
 
+use ferris::game;
 use ggez::conf;
 use ggez::Context;
 use ggez::event;
@@ -15,15 +17,13 @@ use std::env;
 use std::path;
 use std::fs::File;
 
-mod game;
-
 /// Our main function, which does three things:
 ///
 /// * First, create a new `ggez::conf::Conf`
-/// object which contains configuration info on things such
-/// as screen resolution and window title.
+///   object which contains configuration info on things such
+///   as screen resolution and window title.
 /// * Second, create a `ggez::game::Game` object which will
-/// do the work of creating our MainState and running our game.
+///   do the work of creating our MainState and running our game.
 /// * Then, just call `game.run()` which runs the `Game` mainloop.
 pub fn main() {
     // Load settings from conf.toml